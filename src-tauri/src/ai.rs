@@ -1,5 +1,79 @@
+use crate::db::AiProvider;
+use crate::error::{redact, redact_all, AppError, AppResult};
+use crate::sse::{LineDecoder, SseDecoder};
+use crate::stream_parse::{parse_claude_event, parse_ollama_line, parse_openai_line, Delta};
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Emitter};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, EventTarget};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 未配置时的默认连接超时（秒）
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+/// 未配置时的默认读取超时（秒）：两次收到数据之间的最长空闲时间，而非总时长
+pub const DEFAULT_READ_TIMEOUT_SECS: u64 = 60;
+/// 未配置时每个供应商同时进行的请求数
+pub const DEFAULT_MAX_CONCURRENCY: u32 = 2;
+/// Azure OpenAI 未配置 api_version 时使用的接口版本
+pub const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+
+/// 常用服务的供应商预设，设置界面据此一键填好地址和模型，用户只需填写 API Key
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderPreset {
+    pub kind: &'static str,
+    pub label: &'static str,
+    pub endpoint: &'static str,
+    /// 推荐的模型，第一个为默认
+    pub models: &'static [&'static str],
+    pub needs_key: bool,
+}
+
+/// 保存在代码中而非数据库，地址和推荐模型随版本更新
+pub const PROVIDER_PRESETS: &[ProviderPreset] = &[
+    ProviderPreset {
+        kind: "openai",
+        label: "OpenAI",
+        endpoint: "https://api.openai.com/v1",
+        models: &["gpt-4o", "gpt-4o-mini", "gpt-4-turbo", "gpt-3.5-turbo"],
+        needs_key: true,
+    },
+    ProviderPreset {
+        kind: "claude",
+        label: "Claude (Anthropic)",
+        endpoint: "https://api.anthropic.com/v1",
+        models: &["claude-sonnet-4-20250514", "claude-haiku-4-20250414", "claude-opus-4-20250514"],
+        needs_key: true,
+    },
+    ProviderPreset {
+        kind: "kimi",
+        label: "Kimi (月之暗面)",
+        endpoint: "https://api.moonshot.cn/v1",
+        models: &["moonshot-v1-128k", "moonshot-v1-32k", "moonshot-v1-8k"],
+        needs_key: true,
+    },
+    ProviderPreset {
+        kind: "minimax",
+        label: "MiniMax (海螺)",
+        endpoint: "https://api.minimax.chat/v1",
+        models: &["MiniMax-Text-01", "abab6.5s-chat", "abab5.5-chat"],
+        needs_key: true,
+    },
+    ProviderPreset {
+        kind: "azure",
+        label: "Azure OpenAI",
+        endpoint: "https://YOUR-RESOURCE.openai.azure.com",
+        models: &["gpt-4o", "gpt-4o-mini"],
+        needs_key: true,
+    },
+    ProviderPreset {
+        kind: "ollama",
+        label: "Ollama (本地)",
+        endpoint: "http://localhost:11434",
+        models: &["llama3", "mistral", "codellama", "qwen2"],
+        needs_key: false,
+    },
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -7,219 +81,709 @@ pub struct ChatMessage {
     pub content: String,
 }
 
+/// 流式输出的种类：回答正文，或模型的推理/思考过程
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkKind {
+    #[default]
+    Content,
+    Reasoning,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamChunk {
     pub content: String,
+    /// 推理片段单独标记，前端可折叠显示；最终结果只包含正文
+    #[serde(default)]
+    pub kind: ChunkKind,
     pub done: bool,
+    /// 每次生成从 0 开始递增，前端据此发现丢失或乱序的事件
+    pub seq: u64,
+    /// 调用方传入的请求 ID，原样带回以便监听方过滤
+    pub request_id: Option<String>,
 }
 
-pub async fn stream_generate(
+/// 只向发起请求的窗口发送 ai-stream 事件，并维护序号
+pub struct StreamEmitter {
     app: AppHandle,
-    kind: &str,
-    endpoint: &str,
-    model: &str,
-    api_key: &str,
-    prompt: &str,
-) -> Result<String, String> {
-    match kind {
-        "openai" | "kimi" | "minimax" => stream_openai(app, endpoint, model, api_key, prompt).await,
-        "claude" => stream_claude(app, endpoint, model, api_key, prompt).await,
-        "ollama" => stream_ollama(app, endpoint, model, prompt).await,
-        _ => Err(format!("Unknown provider kind: {}", kind)),
-    }
+    window: String,
+    request_id: Option<String>,
+    seq: u64,
+    /// 额外接收每段输出的回调，例如内联生成时把文本送入目标应用
+    sink: Option<Box<dyn FnMut(&str) + Send>>,
 }
 
-async fn stream_openai(
-    app: AppHandle,
-    endpoint: &str,
-    model: &str,
-    api_key: &str,
-    prompt: &str,
-) -> Result<String, String> {
-    let url = format!("{}/chat/completions", endpoint.trim_end_matches('/'));
-    let body = serde_json::json!({
-        "model": model,
-        "messages": [{"role": "user", "content": prompt}],
-        "stream": true
-    });
+impl StreamEmitter {
+    pub fn new(app: AppHandle, window: &str, request_id: Option<String>) -> Self {
+        Self { app, window: window.to_string(), request_id, seq: 0, sink: None }
+    }
 
-    let client = reqwest::Client::new();
-    let response = client
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+    /// 每段输出在发送事件的同时交给 sink；emitter 释放时 sink 随之释放
+    pub fn with_sink(mut self, sink: impl FnMut(&str) + Send + 'static) -> Self {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    fn chunk(&mut self, content: &str) {
+        if let Some(sink) = self.sink.as_mut() {
+            sink(content);
+        }
+        self.emit(content.to_string(), ChunkKind::Content, false);
+    }
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let text = response.text().await.unwrap_or_default();
-        return Err(format!("API error {}: {}", status, text));
+    /// 推理片段只发送给前端，不交给 sink
+    fn reasoning(&mut self, content: &str) {
+        self.emit(content.to_string(), ChunkKind::Reasoning, false);
     }
 
-    let mut full_content = String::new();
-    let mut stream = response.bytes_stream();
-    use futures_util::StreamExt;
-    let mut buffer = String::new();
+    fn done(&mut self) {
+        self.emit(String::new(), ChunkKind::Content, true);
+    }
 
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| format!("Stream error: {}", e))?;
-        buffer.push_str(&String::from_utf8_lossy(&chunk));
+    fn emit(&mut self, content: String, kind: ChunkKind, done: bool) {
+        let chunk = StreamChunk { content, kind, done, seq: self.seq, request_id: self.request_id.clone() };
+        let _ = self.app.emit_to(EventTarget::webview_window(&self.window), "ai-stream", chunk);
+        self.seq += 1;
+    }
 
-        while let Some(line_end) = buffer.find('\n') {
-            let line = buffer[..line_end].trim().to_string();
-            buffer = buffer[line_end + 1..].to_string();
+    /// 通知前端请求正在等待供应商的并发名额
+    pub fn queued(&self, provider_id: &str) {
+        let event = QueuedEvent { request_id: self.request_id.clone(), provider_id: provider_id.to_string() };
+        let _ = self.app.emit_to(EventTarget::webview_window(&self.window), "ai-queued", event);
+    }
+}
 
-            if line.starts_with("data: ") {
-                let data = &line[6..];
-                if data == "[DONE]" {
-                    let _ = app.emit("ai-stream", StreamChunk { content: String::new(), done: true });
-                    return Ok(full_content);
-                }
-                if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(data) {
-                    if let Some(delta) = parsed["choices"][0]["delta"]["content"].as_str() {
-                        full_content.push_str(delta);
-                        let _ = app.emit("ai-stream", StreamChunk { content: delta.to_string(), done: false });
-                    }
-                }
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedEvent {
+    pub request_id: Option<String>,
+    pub provider_id: String,
+}
+
+/// 按供应商限制同时进行的请求数，超出的请求排队等待，避免连续触发时遭遇 429
+#[derive(Default)]
+pub struct ProviderLimiter {
+    semaphores: Mutex<HashMap<String, (u32, Arc<Semaphore>)>>,
+}
+
+impl ProviderLimiter {
+    fn semaphore(&self, provider: &AiProvider) -> Arc<Semaphore> {
+        let limit = provider.max_concurrency.unwrap_or(DEFAULT_MAX_CONCURRENCY).max(1);
+        let mut semaphores = self.semaphores.lock().unwrap();
+        let entry = semaphores
+            .entry(provider.id.clone())
+            .or_insert_with(|| (limit, Arc::new(Semaphore::new(limit as usize))));
+        // 并发数被修改后换用新的信号量，进行中的请求仍在旧信号量上释放名额
+        if entry.0 != limit {
+            *entry = (limit, Arc::new(Semaphore::new(limit as usize)));
+        }
+        entry.1.clone()
+    }
+
+    /// 获取一个并发名额，名额用完时先调用 on_queued 再等待；名额在 permit 释放时归还
+    pub async fn acquire(&self, provider: &AiProvider, on_queued: impl FnOnce()) -> OwnedSemaphorePermit {
+        let semaphore = self.semaphore(provider);
+        match semaphore.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                on_queued();
+                semaphore
+                    .acquire_owned()
+                    .await
+                    .expect("provider semaphores are never closed")
             }
         }
     }
+}
 
-    let _ = app.emit("ai-stream", StreamChunk { content: String::new(), done: true });
-    Ok(full_content)
+/// 供应商返回的 token 用量，未提供时为 None
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
 }
 
-async fn stream_claude(
-    app: AppHandle,
-    endpoint: &str,
-    model: &str,
-    api_key: &str,
+/// 一次生成的完整结果
+#[derive(Debug, Clone)]
+pub struct Generation {
+    pub content: String,
+    pub usage: TokenUsage,
+}
+
+/// 随提示词一起发送给多模态模型的图片
+#[derive(Debug, Clone)]
+pub struct ImageInput {
+    pub media_type: String,
+    /// base64 编码的图片数据（不含 data: 前缀）
+    pub data: String,
+}
+
+/// OpenAI 格式的用户消息内容：没有图片时为纯文本，否则为 text + image_url 数组
+fn openai_user_content(prompt: &str, images: &[ImageInput]) -> serde_json::Value {
+    if images.is_empty() {
+        return serde_json::json!(prompt);
+    }
+    let mut parts = vec![serde_json::json!({"type": "text", "text": prompt})];
+    parts.extend(images.iter().map(|img| {
+        serde_json::json!({
+            "type": "image_url",
+            "image_url": {"url": format!("data:{};base64,{}", img.media_type, img.data)}
+        })
+    }));
+    serde_json::Value::Array(parts)
+}
+
+/// Claude 格式的用户消息内容：图片块在前，文本在后
+fn claude_user_content(prompt: &str, images: &[ImageInput]) -> serde_json::Value {
+    if images.is_empty() {
+        return serde_json::json!(prompt);
+    }
+    let mut parts: Vec<serde_json::Value> = images
+        .iter()
+        .map(|img| {
+            serde_json::json!({
+                "type": "image",
+                "source": {"type": "base64", "media_type": img.media_type, "data": img.data}
+            })
+        })
+        .collect();
+    parts.push(serde_json::json!({"type": "text", "text": prompt}));
+    serde_json::Value::Array(parts)
+}
+
+pub async fn stream_generate(
+    emitter: StreamEmitter,
+    provider: &AiProvider,
     prompt: &str,
-) -> Result<String, String> {
-    let url = format!("{}/messages", endpoint.trim_end_matches('/'));
-    let body = serde_json::json!({
-        "model": model,
-        "max_tokens": 4096,
-        "messages": [{"role": "user", "content": prompt}],
-        "stream": true
+    images: &[ImageInput],
+) -> AppResult<Generation> {
+    match provider.kind.as_str() {
+        "openai" | "kimi" | "minimax" | "azure" => stream_openai(emitter, provider, prompt, images).await,
+        "claude" => stream_claude(emitter, provider, prompt, images).await,
+        "ollama" => stream_ollama(emitter, provider, prompt, images).await,
+        kind => Err(AppError::InvalidInput(format!("Unknown provider kind: {}", kind))),
+    }
+}
+
+/// 按供应商配置构建 HTTP 客户端。read_timeout 在每次读取后重置，
+/// 因此长时间的流式生成只要持续有数据就不会被中断
+fn build_client(provider: &AiProvider) -> AppResult<reqwest::Client> {
+    let connect_timeout = provider.connect_timeout_secs.unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS);
+    let read_timeout = provider.read_timeout_secs.unwrap_or(DEFAULT_READ_TIMEOUT_SECS);
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(connect_timeout))
+        .read_timeout(Duration::from_secs(read_timeout));
+    if let Some(proxy_url) = provider.proxy_url.as_deref().filter(|p| !p.trim().is_empty()) {
+        builder = builder.proxy(parse_proxy(proxy_url)?);
+    }
+    builder
+        .build()
+        .map_err(|e| AppError::Other(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// 解析代理地址，支持 http/https/socks5/socks5h，认证信息写在 URL 中（user:pass@host）
+fn parse_proxy(proxy_url: &str) -> AppResult<reqwest::Proxy> {
+    let proxy_url = proxy_url.trim();
+    let scheme = proxy_url.split("://").next().unwrap_or("").to_ascii_lowercase();
+    if !proxy_url.contains("://") || !matches!(scheme.as_str(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(AppError::InvalidInput(format!(
+            "Unsupported proxy URL \"{}\": expected http://, https://, socks5:// or socks5h://",
+            proxy_for_display(proxy_url)
+        )));
+    }
+    reqwest::Proxy::all(proxy_url)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid proxy URL: {}", scrub_url(e))))
+}
+
+/// 代理地址中的密码在错误信息里打码显示
+fn proxy_for_display(proxy_url: &str) -> String {
+    match reqwest::Url::parse(proxy_url) {
+        Ok(mut url) => {
+            if let Some(password) = url.password().map(redact) {
+                let _ = url.set_password(Some(&password));
+            }
+            url.to_string()
+        }
+        Err(_) => proxy_url.to_string(),
+    }
+}
+
+pub fn validate_proxy_url(proxy_url: &str) -> AppResult<()> {
+    if proxy_url.trim().is_empty() {
+        return Ok(());
+    }
+    parse_proxy(proxy_url).map(|_| ())
+}
+
+/// 校验并规范化供应商地址：必须是带主机名的 http/https URL，去掉首尾空白和末尾的 "/"。
+/// 请求路径（/chat/completions 等）由程序追加，地址里已包含时只记录警告，避免路径重复
+pub fn normalize_endpoint(endpoint: &str) -> AppResult<String> {
+    let endpoint = endpoint.trim().trim_end_matches('/');
+    if endpoint.is_empty() {
+        return Err(AppError::InvalidInput("Endpoint URL is required".to_string()));
+    }
+    let url = reqwest::Url::parse(endpoint).map_err(|e| {
+        AppError::InvalidInput(format!(
+            "Invalid endpoint URL \"{}\": {} (expected e.g. https://api.openai.com/v1)",
+            endpoint, e
+        ))
+    })?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(AppError::InvalidInput(format!(
+            "Unsupported endpoint URL \"{}\": expected http:// or https://",
+            endpoint
+        )));
+    }
+    if url.host_str().unwrap_or("").is_empty() {
+        return Err(AppError::InvalidInput(format!("Endpoint URL \"{}\" has no host", endpoint)));
+    }
+    if url.path().ends_with("/chat/completions") {
+        log::warn!(
+            "Endpoint \"{}\" already ends with /chat/completions; the path is appended automatically",
+            endpoint
+        );
+    }
+    Ok(endpoint.to_string())
+}
+
+/// 拼接供应商 URL 并按类型附加认证头
+fn provider_request(
+    client: &reqwest::Client,
+    provider: &AiProvider,
+    method: reqwest::Method,
+    path: &str,
+) -> reqwest::RequestBuilder {
+    let url = format!("{}{}", provider.endpoint.trim_end_matches('/'), path);
+    let builder = client.request(method, url);
+    let builder = match provider.kind.as_str() {
+        "claude" => builder
+            .header("x-api-key", &provider.api_key)
+            .header("anthropic-version", "2023-06-01"),
+        "ollama" => builder,
+        "azure" => builder.header("api-key", &provider.api_key),
+        _ => builder.header("Authorization", format!("Bearer {}", provider.api_key)),
+    };
+    // 自定义请求头最后附加，网关（OpenRouter、Azure 等）需要的头可覆盖默认值
+    provider
+        .extra_headers
+        .iter()
+        .flatten()
+        .fold(builder, |builder, (name, value)| builder.header(name.as_str(), value.as_str()))
+}
+
+/// 生成接口的路径：配置了 path_template 时使用它（{model} 替换为模型名）；
+/// Azure OpenAI 以模型名作为部署名，路径为 /openai/deployments/{部署名}/chat/completions?api-version=...
+fn generation_path(provider: &AiProvider, default: &str) -> String {
+    match provider.path_template.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+        Some(template) => template.replace("{model}", &provider.model),
+        None if provider.kind == "azure" => format!(
+            "/openai/deployments/{}{}?api-version={}",
+            provider.model.trim(),
+            default,
+            azure_api_version(provider)
+        ),
+        None => default.to_string(),
+    }
+}
+
+fn azure_api_version(provider: &AiProvider) -> &str {
+    provider
+        .api_version
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .unwrap_or(DEFAULT_AZURE_API_VERSION)
+}
+
+/// 校验自定义请求头、路径模板、并发数和 Azure 部署设置，避免保存后每次请求都失败
+pub fn validate_request_options(provider: &AiProvider) -> AppResult<()> {
+    for (name, value) in provider.extra_headers.iter().flatten() {
+        reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| AppError::InvalidInput(format!("Invalid header name \"{}\"", name)))?;
+        reqwest::header::HeaderValue::from_str(value)
+            .map_err(|_| AppError::InvalidInput(format!("Invalid value for header \"{}\"", name)))?;
+    }
+    if provider.max_concurrency == Some(0) {
+        return Err(AppError::InvalidInput("Max concurrency must be at least 1".to_string()));
+    }
+    if let Some(template) = provider.path_template.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+        if !template.starts_with('/') {
+            return Err(AppError::InvalidInput("Path template must start with \"/\"".to_string()));
+        }
+    }
+    if provider.kind == "azure" {
+        if provider.model.trim().is_empty() {
+            return Err(AppError::InvalidInput("Azure OpenAI needs the deployment name as the model".to_string()));
+        }
+        let version = azure_api_version(provider);
+        if !version.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(AppError::InvalidInput(format!("Invalid api-version \"{}\"", version)));
+        }
+    }
+    Ok(())
+}
+
+/// 供应商配置中的敏感值：API Key 和名称像认证信息的自定义请求头（Azure 的 api-key 等）
+pub(crate) fn provider_secrets(provider: &AiProvider) -> Vec<&str> {
+    let headers = provider.extra_headers.iter().flatten().filter(|(name, _)| {
+        let name = name.to_ascii_lowercase();
+        ["key", "auth", "token", "secret"].iter().any(|word| name.contains(word))
     });
+    std::iter::once(provider.api_key.as_str())
+        .chain(headers.map(|(_, value)| value.as_str()))
+        .collect()
+}
 
-    let client = reqwest::Client::new();
-    let response = client
-        .post(&url)
-        .header("x-api-key", api_key)
-        .header("anthropic-version", "2023-06-01")
+/// 非 2xx 响应转换为包含状态码和响应体的错误。有的服务会在响应体中回显请求的密钥，
+/// 因此先把 secrets 打码，避免随错误信息进入日志或界面
+async fn ensure_success(response: reqwest::Response, secrets: &[&str]) -> AppResult<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status().as_u16();
+    let body = redact_all(&response.text().await.unwrap_or_default(), secrets);
+    Err(AppError::Api { status, body })
+}
+
+/// reqwest 的错误信息会附带请求 URL，去掉其中可能带有密钥的查询参数（?key=...）和认证信息
+fn scrub_url(mut e: reqwest::Error) -> reqwest::Error {
+    if let Some(url) = e.url_mut() {
+        url.set_query(None);
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+    }
+    e
+}
+
+fn request_error(e: reqwest::Error) -> AppError {
+    let e = scrub_url(e);
+    if e.is_timeout() {
+        AppError::Timeout(format!("Request timed out: {}", e))
+    } else {
+        AppError::Network(format!("Request failed: {}", e))
+    }
+}
+
+fn stream_error(e: reqwest::Error) -> AppError {
+    let e = scrub_url(e);
+    if e.is_timeout() {
+        AppError::Timeout(format!("Stream timed out waiting for data: {}", e))
+    } else {
+        AppError::Network(format!("Stream error: {}", e))
+    }
+}
+
+/// 发送一个最小的非流式请求，验证 endpoint / key / model 配置是否可用
+pub async fn test_provider(provider: &AiProvider) -> AppResult<String> {
+    let client = build_client(provider)?;
+    match provider.kind.as_str() {
+        "openai" | "kimi" | "minimax" | "azure" => {
+            let body = serde_json::json!({
+                "model": provider.model,
+                "messages": [{"role": "user", "content": "ping"}],
+                "max_tokens": 1,
+                "stream": false
+            });
+            let path = generation_path(provider, "/chat/completions");
+            let response = provider_request(&client, provider, reqwest::Method::POST, &path)
+                .header("Content-Type", "application/json")
+                .json(&body)
+                .send()
+                .await
+                .map_err(request_error)?;
+            ensure_success(response, &provider_secrets(provider)).await?;
+        }
+        "claude" => {
+            let body = serde_json::json!({
+                "model": provider.model,
+                "max_tokens": 1,
+                "messages": [{"role": "user", "content": "ping"}]
+            });
+            let path = generation_path(provider, "/messages");
+            let response = provider_request(&client, provider, reqwest::Method::POST, &path)
+                .header("Content-Type", "application/json")
+                .json(&body)
+                .send()
+                .await
+                .map_err(request_error)?;
+            ensure_success(response, &provider_secrets(provider)).await?;
+        }
+        "ollama" => {
+            let request = provider_request(&client, provider, reqwest::Method::GET, "/api/tags");
+            let models = ollama_models(request, &provider.endpoint).await?;
+            let found = models
+                .iter()
+                .any(|name| *name == provider.model || *name == format!("{}:latest", provider.model));
+            if !found {
+                return Err(AppError::NotFound(format!("Model \"{}\" on Ollama server", provider.model)));
+            }
+        }
+        kind => return Err(AppError::InvalidInput(format!("Unknown provider kind: {}", kind))),
+    }
+    Ok(format!("Connected to {} ({})", provider.name, provider.model))
+}
+
+/// 列出 Ollama 服务器上已安装的模型，供设置界面选择
+pub async fn list_ollama_models(endpoint: &str) -> AppResult<Vec<String>> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS))
+        .read_timeout(Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| AppError::Other(format!("Failed to build HTTP client: {}", e)))?;
+    let request = client.get(format!("{}/api/tags", endpoint.trim().trim_end_matches('/')));
+    ollama_models(request, endpoint).await
+}
+
+/// 请求 /api/tags 并取出模型名；连接失败时提示检查 Ollama 是否在运行
+async fn ollama_models(request: reqwest::RequestBuilder, endpoint: &str) -> AppResult<Vec<String>> {
+    let response = request.send().await.map_err(|e| {
+        if e.is_connect() {
+            AppError::Network(format!(
+                "Cannot connect to Ollama at {}. Is Ollama running?",
+                endpoint
+            ))
+        } else {
+            request_error(e)
+        }
+    })?;
+    let tags: serde_json::Value = ensure_success(response, &[])
+        .await?
+        .json()
+        .await
+        .map_err(|e| AppError::InvalidResponse(format!("Ollama: {}", scrub_url(e))))?;
+    Ok(tags["models"]
+        .as_array()
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|m| m["name"].as_str())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// 非流式生成：一次性返回完整结果，不发送 ai-stream 事件
+pub async fn generate(provider: &AiProvider, prompt: &str) -> AppResult<Generation> {
+    let client = build_client(provider)?;
+    let (path, body) = match provider.kind.as_str() {
+        "openai" | "kimi" | "minimax" | "azure" => (
+            "/chat/completions",
+            serde_json::json!({
+                "model": provider.model,
+                "messages": [{"role": "user", "content": prompt}],
+                "stream": false
+            }),
+        ),
+        "claude" => (
+            "/messages",
+            serde_json::json!({
+                "model": provider.model,
+                "max_tokens": 4096,
+                "messages": [{"role": "user", "content": prompt}],
+                "stream": false
+            }),
+        ),
+        "ollama" => (
+            "/api/generate",
+            serde_json::json!({
+                "model": provider.model,
+                "prompt": prompt,
+                "stream": false
+            }),
+        ),
+        kind => return Err(AppError::InvalidInput(format!("Unknown provider kind: {}", kind))),
+    };
+
+    let path = generation_path(provider, path);
+    let response = provider_request(&client, provider, reqwest::Method::POST, &path)
         .header("Content-Type", "application/json")
         .json(&body)
         .send()
         .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+        .map_err(request_error)?;
+    let parsed: serde_json::Value = ensure_success(response, &provider_secrets(provider))
+        .await?
+        .json()
+        .await
+        .map_err(|e| AppError::InvalidResponse(scrub_url(e).to_string()))?;
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let text = response.text().await.unwrap_or_default();
-        return Err(format!("API error {}: {}", status, text));
+    let (content, usage) = match provider.kind.as_str() {
+        "claude" => (
+            parsed["content"]
+                .as_array()
+                .map(|blocks| {
+                    blocks
+                        .iter()
+                        .filter(|b| b["type"] == "text")
+                        .filter_map(|b| b["text"].as_str())
+                        .collect::<String>()
+                })
+                .unwrap_or_default(),
+            TokenUsage {
+                prompt_tokens: parsed["usage"]["input_tokens"].as_i64(),
+                completion_tokens: parsed["usage"]["output_tokens"].as_i64(),
+            },
+        ),
+        "ollama" => (
+            parsed["response"].as_str().unwrap_or_default().to_string(),
+            TokenUsage {
+                prompt_tokens: parsed["prompt_eval_count"].as_i64(),
+                completion_tokens: parsed["eval_count"].as_i64(),
+            },
+        ),
+        _ => (
+            parsed["choices"][0]["message"]["content"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            TokenUsage {
+                prompt_tokens: parsed["usage"]["prompt_tokens"].as_i64(),
+                completion_tokens: parsed["usage"]["completion_tokens"].as_i64(),
+            },
+        ),
+    };
+    Ok(Generation { content, usage })
+}
+
+/// 流式响应的分帧方式：SSE 按事件取 data，Ollama 按行
+enum FrameDecoder {
+    Sse(SseDecoder),
+    Lines(LineDecoder),
+}
+
+impl FrameDecoder {
+    /// 追加一块数据并取出已完整的帧；chunk 为 None 表示流已结束，取出剩余内容
+    fn frames(&mut self, chunk: Option<&[u8]>) -> Vec<String> {
+        match (self, chunk) {
+            (FrameDecoder::Sse(decoder), Some(chunk)) => decoder.push(chunk).into_iter().map(|e| e.data).collect(),
+            (FrameDecoder::Sse(decoder), None) => decoder.finish().map(|e| e.data).into_iter().collect(),
+            (FrameDecoder::Lines(decoder), Some(chunk)) => decoder.push(chunk),
+            (FrameDecoder::Lines(decoder), None) => decoder.finish().into_iter().collect(),
+        }
     }
+}
 
-    let mut full_content = String::new();
-    let mut stream = response.bytes_stream();
+/// 读取流式响应：分帧后交给 parse，正文和推理片段发送给前端，正文累积为最终结果。
+/// 没有收到结束标记时以连接关闭为结束
+async fn read_stream(
+    response: reqwest::Response,
+    mut emitter: StreamEmitter,
+    mut decoder: FrameDecoder,
+    parse: fn(&str) -> Vec<Delta>,
+) -> AppResult<Generation> {
     use futures_util::StreamExt;
-    let mut buffer = String::new();
-
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| format!("Stream error: {}", e))?;
-        buffer.push_str(&String::from_utf8_lossy(&chunk));
-
-        while let Some(line_end) = buffer.find('\n') {
-            let line = buffer[..line_end].trim().to_string();
-            buffer = buffer[line_end + 1..].to_string();
-
-            if line.starts_with("data: ") {
-                let data = &line[6..];
-                if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(data) {
-                    let event_type = parsed["type"].as_str().unwrap_or("");
-                    match event_type {
-                        "content_block_delta" => {
-                            if let Some(text) = parsed["delta"]["text"].as_str() {
-                                full_content.push_str(text);
-                                let _ = app.emit("ai-stream", StreamChunk { content: text.to_string(), done: false });
-                            }
-                        }
-                        "message_stop" => {
-                            let _ = app.emit("ai-stream", StreamChunk { content: String::new(), done: true });
-                            return Ok(full_content);
-                        }
-                        _ => {}
+    let mut stream = response.bytes_stream();
+    let mut generation = Generation { content: String::new(), usage: TokenUsage::default() };
+
+    loop {
+        let chunk = stream.next().await.transpose().map_err(stream_error)?;
+        for delta in decoder.frames(chunk.as_deref()).iter().flat_map(|frame| parse(frame)) {
+            match delta {
+                Delta::Content(text) => {
+                    generation.content.push_str(&text);
+                    emitter.chunk(&text);
+                }
+                Delta::Reasoning(text) => emitter.reasoning(&text),
+                Delta::Usage { prompt_tokens, completion_tokens } => {
+                    if prompt_tokens.is_some() {
+                        generation.usage.prompt_tokens = prompt_tokens;
+                    }
+                    if completion_tokens.is_some() {
+                        generation.usage.completion_tokens = completion_tokens;
                     }
                 }
+                Delta::Done => {
+                    emitter.done();
+                    return Ok(generation);
+                }
             }
         }
+        if chunk.is_none() {
+            break;
+        }
     }
 
-    let _ = app.emit("ai-stream", StreamChunk { content: String::new(), done: true });
-    Ok(full_content)
+    emitter.done();
+    Ok(generation)
 }
 
-async fn stream_ollama(
-    app: AppHandle,
-    endpoint: &str,
-    model: &str,
+async fn stream_openai(
+    emitter: StreamEmitter,
+    provider: &AiProvider,
     prompt: &str,
-) -> Result<String, String> {
-    let url = format!("{}/api/generate", endpoint.trim_end_matches('/'));
+    images: &[ImageInput],
+) -> AppResult<Generation> {
     let body = serde_json::json!({
-        "model": model,
-        "prompt": prompt,
-        "stream": true
+        "model": provider.model,
+        "messages": [{"role": "user", "content": openai_user_content(prompt, images)}],
+        "stream": true,
+        "stream_options": {"include_usage": true}
     });
 
-    let client = reqwest::Client::new();
-    let response = client
-        .post(&url)
+    let client = build_client(provider)?;
+    let path = generation_path(provider, "/chat/completions");
+    let response = provider_request(&client, provider, reqwest::Method::POST, &path)
         .header("Content-Type", "application/json")
         .json(&body)
         .send()
         .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+        .map_err(request_error)?;
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let text = response.text().await.unwrap_or_default();
-        return Err(format!("API error {}: {}", status, text));
-    }
+    let response = ensure_success(response, &provider_secrets(provider)).await?;
+    read_stream(response, emitter, FrameDecoder::Sse(SseDecoder::default()), parse_openai_line).await
+}
 
-    let mut full_content = String::new();
-    let mut stream = response.bytes_stream();
-    use futures_util::StreamExt;
-    let mut buffer = String::new();
+async fn stream_claude(
+    emitter: StreamEmitter,
+    provider: &AiProvider,
+    prompt: &str,
+    images: &[ImageInput],
+) -> AppResult<Generation> {
+    let body = serde_json::json!({
+        "model": provider.model,
+        "max_tokens": 4096,
+        "messages": [{"role": "user", "content": claude_user_content(prompt, images)}],
+        "stream": true
+    });
 
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| format!("Stream error: {}", e))?;
-        buffer.push_str(&String::from_utf8_lossy(&chunk));
+    let client = build_client(provider)?;
+    let path = generation_path(provider, "/messages");
+    let response = provider_request(&client, provider, reqwest::Method::POST, &path)
+        .header("Content-Type", "application/json")
+        .json(&body)
+        .send()
+        .await
+        .map_err(request_error)?;
 
-        while let Some(line_end) = buffer.find('\n') {
-            let line = buffer[..line_end].trim().to_string();
-            buffer = buffer[line_end + 1..].to_string();
+    let response = ensure_success(response, &provider_secrets(provider)).await?;
+    read_stream(response, emitter, FrameDecoder::Sse(SseDecoder::default()), parse_claude_event).await
+}
 
-            if line.is_empty() {
-                continue;
-            }
-            if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(&line) {
-                if let Some(response_text) = parsed["response"].as_str() {
-                    full_content.push_str(response_text);
-                    let _ = app.emit("ai-stream", StreamChunk { content: response_text.to_string(), done: false });
-                }
-                if parsed["done"].as_bool() == Some(true) {
-                    let _ = app.emit("ai-stream", StreamChunk { content: String::new(), done: true });
-                    return Ok(full_content);
-                }
-            }
-        }
+async fn stream_ollama(
+    emitter: StreamEmitter,
+    provider: &AiProvider,
+    prompt: &str,
+    images: &[ImageInput],
+) -> AppResult<Generation> {
+    let mut body = serde_json::json!({
+        "model": provider.model,
+        "prompt": prompt,
+        "stream": true
+    });
+    if !images.is_empty() {
+        // Ollama 的多模态模型（如 llava）接受原始 base64 列表
+        body["images"] = images.iter().map(|img| serde_json::json!(img.data)).collect();
     }
 
-    let _ = app.emit("ai-stream", StreamChunk { content: String::new(), done: true });
-    Ok(full_content)
+    let client = build_client(provider)?;
+    let path = generation_path(provider, "/api/generate");
+    let response = provider_request(&client, provider, reqwest::Method::POST, &path)
+        .header("Content-Type", "application/json")
+        .json(&body)
+        .send()
+        .await
+        .map_err(request_error)?;
+
+    let response = ensure_success(response, &provider_secrets(provider)).await?;
+    read_stream(response, emitter, FrameDecoder::Lines(LineDecoder::default()), parse_ollama_line).await
 }
@@ -1,10 +1,262 @@
-use crate::db::{ClipItem, Database};
+use crate::db::{ClipImage, ClipItem, Database, DedupScope, ExportedClip};
+use crate::error::{AppError, AppResult};
+use crate::i18n;
+use crate::link_preview;
+use crate::settings;
 use arboard::Clipboard;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+use serde::Serialize;
 use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::io::Write;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 
+/// 获取剪贴板失败后的首次重试间隔，之后每次翻倍
+const RETRY_BACKOFF_MIN: Duration = Duration::from_millis(500);
+const RETRY_BACKOFF_MAX: Duration = Duration::from_secs(30);
+/// 连续读取失败达到该次数后重建剪贴板句柄
+const MAX_READ_FAILURES: u32 = 3;
+
+/// 监听线程读取剪贴板内容的间隔（无法获取变更计数的平台）
+pub const MONITOR_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// macOS 上检查剪贴板变更计数的间隔；只读一个整数，开销很小，可以比读取内容频繁得多
+const CHANGE_COUNT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 暂停记录文本：PasteGo 临时借用剪贴板（如内联生成分批粘贴）时置位，期间的文本变化只更新哈希不入库
+static TEXT_RECORDING_PAUSED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_text_recording_paused(paused: bool) {
+    TEXT_RECORDING_PAUSED.store(paused, Ordering::SeqCst);
+}
+
+/// 用户暂停监听：期间的复制全部不记录，恢复后也不会补记暂停期间复制的内容
+static MONITORING_PAUSED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_monitoring_paused(paused: bool) {
+    MONITORING_PAUSED.store(paused, Ordering::SeqCst);
+}
+
+pub fn is_monitoring_paused() -> bool {
+    MONITORING_PAUSED.load(Ordering::SeqCst)
+}
+
+/// 等待剪贴板变化时的轮询间隔
+const CHANGE_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 文本条目可用的类型（图片和 GIF 只能由监听线程产生）
+const TEXT_CLIP_TYPES: &[&str] = &["text", "code", "url", "color", "json", "xml", "email", "phone"];
+
+/// 记录剪贴板读取的连续失败；内容为空或格式不符不算失败
+#[derive(Default)]
+struct ClipboardHealth {
+    failures: u32,
+    /// 累计失败次数，成功读取后也不清零
+    total_failures: u64,
+    last_error: String,
+}
+
+impl ClipboardHealth {
+    fn check<T>(&mut self, result: Result<T, arboard::Error>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.failures = 0;
+                Some(value)
+            }
+            Err(arboard::Error::ContentNotAvailable) | Err(arboard::Error::ConversionFailure) => None,
+            Err(e) => {
+                self.failures += 1;
+                self.total_failures += 1;
+                self.last_error = e.to_string();
+                None
+            }
+        }
+    }
+}
+
+/// clipboard-changed 事件的负载：只带标识和基本信息，完整内容由前端按需查询
+#[derive(Clone, Serialize)]
+pub struct ClipChanged {
+    pub id: String,
+    pub clip_type: String,
+    pub source_app: Option<String>,
+    pub created_at: String,
+}
+
+impl From<&ClipItem> for ClipChanged {
+    fn from(item: &ClipItem) -> Self {
+        Self {
+            id: item.id.clone(),
+            clip_type: item.clip_type.clone(),
+            source_app: item.source_app.clone(),
+            created_at: item.created_at.clone(),
+        }
+    }
+}
+
+/// 写入新条目并通知前端；按设置决定是否合并重复内容。返回是否新增了条目
+fn store_clip(app: &AppHandle, db: &Arc<Database>, item: &ClipItem) -> bool {
+    let dedup = settings::get_dedup_scope(db);
+    match db.insert_clip(item, dedup) {
+        Ok(true) => {
+            let _ = app.emit("clipboard-changed", ClipChanged::from(item));
+            if item.clip_type == "url" && settings::get_bool(db, settings::LINK_PREVIEW) {
+                spawn_title_fetch(app.clone(), db.clone(), item);
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// 把同一次复制中的图片附加到已记录的文本条目上，并通知前端刷新该条目
+fn attach_image(app: &AppHandle, db: &Database, clip: &ClipChanged, image: &ClipImage) {
+    match db.attach_image(&clip.id, image) {
+        Ok(true) => {
+            let _ = app.emit("clipboard-changed", clip.clone());
+        }
+        Ok(false) => {}
+        Err(e) => log::warn!("Failed to attach image to clip {}: {}", clip.id, e),
+    }
+}
+
+/// 一次剪贴板写入（以变更计数区分）中的文本；clip 为本次新记录的文本条目，
+/// 文本与已有条目重复或未被记录时为 None
+struct CopiedText {
+    change_count: i64,
+    clip: Option<ClipChanged>,
+}
+
+/// 构建文本条目，类型和代码语言由 classify 识别
+fn text_clip(text: String, hash: String, original_length: Option<i64>, source_app: Option<String>) -> ClipItem {
+    let (clip_type, code_language) = classify(&text);
+    ClipItem {
+        id: uuid::Uuid::new_v4().to_string(),
+        content: text,
+        content_hash: hash,
+        clip_type,
+        source_app,
+        image_path: None,
+        is_pinned: false,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        image_width: None,
+        image_height: None,
+        image_bytes: None,
+        code_language,
+        deleted_at: None,
+        is_favorite: false,
+        phash: None,
+        original_length,
+        preview: None,
+        content_length: None,
+        pin_order: None,
+        title: None,
+        expires_at: None,
+        copy_count: 1,
+        age_seconds: None,
+        match_ranges: None,
+    }
+}
+
+/// 手动添加一条文本记录（不经过系统剪贴板）。哈希规范化、超大文本处理和去重规则与监听线程一致；
+/// clip_type 为空时自动识别。返回是否新增了条目，内容与已有条目重复时只会把已有条目提到最前
+pub fn create_clip(
+    app: &AppHandle,
+    db: &Arc<Database>,
+    text: String,
+    clip_type: Option<String>,
+) -> AppResult<bool> {
+    let mut item = prepare_text_clip(db, text)?;
+    if let Some(t) = clip_type {
+        if !TEXT_CLIP_TYPES.contains(&t.as_str()) {
+            return Err(AppError::InvalidInput(format!("Unsupported clip type: {}", t)));
+        }
+        if t != item.clip_type {
+            item.code_language = if t == "code" {
+                detect_code_language(&item.content)
+            } else {
+                None
+            };
+            item.clip_type = t;
+        }
+    }
+    Ok(store_clip(app, db, &item))
+}
+
+/// 按当前设置为一段文本构建条目：统一换行、规范化后计算哈希、处理超大文本并识别类型
+pub fn prepare_text_clip(db: &Database, text: String) -> AppResult<ClipItem> {
+    build_text_clip(
+        text,
+        settings::get_bool(db, settings::NORMALIZE_LINE_ENDINGS),
+        &settings::get(db, settings::HASH_NORMALIZATION),
+        settings::get_i64(db, settings::MAX_CONTENT_BYTES).max(1) as usize,
+        settings::get(db, settings::OVERSIZE_ACTION) == "skip",
+    )
+}
+
+fn build_text_clip(
+    text: String,
+    normalize_eol: bool,
+    hash_policy: &str,
+    max_bytes: usize,
+    skip: bool,
+) -> AppResult<ClipItem> {
+    if text.trim().is_empty() {
+        return Err(AppError::InvalidInput("Content must not be empty".to_string()));
+    }
+    let (text, hash) = prepare_text(text, normalize_eol, hash_policy);
+    let (text, original_length) = fit_content(text, max_bytes, skip).ok_or_else(|| {
+        AppError::InvalidInput(format!("Content exceeds {} bytes", max_bytes))
+    })?;
+
+    Ok(text_clip(text, hash, original_length, None))
+}
+
+/// 为导入的条目构建记录：哈希按本机设置重新计算；文件中的类型不是已知文本类型时重新识别，
+/// 时间无法解析时使用当前时间
+pub fn imported_clip(db: &Database, exported: ExportedClip) -> AppResult<ClipItem> {
+    let mut item = prepare_text_clip(db, exported.content)?;
+    if TEXT_CLIP_TYPES.contains(&exported.clip_type.as_str()) {
+        item.clip_type = exported.clip_type;
+        item.code_language = exported.code_language.filter(|_| item.clip_type == "code");
+    }
+    if chrono::DateTime::parse_from_rfc3339(&exported.created_at).is_ok() {
+        item.created_at = exported.created_at;
+    }
+    item.title = exported.title;
+    item.is_pinned = exported.is_pinned;
+    item.is_favorite = exported.is_favorite;
+    Ok(item)
+}
+
+/// 后台抓取链接的网页标题，写入后再通知前端刷新；失败只记日志
+fn spawn_title_fetch(app: AppHandle, db: Arc<Database>, item: &ClipItem) {
+    let changed = ClipChanged::from(item);
+    let url = item.content.clone();
+    tauri::async_runtime::spawn(async move {
+        match link_preview::fetch_title(&url).await {
+            Ok(Some(title)) => {
+                if let Ok(true) = db.set_clip_title(&changed.id, Some(&title)) {
+                    let _ = app.emit("clipboard-changed", changed);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => log::info!("link preview failed: {}", e),
+        }
+    });
+}
+
+/// 通知前端监听处于异常状态
+fn emit_monitor_error(app: &AppHandle, message: String) {
+    log::warn!("clipboard monitor: {}", message);
+    let _ = app.emit("monitor-error", message);
+}
+
 pub struct ClipboardMonitor {
     running: Arc<AtomicBool>,
 }
@@ -19,63 +271,244 @@ impl ClipboardMonitor {
     pub fn start(&self, app: AppHandle, db: Arc<Database>, images_dir: std::path::PathBuf) {
         let running = self.running.clone();
         running.store(true, Ordering::SeqCst);
+        let image_jobs = spawn_image_worker(app.clone(), db.clone(), images_dir.clone());
 
         std::thread::spawn(move || {
-            let mut clipboard = Clipboard::new().expect("Failed to access clipboard");
-            let mut last_text_hash = String::new();
-            let mut last_image_hash = String::new();
+            let mut clipboard: Option<Clipboard> = None;
+            let mut health = ClipboardHealth::default();
+            let mut backoff = RETRY_BACKOFF_MIN;
+            let mut degraded = false;
+            // 从最近一条记录恢复，避免重启后把剪贴板上已有的内容当作新条目
+            let mut last_text_hash = db.latest_clip_hash(false).ok().flatten().unwrap_or_default();
+            let mut last_image_hash = db.latest_clip_hash(true).ok().flatten().unwrap_or_default();
+            let mut last_change_count = pasteboard_change_count();
+            // 启动时剪贴板上已有的内容视为已处理，和文本一起存在的图片不再单独记录
+            let mut copied_text = last_change_count.map(|change_count| CopiedText { change_count, clip: None });
+            // 已经读取过内容的变更计数；启动后的第一轮总是读取一次
+            let mut processed_change_count: Option<i64> = None;
+            let mut last_image_capture: Option<ImageCapture> = None;
 
             while running.load(Ordering::SeqCst) {
+                // 剪贴板暂时不可用（如快速切换用户）时退避重试，而不是让线程崩溃
+                let cb = match clipboard.as_mut() {
+                    Some(cb) => cb,
+                    None => match Clipboard::new() {
+                        Ok(cb) => {
+                            backoff = RETRY_BACKOFF_MIN;
+                            if degraded {
+                                degraded = false;
+                                let _ = app.emit("monitor-recovered", ());
+                            }
+                            clipboard.insert(cb)
+                        }
+                        Err(e) => {
+                            degraded = true;
+                            emit_monitor_error(&app, format!("Failed to access clipboard: {}", e));
+                            std::thread::sleep(backoff);
+                            backoff = (backoff * 2).min(RETRY_BACKOFF_MAX);
+                            continue;
+                        }
+                    },
+                };
+
+                // 变更计数没变说明剪贴板没有被写入，跳过读取内容（读取图片尤其昂贵）；
+                // 拿不到变更计数的平台每轮都读取内容，靠哈希判断是否变化
+                let change_count = pasteboard_change_count();
+                if change_count.is_some() && change_count == processed_change_count {
+                    std::thread::sleep(CHANGE_COUNT_POLL_INTERVAL);
+                    continue;
+                }
+                let failures_before = health.total_failures;
+
+                // 带有隐藏标记的写入（如密码管理器复制的密码）整体跳过，文本和图片都不记录；
+                // 哈希保持不变，之后恢复成原来的内容时也不会被当作新条目。暂停监听时同样处理
+                if pasteboard_is_concealed() || is_monitoring_paused() {
+                    last_change_count = change_count;
+                    processed_change_count = change_count;
+                    std::thread::sleep(CHANGE_COUNT_POLL_INTERVAL);
+                    continue;
+                }
+
+                // 关闭去重或按来源应用去重时，再次复制相同内容也要记录：哈希不变但剪贴板变更计数增加。
+                // 按来源应用去重时由 insert_clip 决定合并到同一应用的已有条目还是新增
+                let recopied = change_count.is_some()
+                    && change_count != last_change_count
+                    && settings::get_dedup_scope(&db) != DedupScope::Global;
+                last_change_count = change_count;
+
                 // Check for text
-                if let Ok(text) = clipboard.get_text() {
+                let mut has_text = false;
+                if let Some(text) = health.check(cb.get_text()) {
                     if !text.trim().is_empty() {
-                        let hash = compute_hash(&text);
-                        if hash != last_text_hash {
+                        has_text = true;
+                        let (text, hash) = prepare_text(
+                            text,
+                            settings::get_bool(&db, settings::NORMALIZE_LINE_ENDINGS),
+                            &settings::get(&db, settings::HASH_NORMALIZATION),
+                        );
+                        if hash != last_text_hash || recopied {
                             last_text_hash = hash.clone();
-                            let clip_type = detect_type(&text);
-                            let item = ClipItem {
-                                id: uuid::Uuid::new_v4().to_string(),
-                                content: text,
-                                content_hash: hash,
-                                clip_type,
-                                source_app: get_frontmost_app(),
-                                image_path: None,
-                                is_pinned: false,
-                                created_at: chrono::Utc::now().to_rfc3339(),
-                            };
-                            if let Ok(true) = db.insert_clip(&item) {
-                                let _ = app.emit("clipboard-changed", &item);
+                            let mut stored = None;
+                            // 超大文本：哈希基于原文计算，去重不受截断影响
+                            let max_bytes = settings::get_i64(&db, settings::MAX_CONTENT_BYTES).max(1) as usize;
+                            let skip = settings::get(&db, settings::OVERSIZE_ACTION) == "skip";
+                            let paused = TEXT_RECORDING_PAUSED.load(Ordering::SeqCst);
+                            if let Some((text, original_length)) =
+                                fit_content(text, max_bytes, skip).filter(|_| !paused)
+                            {
+                                let item = text_clip(text, hash, original_length, get_frontmost_app());
+                                if store_clip(&app, &db, &item) {
+                                    stored = Some(ClipChanged::from(&item));
+                                }
                             }
+                            copied_text = change_count.map(|change_count| CopiedText { change_count, clip: stored });
+                        } else if copied_text.as_ref().map(|t| t.change_count) != change_count {
+                            // 再次复制了相同的文本
+                            copied_text = change_count.map(|change_count| CopiedText { change_count, clip: None });
                         }
                     }
                 }
 
-                // Check for image
-                if let Ok(img) = clipboard.get_image() {
-                    let raw_bytes = img.bytes.as_ref();
-                    let hash = compute_hash_bytes(raw_bytes);
-                    if hash != last_image_hash {
+                // 富文本选区等一次复制会同时写入文本和图片：与文本属于同一次写入的图片并入文本条目，
+                // 文本没有生成新条目时丢弃，避免一次复制出现两条记录
+                let same_copy = has_text
+                    && change_count.is_some()
+                    && copied_text.as_ref().map(|t| t.change_count) == change_count;
+                let attach_to = copied_text.as_ref().and_then(|t| t.clip.clone()).filter(|_| same_copy);
+
+                // Check for GIF first so animation survives; arboard only exposes the first frame
+                if let Some(gif) = read_pasteboard_gif() {
+                    let hash = compute_hash_bytes(&gif);
+                    if hash != last_image_hash || recopied {
                         last_image_hash = hash.clone();
-                        // Save image to disk
-                        if let Some(path) = save_image(&images_dir, &hash, &img) {
+                        let (width, height) = gif_dimensions(&gif).unwrap_or((0, 0));
+                        let saved = if same_copy && attach_to.is_none() {
+                            None
+                        } else {
+                            save_gif(&images_dir, &hash, &gif)
+                        };
+                        if let (Some((path, size)), Some(clip)) = (saved.clone(), attach_to.as_ref()) {
+                            let image = ClipImage {
+                                path,
+                                width: width as i64,
+                                height: height as i64,
+                                bytes: size,
+                                phash: None,
+                            };
+                            attach_image(&app, &db, clip, &image);
+                        } else if let Some((path, size)) = saved {
                             let item = ClipItem {
                                 id: uuid::Uuid::new_v4().to_string(),
-                                content: format!("[图片 {}x{}]", img.width, img.height),
+                                content: format!("[GIF {}x{}]", width, height),
                                 content_hash: hash,
-                                clip_type: "image".to_string(),
+                                clip_type: "gif".to_string(),
                                 source_app: get_frontmost_app(),
                                 image_path: Some(path),
                                 is_pinned: false,
                                 created_at: chrono::Utc::now().to_rfc3339(),
+                                image_width: Some(width as i64),
+                                image_height: Some(height as i64),
+                                image_bytes: Some(size),
+                                code_language: None,
+                                deleted_at: None,
+                                is_favorite: false,
+                                phash: None,
+                                original_length: None,
+                                preview: None,
+                                content_length: None,
+                                pin_order: None,
+                                title: None,
+                                expires_at: None,
+                                copy_count: 1,
+                                age_seconds: None,
+                                match_ranges: None,
                             };
-                            if let Ok(true) = db.insert_clip(&item) {
-                                let _ = app.emit("clipboard-changed", &item);
+                            store_clip(&app, &db, &item);
+                        }
+                    }
+                } else if let Some(img) = health.check(cb.get_image()) {
+                    // Check for image
+                    let raw_bytes = img.bytes.as_ref();
+                    let hash = compute_hash_bytes(raw_bytes);
+                    if hash != last_image_hash || recopied {
+                        last_image_hash = hash.clone();
+                        let rgba = normalize_rgba(raw_bytes, img.width, img.height);
+                        let phash = rgba
+                            .as_deref()
+                            .and_then(|rgba| dhash(rgba, img.width, img.height))
+                            .map(|h| h as i64);
+                        let now = Instant::now();
+                        let window =
+                            Duration::from_millis(settings::get_i64(&db, settings::IMAGE_BURST_WINDOW_MS).max(0) as u64);
+                        let burst = attach_to.is_none()
+                            && is_image_burst(last_image_capture.as_ref(), now, window, phash, phash_threshold(&db));
+                        if burst {
+                            // 录屏或连续截图：和上一张几乎相同，只顺延时间窗口，比较基准仍是上一张记录的图片
+                            if let Some(capture) = last_image_capture.as_mut() {
+                                capture.at = now;
                             }
+                        } else if rgba.is_some() && !(same_copy && attach_to.is_none()) {
+                            last_image_capture = Some(ImageCapture { at: now, phash });
+                        }
+                        if rgba.is_none() {
+                            emit_monitor_error(
+                                &app,
+                                format!(
+                                    "Skipped image with unexpected pixel data: {}x{}, {} bytes",
+                                    img.width,
+                                    img.height,
+                                    raw_bytes.len()
+                                ),
+                            );
+                        } else if same_copy && attach_to.is_none() {
+                            // 属于已有文本的同一次复制，不单独记录
+                        } else if burst {
+                            // 短时间内连续到达的相似图片，不记录也不置顶
+                        } else if let Some(similar_id) =
+                            phash
+                            .filter(|_| attach_to.is_none())
+                            .and_then(|h| find_similar_image(&db, h, get_frontmost_app().as_deref()))
+                        {
+                            // 与已有图片几乎相同（如不同缩放比例的同一截图）：把已有条目提到最前，不新增
+                            if let Err(e) = db.recopy_clip(&similar_id, &chrono::Utc::now().to_rfc3339()) {
+                                log::warn!("Failed to bump similar image {}: {}", similar_id, e);
+                            }
+                        } else if let Some(rgba) = rgba {
+                            // 编码和写盘交给后台线程，大图不会阻塞剪贴板轮询
+                            let _ = image_jobs.send(ImageJob {
+                                hash,
+                                width: img.width,
+                                height: img.height,
+                                rgba: rgba.into_owned(),
+                                phash,
+                                source_app: get_frontmost_app(),
+                                created_at: chrono::Utc::now().to_rfc3339(),
+                                attach_to,
+                            });
                         }
                     }
                 }
 
-                std::thread::sleep(std::time::Duration::from_millis(500));
+                // 读取失败时不标记为已处理，下一轮重新读取这次变更
+                if health.total_failures == failures_before {
+                    processed_change_count = change_count;
+                }
+
+                if health.failures >= MAX_READ_FAILURES {
+                    degraded = true;
+                    emit_monitor_error(
+                        &app,
+                        format!("Clipboard reads keep failing, reconnecting: {}", health.last_error),
+                    );
+                    clipboard = None;
+                    health = ClipboardHealth::default();
+                }
+
+                std::thread::sleep(if change_count.is_some() {
+                    CHANGE_COUNT_POLL_INTERVAL
+                } else {
+                    MONITOR_POLL_INTERVAL
+                });
             }
         });
     }
@@ -85,21 +518,165 @@ impl ClipboardMonitor {
     }
 }
 
+/// 待保存的剪贴板图片，像素数据已整理为紧密排列的 RGBA
+struct ImageJob {
+    hash: String,
+    width: usize,
+    height: usize,
+    rgba: Vec<u8>,
+    phash: Option<i64>,
+    source_app: Option<String>,
+    created_at: String,
+    /// 与文本来自同一次复制时，保存后附加到该文本条目而不是新建条目
+    attach_to: Option<ClipChanged>,
+}
+
+/// 启动图片保存线程：编码写盘完成后再写入数据库并发送 clipboard-changed。
+/// 队列不限长度，连续复制多张图片时任务只会排队而不会被丢弃；
+/// 监听线程退出、发送端被释放后，处理完剩余任务再结束
+fn spawn_image_worker(
+    app: AppHandle,
+    db: Arc<Database>,
+    images_dir: std::path::PathBuf,
+) -> mpsc::Sender<ImageJob> {
+    let (tx, rx) = mpsc::channel::<ImageJob>();
+    std::thread::spawn(move || {
+        for job in rx {
+            let format = settings::get(&db, settings::IMAGE_FORMAT);
+            let quality = settings::get_i64(&db, settings::IMAGE_QUALITY).clamp(1, 100) as u8;
+            let Some((path, size)) =
+                save_image(&images_dir, &job.hash, job.width, job.height, &job.rgba, &format, quality)
+            else {
+                log::warn!("Failed to save clipboard image {}x{}", job.width, job.height);
+                continue;
+            };
+            if let Some(clip) = &job.attach_to {
+                let image = ClipImage {
+                    path,
+                    width: job.width as i64,
+                    height: job.height as i64,
+                    bytes: size,
+                    phash: job.phash,
+                };
+                attach_image(&app, &db, clip, &image);
+                continue;
+            }
+            let item = ClipItem {
+                id: uuid::Uuid::new_v4().to_string(),
+                content: i18n::image_placeholder(i18n::current(&db), job.width, job.height),
+                content_hash: job.hash,
+                clip_type: "image".to_string(),
+                source_app: job.source_app,
+                image_path: Some(path),
+                is_pinned: false,
+                created_at: job.created_at,
+                image_width: Some(job.width as i64),
+                image_height: Some(job.height as i64),
+                image_bytes: Some(size),
+                code_language: None,
+                deleted_at: None,
+                is_favorite: false,
+                phash: job.phash,
+                original_length: None,
+                preview: None,
+                content_length: None,
+                pin_order: None,
+                title: None,
+                expires_at: None,
+                copy_count: 1,
+                age_seconds: None,
+                match_ranges: None,
+            };
+            store_clip(&app, &db, &item);
+        }
+    });
+    tx
+}
+
+/// 超大文本的截断标记
+const TRUNCATED_MARKER: &str = "\n…[内容过长，已截断]";
+
+/// 按字节上限处理文本：未超限原样返回；超限时跳过（None）或在字符边界截断并追加标记，
+/// 同时返回原始字节数
+fn fit_content(text: String, max_bytes: usize, skip: bool) -> Option<(String, Option<i64>)> {
+    if text.len() <= max_bytes {
+        return Some((text, None));
+    }
+    if skip {
+        return None;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let truncated = format!("{}{}", &text[..end], TRUNCATED_MARKER);
+    Some((truncated, Some(text.len() as i64)))
+}
+
 fn compute_hash(text: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(text.as_bytes());
     hex::encode(hasher.finalize())
 }
 
+/// 按 HASH_NORMALIZATION 设置规范化文本，使仅空白不同的复制内容得到相同的哈希
+fn normalize_for_hash<'a>(text: &'a str, policy: &str) -> Cow<'a, str> {
+    match policy {
+        "trim" => Cow::Borrowed(text.trim()),
+        "whitespace" => Cow::Owned(text.split_whitespace().collect::<Vec<_>>().join(" ")),
+        _ => Cow::Borrowed(text),
+    }
+}
+
+/// 把 \r\n 换行统一为 \n；单独的 \r 可能有含义（如终端进度输出），保持不变
+fn normalize_line_endings(text: String) -> String {
+    if text.contains("\r\n") {
+        text.replace("\r\n", "\n")
+    } else {
+        text
+    }
+}
+
+/// 读到的文本在保存前的处理：按设置统一换行，返回要保存的文本和去重哈希
+fn prepare_text(text: String, normalize_eol: bool, hash_policy: &str) -> (String, String) {
+    let text = if normalize_eol { normalize_line_endings(text) } else { text };
+    let hash = compute_hash(&normalize_for_hash(&text, hash_policy));
+    (text, hash)
+}
+
 fn compute_hash_bytes(data: &[u8]) -> String {
     let mut hasher = Sha256::new();
     hasher.update(data);
     hex::encode(hasher.finalize())
 }
 
+/// 识别文本条目的类型，代码类型同时给出猜测的语言
+pub fn classify(text: &str) -> (String, Option<String>) {
+    let clip_type = detect_type(text);
+    let code_language = if clip_type == "code" {
+        detect_code_language(text)
+    } else {
+        None
+    };
+    (clip_type, code_language)
+}
+
 fn detect_type(text: &str) -> String {
     let trimmed = text.trim();
 
+    // Color literal detection (whole content only)
+    if is_color_literal(trimmed) {
+        return "color".to_string();
+    }
+
+    // Contact detection (whole content only, so prose mentioning an address stays text)
+    if is_email_address(trimmed) {
+        return "email".to_string();
+    }
+    if is_phone_number(trimmed) {
+        return "phone".to_string();
+    }
+
     // URL detection
     if trimmed.starts_with("http://")
         || trimmed.starts_with("https://")
@@ -110,6 +687,14 @@ fn detect_type(text: &str) -> String {
         }
     }
 
+    // Structured data detection (size-bounded parse)
+    if crate::transform::is_json(trimmed) {
+        return "json".to_string();
+    }
+    if crate::transform::is_xml(trimmed) {
+        return "xml".to_string();
+    }
+
     // Code detection heuristics
     let code_indicators = [
         "fn ", "func ", "def ", "class ", "import ", "from ", "#include",
@@ -131,29 +716,511 @@ fn detect_type(text: &str) -> String {
     "text".to_string()
 }
 
+/// 根据关键字粗略猜测代码语言，得分最高者胜出；无任何特征时返回 None
+fn detect_code_language(text: &str) -> Option<String> {
+    const LANGUAGES: &[(&str, &[&str])] = &[
+        ("rust", &["fn ", "let mut ", "println!", "pub fn", "impl ", "&self", "use std::", "Vec<", "Option<", "match "]),
+        ("python", &["def ", "import ", "elif ", "self.", "print(", "__init__", "None", "True", "lambda "]),
+        ("javascript", &["=>", "console.log", "function ", "const ", "===", "require(", "export ", "document."]),
+        ("typescript", &["interface ", ": string", ": number", ": boolean", "export type ", "as const"]),
+        ("go", &["func ", "package ", ":= ", "fmt.", "err != nil", "go func"]),
+        ("java", &["public class", "System.out", "public static void", "private ", "@Override", "import java."]),
+        ("c", &["#include", "printf(", "int main(", "malloc(", "->"]),
+        ("cpp", &["std::", "#include <iostream>", "cout <<", "template<", "nullptr"]),
+        ("php", &["<?php", "$this->", "echo ", "function ", "=> $"]),
+        ("shell", &["#!/bin/", "echo ", "fi\n", "done\n", "$(", "export "]),
+    ];
+
+    let mut best: Option<(&str, usize)> = None;
+    for (lang, indicators) in LANGUAGES {
+        let score = indicators.iter().filter(|&&ind| text.contains(ind)).count();
+        if score > 0 && best.map_or(true, |(_, s)| score > s) {
+            best = Some((lang, score));
+        }
+    }
+    best.map(|(lang, _)| lang.to_string())
+}
+
+/// 判断整段内容是否为单个颜色值：#RGB / #RRGGBB / #RRGGBBAA、rgb()/rgba()、hsl()/hsla()
+fn is_color_literal(text: &str) -> bool {
+    if let Some(hex) = text.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+
+    let lower = text.to_ascii_lowercase();
+    let Some(open) = lower.find('(') else {
+        return false;
+    };
+    let Some(args) = lower[open + 1..].strip_suffix(')') else {
+        return false;
+    };
+    let func = lower[..open].trim_end();
+    let parts: Vec<&str> = args
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|p| !p.is_empty())
+        .collect();
+
+    match func {
+        "rgb" | "rgba" => {
+            matches!(parts.len(), 3 | 4)
+                && parts[..3].iter().all(|p| is_color_number(p, 255.0))
+                && parts.get(3).map_or(true, |a| is_alpha(a))
+        }
+        "hsl" | "hsla" => {
+            matches!(parts.len(), 3 | 4)
+                && is_hue(parts[0])
+                && parts[1..3]
+                    .iter()
+                    .all(|p| p.strip_suffix('%').is_some_and(|v| is_color_number(v, 100.0)))
+                && parts.get(3).map_or(true, |a| is_alpha(a))
+        }
+        _ => false,
+    }
+}
+
+/// 判断整段内容是否为单个邮箱地址：本地部分为常见的 ASCII 字符且不以点开头/结尾，
+/// 域名至少两段，每段为字母数字和连字符，顶级域名为至少两个字母
+fn is_email_address(text: &str) -> bool {
+    let Some((local, domain)) = text.split_once('@') else {
+        return false;
+    };
+    let local_ok = !local.is_empty()
+        && local.len() <= 64
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~.-".contains(c));
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_ok = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && labels
+            .last()
+            .is_some_and(|tld| tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()));
+    local_ok && domain_ok
+}
+
+/// 判断整段内容是否为单个电话号码：可选的 + 国家码，数字之间可用空格、- 、. 分隔，
+/// 最多一对括号（区号），共 7–15 位数字（E.164 上限）。没有分隔符的纯数字只接受中国大陆手机号，
+/// 以免把时间戳、订单号当成电话。没有 + 和括号时规则更严：形如日期、IPv4 地址、小数、
+/// 千位分组或版本号的内容不算，且至少要分成三组，或是以 - 或空格分成两组、第一组不超过 4 位
+fn is_phone_number(text: &str) -> bool {
+    let body = text.strip_prefix('+').unwrap_or(text);
+    let international = body.len() != text.len();
+    if body.is_empty() || !body.chars().all(|c| c.is_ascii_digit() || " -.()".contains(c)) {
+        return false;
+    }
+    if !body.starts_with(|c: char| c.is_ascii_digit() || c == '(') || !body.ends_with(|c: char| c.is_ascii_digit()) {
+        return false;
+    }
+    // 括号最多一对，且只包住数字
+    let (opens, closes) = (body.matches('(').count(), body.matches(')').count());
+    if opens != closes || opens > 1 {
+        return false;
+    }
+    if let (Some(open), Some(close)) = (body.find('('), body.find(')')) {
+        if close < open + 2 || !body[open + 1..close].chars().all(|c| c.is_ascii_digit()) {
+            return false;
+        }
+    }
+    let digits = body.chars().filter(|c| c.is_ascii_digit()).count();
+    if !(7..=15).contains(&digits) {
+        return false;
+    }
+    // 分隔符不能连续出现，如 "1--2"
+    let separators = |c: char| " -.".contains(c);
+    if body.chars().zip(body.chars().skip(1)).any(|(a, b)| separators(a) && separators(b)) {
+        return false;
+    }
+    let groups: Vec<&str> = body
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|g| !g.is_empty())
+        .collect();
+    if groups.len() == 1 && !international && opens == 0 {
+        let mobile = body.len() == 11 && body.starts_with('1') && matches!(body.as_bytes()[1], b'3'..=b'9');
+        return mobile;
+    }
+    if !international && opens == 0 {
+        let date = groups.len() == 3
+            && ((groups[0].len() == 4 && groups[1].len() <= 2 && groups[2].len() <= 2)
+                || (groups[0].len() <= 2 && groups[1].len() <= 2 && groups[2].len() == 4));
+        let ipv4 = groups.len() == 4 && body.contains('.') && groups.iter().all(|g| g.len() <= 3);
+        // 1 000 000、1.000.000 这样按千位分组的数字
+        let thousands = groups[0].len() <= 3 && groups[1..].iter().all(|g| g.len() == 3);
+        // 只用 . 分隔时，有一位数的组更像版本号（如 10.15.7.1234）；两组则是小数
+        let dots_only = !body.contains([' ', '-']);
+        let version = dots_only && groups.iter().any(|g| g.len() == 1);
+        let decimal = dots_only && groups.len() == 2;
+        if date || ipv4 || thousands || version || decimal {
+            return false;
+        }
+        let two_groups = groups.len() == 2 && !dots_only && groups[0].len() <= 4;
+        return groups.len() >= 3 || two_groups;
+    }
+    true
+}
+
+/// 数值分量：0..=max 的数字，或 0%..=100% 的百分比
+fn is_color_number(part: &str, max: f64) -> bool {
+    match part.strip_suffix('%') {
+        Some(pct) => pct.parse::<f64>().is_ok_and(|v| (0.0..=100.0).contains(&v)),
+        None => part.parse::<f64>().is_ok_and(|v| (0.0..=max).contains(&v)),
+    }
+}
+
+fn is_alpha(part: &str) -> bool {
+    is_color_number(part, 1.0)
+}
+
+fn is_hue(part: &str) -> bool {
+    part.strip_suffix("deg")
+        .unwrap_or(part)
+        .parse::<f64>()
+        .is_ok_and(|v| v.is_finite())
+}
+
+/// 按设置查找感知哈希相近的已有图片，按来源应用去重时只在同一应用复制的图片中查找
+fn find_similar_image(db: &Database, phash: i64, source_app: Option<&str>) -> Option<String> {
+    let dedup = settings::get_dedup_scope(db);
+    if dedup == DedupScope::Off || !settings::get_bool(db, settings::PHASH_DEDUP) {
+        return None;
+    }
+    db.find_similar_image(phash, phash_threshold(db), dedup, source_app).ok().flatten()
+}
+
+fn phash_threshold(db: &Database) -> u32 {
+    settings::get_i64(db, settings::PHASH_THRESHOLD).clamp(0, 64) as u32
+}
+
+/// 监听线程最近读到的一张图片
+struct ImageCapture {
+    at: Instant,
+    phash: Option<i64>,
+}
+
+/// 图片是否紧跟在上一张之后到达且感知哈希几乎相同；编码器每次输出的字节略有差异时，
+/// 字节哈希无法识别这种重复。没有感知哈希时无法比较，总是记录
+fn is_image_burst(
+    last: Option<&ImageCapture>,
+    now: Instant,
+    window: Duration,
+    phash: Option<i64>,
+    threshold: u32,
+) -> bool {
+    let Some(last) = last else {
+        return false;
+    };
+    if now.saturating_duration_since(last.at) >= window {
+        return false;
+    }
+    match (phash, last.phash) {
+        (Some(a), Some(b)) => (a ^ b).count_ones() <= threshold,
+        _ => false,
+    }
+}
+
+/// 差值哈希（dHash）：缩放为 9x8 灰度图，比较相邻像素亮度得到 64 位指纹
+fn dhash(rgba: &[u8], width: usize, height: usize) -> Option<u64> {
+    if width == 0 || height == 0 || rgba.len() < width * height * 4 {
+        return None;
+    }
+    const W: usize = 9;
+    const H: usize = 8;
+    let mut gray = [0f64; W * H];
+    for (gy, row) in gray.chunks_mut(W).enumerate() {
+        let y0 = gy * height / H;
+        let y1 = ((gy + 1) * height / H).max(y0 + 1);
+        for (gx, cell) in row.iter_mut().enumerate() {
+            let x0 = gx * width / W;
+            let x1 = ((gx + 1) * width / W).max(x0 + 1);
+            let mut sum = 0f64;
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let i = (y * width + x) * 4;
+                    sum += 0.299 * rgba[i] as f64 + 0.587 * rgba[i + 1] as f64 + 0.114 * rgba[i + 2] as f64;
+                }
+            }
+            *cell = sum / ((y1 - y0) * (x1 - x0)) as f64;
+        }
+    }
+
+    let mut hash = 0u64;
+    for y in 0..H {
+        for x in 0..W - 1 {
+            hash <<= 1;
+            if gray[y * W + x] < gray[y * W + x + 1] {
+                hash |= 1;
+            }
+        }
+    }
+    Some(hash)
+}
+
+/// 将剪贴板图片数据整理为紧密排列的 RGBA（width*height*4 字节）。
+/// 长度恰好匹配时直接借用；每行带有对齐填充时逐行去掉填充；其他情况无法安全解释，返回 None
+fn normalize_rgba(bytes: &[u8], width: usize, height: usize) -> Option<Cow<'_, [u8]>> {
+    let row = width.checked_mul(4)?;
+    let expected = row.checked_mul(height)?;
+    if expected == 0 {
+        return None;
+    }
+    if bytes.len() == expected {
+        return Some(Cow::Borrowed(bytes));
+    }
+    if bytes.len() % height != 0 || bytes.len() / height < row {
+        return None;
+    }
+    let stride = bytes.len() / height;
+    let mut packed = Vec::with_capacity(expected);
+    for line in bytes.chunks_exact(stride) {
+        packed.extend_from_slice(&line[..row]);
+    }
+    Some(Cow::Owned(packed))
+}
+
+/// 按设置的格式保存图片：png（默认）/ webp 无损保存，jpeg 按 quality 有损压缩
 fn save_image(
     dir: &std::path::Path,
     hash: &str,
-    img: &arboard::ImageData,
-) -> Option<String> {
+    width: usize,
+    height: usize,
+    rgba_data: &[u8],
+    format: &str,
+    quality: u8,
+) -> Option<(String, i64)> {
     std::fs::create_dir_all(dir).ok()?;
-    let filename = format!("{}.png", &hash[..16]);
+    let ext = image_extension(format);
+    let filename = format!("{}.{}", &hash[..16], ext);
     let path = dir.join(&filename);
+    let size = write_image(&path, width as u32, height as u32, rgba_data, ext, quality)?;
+    Some((path.to_string_lossy().to_string(), size))
+}
 
-    // Convert RGBA raw data to PNG
-    let width = img.width as u32;
-    let height = img.height as u32;
-    let rgba_data = img.bytes.as_ref();
+/// 图片格式设置对应的文件扩展名
+pub fn image_extension(format: &str) -> &'static str {
+    match format {
+        "jpeg" => "jpg",
+        "webp" => "webp",
+        _ => "png",
+    }
+}
 
-    let file = std::fs::File::create(&path).ok()?;
-    let writer = std::io::BufWriter::new(file);
-    let mut encoder = png::Encoder::new(writer, width, height);
-    encoder.set_color(png::ColorType::Rgba);
-    encoder.set_depth(png::BitDepth::Eight);
-    let mut writer = encoder.write_header().ok()?;
-    writer.write_image_data(rgba_data).ok()?;
+/// 按扩展名编码 RGBA 像素并写入 path，返回文件大小；失败时删除写了一半的文件
+fn write_image(
+    path: &std::path::Path,
+    width: u32,
+    height: u32,
+    rgba_data: &[u8],
+    ext: &str,
+    quality: u8,
+) -> Option<i64> {
+    let file = std::fs::File::create(path).ok()?;
+    let mut writer = std::io::BufWriter::new(file);
+    let result = match ext {
+        "jpg" => JpegEncoder::new_with_quality(&mut writer, quality).write_image(
+            &flatten_alpha(rgba_data),
+            width,
+            height,
+            ExtendedColorType::Rgb8,
+        ),
+        "webp" => WebPEncoder::new_lossless(&mut writer).write_image(
+            rgba_data,
+            width,
+            height,
+            ExtendedColorType::Rgba8,
+        ),
+        _ => PngEncoder::new(&mut writer).write_image(rgba_data, width, height, ExtendedColorType::Rgba8),
+    };
+    if result.is_err() || writer.flush().is_err() {
+        let _ = std::fs::remove_file(path);
+        return None;
+    }
+    Some(std::fs::metadata(path).ok()?.len() as i64)
+}
 
-    Some(path.to_string_lossy().to_string())
+/// 把已保存的图片解码后按 format 重新编码，写到原文件旁（同名、换扩展名），返回新路径和大小。
+/// 原文件保留，由调用方在更新数据库后删除
+pub fn reencode_image(path: &str, format: &str, quality: u8) -> Option<(String, i64)> {
+    let rgba = image::open(path).ok()?.to_rgba8();
+    let new_path = std::path::Path::new(path).with_extension(image_extension(format));
+    let size = write_image(
+        &new_path,
+        rgba.width(),
+        rgba.height(),
+        rgba.as_raw(),
+        image_extension(format),
+        quality,
+    )?;
+    Some((new_path.to_string_lossy().to_string(), size))
+}
+
+/// JPEG 不支持透明度：将 RGBA 合成到白色背景上，得到 RGB
+fn flatten_alpha(rgba: &[u8]) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(rgba.len() / 4 * 3);
+    for px in rgba.chunks_exact(4) {
+        let alpha = px[3] as u32;
+        for &c in &px[..3] {
+            rgb.push(((c as u32 * alpha + 255 * (255 - alpha)) / 255) as u8);
+        }
+    }
+    rgb
+}
+
+/// 删除图片文件，只处理位于受管图片目录内的文件
+pub fn remove_image_files(images_dir: &std::path::Path, paths: &[String]) {
+    let Ok(root) = images_dir.canonicalize() else {
+        return;
+    };
+    for path in paths {
+        if let Ok(file) = std::path::Path::new(path).canonicalize() {
+            if file.parent() == Some(root.as_path()) && file.is_file() {
+                let _ = std::fs::remove_file(&file);
+            }
+        }
+    }
+}
+
+/// 删除图片目录中没有被任何条目引用的文件，返回删除数量
+pub fn cleanup_orphan_images(
+    images_dir: &std::path::Path,
+    referenced: &std::collections::HashSet<String>,
+) -> std::io::Result<usize> {
+    if !images_dir.exists() {
+        return Ok(0);
+    }
+    let mut removed = 0;
+    for entry in std::fs::read_dir(images_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !referenced.contains(&name) {
+            std::fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+fn save_gif(dir: &std::path::Path, hash: &str, data: &[u8]) -> Option<(String, i64)> {
+    std::fs::create_dir_all(dir).ok()?;
+    let filename = format!("{}.gif", &hash[..16]);
+    let path = dir.join(&filename);
+    std::fs::write(&path, data).ok()?;
+    Some((path.to_string_lossy().to_string(), data.len() as i64))
+}
+
+/// 从 GIF 头部的逻辑屏幕描述符读取宽高
+fn gif_dimensions(data: &[u8]) -> Option<(u16, u16)> {
+    if data.len() < 10 || !(data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a")) {
+        return None;
+    }
+    let width = u16::from_le_bytes([data[6], data[7]]);
+    let height = u16::from_le_bytes([data[8], data[9]]);
+    Some((width, height))
+}
+
+/// 读取剪贴板中原始的 GIF 数据（如果来源应用提供了 GIF 类型）
+fn read_pasteboard_gif() -> Option<Vec<u8>> {
+    #[cfg(target_os = "macos")]
+    {
+        use cocoa::base::{id, nil};
+        use cocoa::foundation::NSString;
+        unsafe {
+            let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+            let gif_type = NSString::alloc(nil).init_str("com.compuserve.gif");
+            let data: id = msg_send![pasteboard, dataForType: gif_type];
+            let _: () = msg_send![gif_type, release];
+            if data == nil {
+                return None;
+            }
+            let len: usize = msg_send![data, length];
+            let bytes: *const u8 = msg_send![data, bytes];
+            if bytes.is_null() || len == 0 {
+                return None;
+            }
+            let gif = std::slice::from_raw_parts(bytes, len).to_vec();
+            gif_dimensions(&gif).map(|_| gif)
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        None
+    }
+}
+
+/// 当前剪贴板内容是否带有 nspasteboard.org 约定的 Concealed / Transient 标记：
+/// 密码管理器等应用写入时附带，表示内容不应被剪贴板历史保存
+fn pasteboard_is_concealed() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        use cocoa::base::{id, nil};
+        use cocoa::foundation::NSString;
+        const CONCEALED_TYPES: &[&str] = &["org.nspasteboard.ConcealedType", "org.nspasteboard.TransientType"];
+        unsafe {
+            let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+            let types: id = msg_send![pasteboard, types];
+            if types == nil {
+                return false;
+            }
+            let count: usize = msg_send![types, count];
+            (0..count).any(|i| {
+                let pasteboard_type: id = msg_send![types, objectAtIndex: i];
+                let name = pasteboard_type.UTF8String();
+                !name.is_null()
+                    && CONCEALED_TYPES
+                        .contains(&std::ffi::CStr::from_ptr(name).to_string_lossy().as_ref())
+            })
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        false
+    }
+}
+
+/// 剪贴板的变更计数（NSPasteboard changeCount），每次写入剪贴板都会递增
+pub fn pasteboard_change_count() -> Option<i64> {
+    #[cfg(target_os = "macos")]
+    {
+        use cocoa::base::id;
+        unsafe {
+            let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+            let count: i64 = msg_send![pasteboard, changeCount];
+            Some(count)
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        None
+    }
+}
+
+/// 轮询变更计数等待剪贴板被写入，变化后立即返回 true，超时返回 false；
+/// 无法读取变更计数时等满超时并视为已变化
+pub fn wait_for_pasteboard_change(before: Option<i64>, timeout: Duration) -> bool {
+    let Some(before) = before else {
+        std::thread::sleep(timeout);
+        return true;
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if pasteboard_change_count() != Some(before) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(CHANGE_POLL_INTERVAL);
+    }
 }
 
 fn get_frontmost_app() -> Option<String> {
@@ -172,3 +1239,198 @@ fn get_frontmost_app() -> Option<String> {
         None
     }
 }
+
+/// 当前前台应用的 bundle id（如 com.apple.Safari），用于粘贴前切回该应用
+pub fn get_frontmost_app_bundle_id() -> Option<String> {
+    #[cfg(target_os = "macos")]
+    {
+        use cocoa::base::{id, nil};
+        use cocoa::foundation::NSString;
+        unsafe {
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let front_app: id = msg_send![workspace, frontmostApplication];
+            if front_app == nil {
+                return None;
+            }
+            let bundle_id: id = msg_send![front_app, bundleIdentifier];
+            if bundle_id == nil {
+                return None;
+            }
+            let ptr = bundle_id.UTF8String();
+            Some(std::ffi::CStr::from_ptr(ptr).to_string_lossy().into_owned())
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalized_hashes_ignore_whitespace() {
+        let hash = |text, policy| compute_hash(&normalize_for_hash(text, policy));
+        assert_ne!(hash("foo\n", "none"), hash("foo", "none"));
+        assert_eq!(hash("foo\n", "trim"), hash("foo", "trim"));
+        assert_eq!(hash("  foo \t", "trim"), hash("foo", "trim"));
+        assert_ne!(hash("foo  bar", "trim"), hash("foo bar", "trim"));
+        assert_eq!(hash("foo\n", "whitespace"), hash("foo", "whitespace"));
+        assert_eq!(hash(" foo \n\t bar\n", "whitespace"), hash("foo bar", "whitespace"));
+        assert_ne!(hash("foobar", "whitespace"), hash("foo bar", "whitespace"));
+    }
+
+    #[test]
+    fn normalization_keeps_original_when_disabled() {
+        assert_eq!(normalize_for_hash(" foo\n", "none"), " foo\n");
+        assert_eq!(normalize_for_hash(" foo\n", "unknown"), " foo\n");
+        assert_eq!(normalize_for_hash("a \n b", "whitespace"), "a b");
+    }
+
+    #[test]
+    fn crlf_and_lf_text_dedup_when_normalized() {
+        let crlf = prepare_text("fn main() {\r\n    run();\r\n}\r\n".to_string(), true, "none");
+        let lf = prepare_text("fn main() {\n    run();\n}\n".to_string(), true, "none");
+        assert_eq!(crlf, lf);
+        assert_eq!(
+            prepare_text("a\r\nb".to_string(), true, "whitespace").1,
+            prepare_text("a\nb".to_string(), true, "whitespace").1
+        );
+        // 手动添加和导入的条目与监听线程使用同样的规则
+        let manual = build_text_clip("a\r\nb\r\n".to_string(), true, "none", 1024, false).unwrap();
+        assert_eq!(manual.content, "a\nb\n");
+        assert_eq!(manual.content_hash, prepare_text("a\nb\n".to_string(), true, "none").1);
+        let kept = build_text_clip("a\r\nb".to_string(), false, "none", 1024, false).unwrap();
+        assert_eq!(kept.content, "a\r\nb");
+    }
+
+    #[test]
+    fn line_endings_kept_when_normalization_disabled() {
+        let (text, hash) = prepare_text("a\r\nb".to_string(), false, "none");
+        assert_eq!(text, "a\r\nb");
+        assert_ne!(hash, prepare_text("a\nb".to_string(), false, "none").1);
+        assert_eq!(normalize_line_endings("a\rb\n".to_string()), "a\rb\n");
+        assert_eq!(normalize_line_endings("a\r\r\nb".to_string()), "a\r\nb");
+    }
+
+    #[test]
+    fn guesses_code_language() {
+        let rust = "pub fn main() {\n    let mut v = Vec::new();\n    println!(\"{:?}\", v);\n}";
+        assert_eq!(detect_code_language(rust).as_deref(), Some("rust"));
+
+        let python = "import os\n\ndef main():\n    print(os.getcwd())\n";
+        assert_eq!(detect_code_language(python).as_deref(), Some("python"));
+
+        let js = "const add = (a, b) => a + b;\nconsole.log(add(1, 2));\n";
+        assert_eq!(detect_code_language(js).as_deref(), Some("javascript"));
+
+        let go = "package main\n\nfunc main() {\n    x := 1\n    fmt.Println(x)\n}";
+        assert_eq!(detect_code_language(go).as_deref(), Some("go"));
+
+        let java = "public class Main {\n    public static void main(String[] args) {\n        System.out.println(1);\n    }\n}";
+        assert_eq!(detect_code_language(java).as_deref(), Some("java"));
+
+        assert_eq!(detect_code_language("just some prose"), None);
+    }
+
+    #[test]
+    fn detects_json_and_xml() {
+        assert_eq!(detect_type("{\"a\":1,\"b\":[true,null]}"), "json");
+        assert_eq!(detect_type("[1, 2, 3]"), "json");
+        assert_eq!(detect_type("{\"a\":1"), "text");
+        assert_eq!(detect_type("<a><b x=\"1\">hi</b><c/></a>"), "xml");
+        assert_eq!(detect_type("<?xml version=\"1.0\"?>\n<root/>"), "xml");
+        assert_eq!(detect_type("<a><b></a>"), "text");
+        assert_eq!(detect_type("<a></a><b></b>"), "text");
+    }
+
+    #[test]
+    fn detects_hex_colors() {
+        assert_eq!(detect_type("#fff"), "color");
+        assert_eq!(detect_type("#1e90ff"), "color");
+        assert_eq!(detect_type("  #1E90FF80\n"), "color");
+    }
+
+    #[test]
+    fn detects_functional_colors() {
+        assert_eq!(detect_type("rgb(30,144,255)"), "color");
+        assert_eq!(detect_type("rgb(30 144 255)"), "color");
+        assert_eq!(detect_type("rgba(30, 144, 255, 0.5)"), "color");
+        assert_eq!(detect_type("RGBA(100%, 50%, 0%, 50%)"), "color");
+        assert_eq!(detect_type("hsl(210, 100%, 56%)"), "color");
+        assert_eq!(detect_type("hsla(210deg 100% 56% / 0.4)"), "color");
+    }
+
+    #[test]
+    fn rejects_non_colors() {
+        assert_eq!(detect_type("#12"), "text");
+        assert_eq!(detect_type("#12345"), "text");
+        assert_eq!(detect_type("#ggg"), "text");
+        assert_eq!(detect_type("rgb(300, 0, 0)"), "text");
+        assert_eq!(detect_type("rgb(1, 2)"), "text");
+        assert_eq!(detect_type("rgba(0, 0, 0, 2)"), "text");
+        assert_eq!(detect_type("hsl(210, 100, 56)"), "text");
+        assert_eq!(detect_type("cmyk(0, 0, 0, 0)"), "text");
+        assert_eq!(detect_type("color: #1e90ff;"), "text");
+        assert_eq!(detect_type("background: rgb(30,144,255)"), "text");
+    }
+
+    #[test]
+    fn detects_email_addresses() {
+        assert_eq!(detect_type("alice@example.com"), "email");
+        assert_eq!(detect_type("  first.last+tag@mail.example.co.uk\n"), "email");
+        assert_eq!(detect_type("o'brien_99@sub-domain.example.org"), "email");
+    }
+
+    #[test]
+    fn rejects_non_email_addresses() {
+        assert_eq!(detect_type("contact alice@example.com for details"), "text");
+        assert_eq!(detect_type("alice@localhost"), "text");
+        assert_eq!(detect_type("alice@@example.com"), "text");
+        assert_eq!(detect_type(".alice@example.com"), "text");
+        assert_eq!(detect_type("al..ice@example.com"), "text");
+        assert_eq!(detect_type("alice@-example.com"), "text");
+        assert_eq!(detect_type("alice@example.c"), "text");
+        assert_eq!(detect_type("alice@example.123"), "text");
+        assert_eq!(detect_type("@example.com"), "text");
+        assert_eq!(detect_type("a@b.com\nc@d.com"), "text");
+    }
+
+    #[test]
+    fn detects_phone_numbers() {
+        assert_eq!(detect_type("+1 (415) 555-2671"), "phone");
+        assert_eq!(detect_type("+44 20 7946 0958"), "phone");
+        assert_eq!(detect_type("+86 138 0013 8000"), "phone");
+        assert_eq!(detect_type("+33 1 23 45 67 89"), "phone");
+        assert_eq!(detect_type("+4930123456"), "phone");
+        assert_eq!(detect_type("(020) 7946 0958"), "phone");
+        assert_eq!(detect_type("415.555.2671"), "phone");
+        assert_eq!(detect_type("010-12345678"), "phone");
+        assert_eq!(detect_type("13800138000"), "phone");
+    }
+
+    #[test]
+    fn rejects_non_phone_numbers() {
+        assert_eq!(detect_type("call me at 415-555-2671"), "text");
+        assert_eq!(detect_type("1700000000"), "text");
+        assert_eq!(detect_type("1234567"), "text");
+        assert_eq!(detect_type("2024-01-15"), "text");
+        assert_eq!(detect_type("15.01.2024"), "text");
+        assert_eq!(detect_type("192.168.100.200"), "text");
+        assert_eq!(detect_type("+1 555"), "text");
+        assert_eq!(detect_type("+1234567890123456"), "text");
+        assert_eq!(detect_type("415--555-2671"), "text");
+        assert_eq!(detect_type("(415 555-2671"), "text");
+        assert_eq!(detect_type("415-555-2671-"), "text");
+        assert_eq!(detect_type("3.14159"), "text");
+        assert_eq!(detect_type("3.1415926"), "text");
+        assert_eq!(detect_type("0.1234567"), "text");
+        assert_eq!(detect_type("1234.5678"), "text");
+        assert_eq!(detect_type("1234567.89"), "text");
+        assert_eq!(detect_type("1 000 000"), "text");
+        assert_eq!(detect_type("1.000.000"), "text");
+        assert_eq!(detect_type("10.15.7.1234"), "text");
+    }
+}
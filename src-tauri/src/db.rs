@@ -1,4 +1,6 @@
+use crate::transform::PostProcess;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::sync::Mutex;
 
@@ -7,25 +9,353 @@ pub struct ClipItem {
     pub id: String,
     pub content: String,
     pub content_hash: String,
-    pub clip_type: String, // text, code, url, image
+    pub clip_type: String, // text, code, url, image, gif, color, json, xml, email, phone
     pub source_app: Option<String>,
     pub image_path: Option<String>,
     pub is_pinned: bool,
     pub created_at: String,
+    pub image_width: Option<i64>,
+    pub image_height: Option<i64>,
+    pub image_bytes: Option<i64>,
+    pub code_language: Option<String>,
+    pub deleted_at: Option<String>,
+    pub is_favorite: bool,
+    pub phash: Option<i64>,
+    /// Original size in bytes when the stored content was truncated
+    pub original_length: Option<i64>,
+    /// Whitespace-normalized excerpt; list queries fill this and leave `content` empty
+    #[serde(default)]
+    pub preview: Option<String>,
+    /// Length of the full content in characters, set alongside `preview`
+    #[serde(default)]
+    pub content_length: Option<i64>,
+    /// Position among pinned clips, lowest first; None when unpinned
+    #[serde(default)]
+    pub pin_order: Option<i64>,
+    /// Page title for URL clips, fetched on demand
+    #[serde(default)]
+    pub title: Option<String>,
+    /// When set, the clip is permanently deleted after this time even if pinned
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// How many times this content has been copied; bumped when a duplicate is merged
+    #[serde(default)]
+    pub copy_count: i64,
+    /// Seconds since `created_at`, computed by list queries so the UI needn't parse timestamps
+    #[serde(default)]
+    pub age_seconds: Option<i64>,
+    /// Where the search term occurs in `preview`, as [start, end) offsets in UTF-16 code units so
+    /// they index JavaScript strings directly; set by list queries with a search term. Matches past
+    /// the end of the preview are not highlighted, so a hit can come with no ranges
+    #[serde(default)]
+    pub match_ranges: Option<Vec<[usize; 2]>>,
+}
+
+const CLIP_COLUMNS: &str = "id, content, content_hash, clip_type, source_app, image_path, is_pinned, created_at, image_width, image_height, image_bytes, code_language, deleted_at, is_favorite, phash, original_length, pin_order, title, expires_at, copy_count";
+
+fn clip_from_row(row: &rusqlite::Row) -> Result<ClipItem, rusqlite::Error> {
+    Ok(ClipItem {
+        id: row.get(0)?,
+        content: row.get(1)?,
+        content_hash: row.get(2)?,
+        clip_type: row.get(3)?,
+        source_app: row.get(4)?,
+        image_path: row.get(5)?,
+        is_pinned: row.get::<_, i32>(6)? != 0,
+        created_at: row.get(7)?,
+        image_width: row.get(8)?,
+        image_height: row.get(9)?,
+        image_bytes: row.get(10)?,
+        code_language: row.get(11)?,
+        deleted_at: row.get(12)?,
+        is_favorite: row.get::<_, i32>(13)? != 0,
+        phash: row.get(14)?,
+        original_length: row.get(15)?,
+        preview: None,
+        content_length: None,
+        pin_order: row.get(16)?,
+        title: row.get(17)?,
+        expires_at: row.get(18)?,
+        copy_count: row.get(19)?,
+        age_seconds: None,
+        match_ranges: None,
+    })
+}
+
+/// Order value that places a newly pinned clip after all currently pinned ones
+const NEXT_PIN_ORDER: &str = "(SELECT COALESCE(MAX(pin_order), -1) + 1 FROM clip_items WHERE is_pinned = 1)";
+
+/// Number of characters kept in list previews
+const PREVIEW_CHARS: usize = 300;
+
+// Same layout as CLIP_COLUMNS, but only reads the head of `content` (enough to survive
+// whitespace collapsing) and appends the full length.
+const CLIP_LIST_COLUMNS: &str = "id, substr(content, 1, 1200), content_hash, clip_type, source_app, image_path, is_pinned, created_at, image_width, image_height, image_bytes, code_language, deleted_at, is_favorite, phash, original_length, pin_order, title, expires_at, copy_count, length(content)";
+
+fn clip_summary_from_row(row: &rusqlite::Row) -> Result<ClipItem, rusqlite::Error> {
+    let mut item = clip_from_row(row)?;
+    item.preview = Some(make_preview(&std::mem::take(&mut item.content)));
+    item.content_length = row.get(20)?;
+    Ok(item)
+}
+
+/// Unix milliseconds of an RFC3339 timestamp, whatever its offset; None if it doesn't parse
+pub fn timestamp_ms(rfc3339: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(rfc3339)
+        .ok()
+        .map(|t| t.timestamp_millis())
+}
+
+/// Seconds from an RFC3339 timestamp to `now`, never negative; None if it doesn't parse
+fn age_seconds(created_at: &str, now: chrono::DateTime<chrono::Utc>) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(created_at)
+        .ok()
+        .map(|t| (now - t.with_timezone(&chrono::Utc)).num_seconds().max(0))
+}
+
+/// Non-overlapping occurrences of `needle` in `text` as UTF-16 [start, end) offsets. Matching
+/// follows SQLite's LIKE: ASCII letters ignore case, everything else must match exactly
+fn match_ranges(text: &str, needle: &str) -> Vec<[usize; 2]> {
+    let (haystack, pattern) = (text.as_bytes(), needle.as_bytes());
+    let mut ranges = Vec::new();
+    if pattern.is_empty() {
+        return ranges;
+    }
+    // Walk the text once, tracking the UTF-16 offset of each char boundary
+    let mut utf16 = 0;
+    let mut match_end: Option<(usize, usize)> = None; // (byte end, utf16 start)
+    for (i, c) in text.char_indices() {
+        if let Some((end, start16)) = match_end {
+            if i == end {
+                ranges.push([start16, utf16]);
+                match_end = None;
+            }
+        }
+        if match_end.is_none()
+            && haystack.len() - i >= pattern.len()
+            && haystack[i..i + pattern.len()].eq_ignore_ascii_case(pattern)
+        {
+            match_end = Some((i + pattern.len(), utf16));
+        }
+        utf16 += c.len_utf16();
+    }
+    if let Some((_, start16)) = match_end {
+        ranges.push([start16, utf16]);
+    }
+    ranges
+}
+
+/// Collapses runs of whitespace and keeps the first PREVIEW_CHARS characters
+fn make_preview(content: &str) -> String {
+    let mut preview = String::new();
+    for (i, word) in content.split_whitespace().enumerate() {
+        if i > 0 {
+            preview.push(' ');
+        }
+        preview.push_str(word);
+        if preview.len() >= PREVIEW_CHARS * 4 {
+            break;
+        }
+    }
+    match preview.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => preview[..end].to_string(),
+        None => preview,
+    }
+}
+
+/// Age at which a clip's frecency weight has halved: a clip copied twice three days ago ranks
+/// level with one copied once just now
+const FRECENCY_HALF_LIFE_MS: i64 = 3 * 24 * 60 * 60 * 1000;
+
+/// Which existing clips a new or edited clip with the same content hash is merged into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupScope {
+    /// Every copy is kept as its own clip
+    Off,
+    /// Any clip with the same hash
+    Global,
+    /// Only clips with the same hash that were copied from the same app
+    PerSourceApp,
+}
+
+impl DedupScope {
+    /// SQL condition matching the other clips that `?2`'s clip would be merged with; `?1` is the
+    /// hash and `?2` the id of the clip being kept. None when dedup is off
+    fn duplicates_of(self) -> Option<&'static str> {
+        match self {
+            DedupScope::Off => None,
+            DedupScope::Global => Some("content_hash = ?1 AND id != ?2"),
+            DedupScope::PerSourceApp => Some(
+                "content_hash = ?1 AND id != ?2 AND source_app IS (SELECT source_app FROM clip_items WHERE id = ?2)",
+            ),
+        }
+    }
+}
+
+/// Filters for listing clips; `after`/`before` are unix milliseconds
+#[derive(Debug, Default)]
+pub struct ClipFilter<'a> {
+    pub search: Option<&'a str>,
+    pub clip_type: Option<&'a str>,
+    pub favorites_only: bool,
+    pub sort: Option<&'a str>,
+    pub after: Option<i64>,
+    pub before: Option<i64>,
+}
+
+/// Appends `filter`'s conditions (everything except sorting) to a query over clip_items
+fn push_filter_conditions(
+    filter: &ClipFilter,
+    sql: &mut String,
+    params: &mut Vec<Box<dyn rusqlite::types::ToSql>>,
+) {
+    if let Some(s) = filter.search {
+        if !s.is_empty() {
+            sql.push_str(" AND content LIKE ?");
+            params.push(Box::new(format!("%{}%", s)));
+        }
+    }
+    if let Some(t) = filter.clip_type {
+        if t == "image" {
+            // GIFs are stored as their own type but belong under the image filter
+            sql.push_str(" AND clip_type IN ('image', 'gif')");
+        } else if !t.is_empty() && t != "all" {
+            sql.push_str(" AND clip_type = ?");
+            params.push(Box::new(t.to_string()));
+        }
+    }
+    if filter.favorites_only {
+        sql.push_str(" AND is_favorite = 1");
+    }
+    if let Some(after) = filter.after {
+        sql.push_str(" AND created_at_ms >= ?");
+        params.push(Box::new(after));
+    }
+    if let Some(before) = filter.before {
+        sql.push_str(" AND created_at_ms < ?");
+        params.push(Box::new(before));
+    }
+}
+
+/// Fills created_at_ms for rows that predate the column, parsing created_at in Rust because
+/// SQLite's date functions don't cover every RFC3339 form. Rows that don't parse stay NULL
+fn backfill_created_at_ms(conn: &mut rusqlite::Connection) -> Result<(), rusqlite::Error> {
+    let tx = conn.transaction()?;
+    {
+        let mut select = tx.prepare("SELECT id, created_at FROM clip_items WHERE created_at_ms IS NULL")?;
+        let rows = select
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut update = tx.prepare("UPDATE clip_items SET created_at_ms = ?1 WHERE id = ?2")?;
+        for (id, created_at) in rows {
+            if let Some(ms) = timestamp_ms(&created_at) {
+                update.execute(rusqlite::params![ms, id])?;
+            }
+        }
+    }
+    tx.commit()
+}
+
+/// Which clips `export_clips` writes out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportScope {
+    All,
+    Pinned,
+    Favorites,
+}
+
+/// A text clip as stored in an export file; image clips are not exported since their files
+/// stay on this machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedClip {
+    pub content: String,
+    pub clip_type: String,
+    #[serde(default)]
+    pub code_language: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub is_pinned: bool,
+    #[serde(default)]
+    pub is_favorite: bool,
+    pub created_at: String,
+}
+
+/// A saved image file and its metadata, attached to an existing clip
+#[derive(Debug, Clone)]
+pub struct ClipImage {
+    pub path: String,
+    pub width: i64,
+    pub height: i64,
+    pub bytes: i64,
+    pub phash: Option<i64>,
+}
+
+/// Rows permanently removed from clip_items, plus image files that no remaining row references
+#[derive(Debug, Default)]
+pub struct Purged {
+    pub count: usize,
+    pub image_paths: Vec<String>,
+}
+
+impl Purged {
+    fn merge(&mut self, other: Purged) {
+        self.count += other.count;
+        self.image_paths.extend(other.image_paths);
+    }
+}
+
+/// Permanently deletes the matching clips and reports their now-unreferenced image files
+fn purge_where(
+    conn: &rusqlite::Connection,
+    condition: &str,
+    params: &[&dyn rusqlite::types::ToSql],
+) -> Result<Purged, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT image_path FROM clip_items WHERE image_path IS NOT NULL AND {}",
+        condition
+    ))?;
+    let candidates = stmt
+        .query_map(params, |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let count = conn.execute(&format!("DELETE FROM clip_items WHERE {}", condition), params)?;
+    if count > 0 {
+        conn.execute("DELETE FROM copy_events WHERE clip_id NOT IN (SELECT id FROM clip_items)", [])?;
+    }
+
+    let mut image_paths = Vec::new();
+    for path in candidates {
+        let still_used: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM clip_items WHERE image_path = ?1)",
+            [&path],
+            |row| row.get(0),
+        )?;
+        if !still_used {
+            image_paths.push(path);
+        }
+    }
+    Ok(Purged { count, image_paths })
 }
 
 pub struct Database {
     conn: Mutex<rusqlite::Connection>,
+    /// Separate read-only connection so list queries don't wait on the write mutex (WAL)
+    reader: Mutex<rusqlite::Connection>,
 }
 
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(5000);
+
 impl Database {
     pub fn new(db_path: &Path) -> Result<Self, rusqlite::Error> {
-        let conn = rusqlite::Connection::open(db_path)?;
+        let mut conn = rusqlite::Connection::open(db_path)?;
+        // WAL lets command handlers read while the monitor thread writes
+        conn.query_row("PRAGMA journal_mode=WAL", [], |row| row.get::<_, String>(0))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
         conn.execute_batch(
             "CREATE TABLE IF NOT EXISTS clip_items (
                 id TEXT PRIMARY KEY,
                 content TEXT NOT NULL,
-                content_hash TEXT NOT NULL UNIQUE,
+                content_hash TEXT NOT NULL,
                 clip_type TEXT NOT NULL DEFAULT 'text',
                 source_app TEXT,
                 image_path TEXT,
@@ -33,7 +363,7 @@ impl Database {
                 created_at TEXT NOT NULL
             );
             CREATE INDEX IF NOT EXISTS idx_clip_items_created_at ON clip_items(created_at DESC);
-            CREATE INDEX IF NOT EXISTS idx_clip_items_hash ON clip_items(content_hash);
+            CREATE INDEX IF NOT EXISTS idx_clip_items_hash_source ON clip_items(content_hash, source_app);
             CREATE INDEX IF NOT EXISTS idx_clip_items_type ON clip_items(clip_type);
 
             CREATE TABLE IF NOT EXISTS ai_providers (
@@ -52,12 +382,136 @@ impl Database {
                 prompt TEXT NOT NULL,
                 category TEXT NOT NULL DEFAULT 'general',
                 shortcut TEXT
-            );",
+            );
+
+            CREATE TABLE IF NOT EXISTS template_categories (
+                name TEXT PRIMARY KEY,
+                position INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS ai_results (
+                id TEXT PRIMARY KEY,
+                provider_id TEXT,
+                prompt TEXT NOT NULL,
+                content TEXT NOT NULL,
+                prompt_tokens INTEGER,
+                completion_tokens INTEGER,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_ai_results_created_at ON ai_results(created_at DESC);
+
+            CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS copy_events (
+                clip_id TEXT NOT NULL,
+                copied_at_ms INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_copy_events_clip ON copy_events(clip_id, copied_at_ms);",
         )?;
         // Migration: add api_key column if missing
         let _ = conn.execute("ALTER TABLE ai_providers ADD COLUMN api_key TEXT NOT NULL DEFAULT ''", []);
         // Migration: add shortcut column to templates if missing
         let _ = conn.execute("ALTER TABLE templates ADD COLUMN shortcut TEXT", []);
+        // Migration: image metadata columns (left NULL for existing rows)
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN image_width INTEGER", []);
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN image_height INTEGER", []);
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN image_bytes INTEGER", []);
+        // Migration: optional provider bound to a template
+        let _ = conn.execute("ALTER TABLE templates ADD COLUMN provider_id TEXT", []);
+        // Migration: per-provider request timeouts
+        let _ = conn.execute("ALTER TABLE ai_providers ADD COLUMN connect_timeout_secs INTEGER", []);
+        let _ = conn.execute("ALTER TABLE ai_providers ADD COLUMN read_timeout_secs INTEGER", []);
+        // Migration: optional per-provider proxy
+        let _ = conn.execute("ALTER TABLE ai_providers ADD COLUMN proxy_url TEXT", []);
+        // Migration: guessed language for code clips
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN code_language TEXT", []);
+        // Migration: soft delete (trash)
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN deleted_at TEXT", []);
+        // Migration: favorite flag (does not affect ordering)
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN is_favorite INTEGER NOT NULL DEFAULT 0", []);
+        // Migration: perceptual hash for near-duplicate image detection
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN phash INTEGER", []);
+        // Migration: original size of truncated text clips
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN original_length INTEGER", []);
+        // Migration: manual ordering of pinned clips, seeded from the old newest-first order
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN pin_order INTEGER", []);
+        conn.execute(
+            "UPDATE clip_items SET pin_order = (
+                SELECT COUNT(*) FROM clip_items AS newer
+                WHERE newer.is_pinned = 1 AND newer.created_at > clip_items.created_at
+            ) WHERE is_pinned = 1 AND pin_order IS NULL",
+            [],
+        )?;
+        // Migration: custom headers and request path for OpenAI-compatible gateways
+        let _ = conn.execute("ALTER TABLE ai_providers ADD COLUMN extra_headers TEXT", []);
+        let _ = conn.execute("ALTER TABLE ai_providers ADD COLUMN path_template TEXT", []);
+        // Migration: per-provider concurrent request limit
+        let _ = conn.execute("ALTER TABLE ai_providers ADD COLUMN max_concurrency INTEGER", []);
+        // Migration: api-version query parameter for Azure OpenAI
+        let _ = conn.execute("ALTER TABLE ai_providers ADD COLUMN api_version TEXT", []);
+        // Migration: optional post-processing of a template's AI output
+        let _ = conn.execute("ALTER TABLE templates ADD COLUMN post_process TEXT NOT NULL DEFAULT 'none'", []);
+        // Migration: content_hash is no longer UNIQUE so deduplication can be turned off.
+        // SQLite cannot drop a constraint, so the table is rebuilt once
+        let clip_schema: String = conn.query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'clip_items'",
+            [],
+            |row| row.get(0),
+        )?;
+        if clip_schema.contains("content_hash TEXT NOT NULL UNIQUE") {
+            // A failed statement drops the transaction, which rolls the rebuild back
+            let tx = conn.transaction()?;
+            tx.execute_batch(
+                "CREATE TABLE clip_items_new (
+                    id TEXT PRIMARY KEY,
+                    content TEXT NOT NULL,
+                    content_hash TEXT NOT NULL,
+                    clip_type TEXT NOT NULL DEFAULT 'text',
+                    source_app TEXT,
+                    image_path TEXT,
+                    is_pinned INTEGER NOT NULL DEFAULT 0,
+                    created_at TEXT NOT NULL,
+                    image_width INTEGER,
+                    image_height INTEGER,
+                    image_bytes INTEGER,
+                    code_language TEXT,
+                    deleted_at TEXT,
+                    is_favorite INTEGER NOT NULL DEFAULT 0,
+                    phash INTEGER,
+                    original_length INTEGER,
+                    pin_order INTEGER
+                );
+                INSERT INTO clip_items_new SELECT id, content, content_hash, clip_type, source_app, image_path, is_pinned, created_at, image_width, image_height, image_bytes, code_language, deleted_at, is_favorite, phash, original_length, pin_order FROM clip_items;
+                DROP TABLE clip_items;
+                ALTER TABLE clip_items_new RENAME TO clip_items;
+                CREATE INDEX idx_clip_items_created_at ON clip_items(created_at DESC);
+                CREATE INDEX idx_clip_items_hash ON clip_items(content_hash);
+                CREATE INDEX idx_clip_items_type ON clip_items(clip_type);",
+            )?;
+            tx.commit()?;
+        }
+        // Migration: page title fetched for URL clips
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN title TEXT", []);
+        // Migration: per-clip expiry
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN expires_at TEXT", []);
+        // Migration: number of times each clip was copied
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN copy_count INTEGER NOT NULL DEFAULT 1", []);
+        // Migration: integer creation time, so ordering doesn't depend on the RFC3339 offset format
+        let _ = conn.execute("ALTER TABLE clip_items ADD COLUMN created_at_ms INTEGER", []);
+        backfill_created_at_ms(&mut conn)?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_clip_items_created_at_ms ON clip_items(created_at_ms DESC)",
+            [],
+        )?;
+        // Migration: dedup can be scoped to the source app; the composite index also serves
+        // lookups by hash alone, so it replaces the old hash index
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_clip_items_hash_source ON clip_items(content_hash, source_app);
+            DROP INDEX IF EXISTS idx_clip_items_hash;",
+        )?;
 
         // Migration: clean up old preset templates, keep only tpl-translate
         conn.execute(
@@ -79,29 +533,53 @@ impl Database {
                 ('tpl-translate', '翻译', '请将以下内容翻译为中文（如已是中文则翻译为英文）：\n\n{{materials}}', 'general', 'CmdOrCtrl+Shift+T');",
             )?;
         }
+
+        let reader = rusqlite::Connection::open_with_flags(
+            db_path,
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        reader.busy_timeout(BUSY_TIMEOUT)?;
+
         Ok(Self {
             conn: Mutex::new(conn),
+            reader: Mutex::new(reader),
         })
     }
 
-    pub fn insert_clip(&self, item: &ClipItem) -> Result<bool, rusqlite::Error> {
+    /// Inserts a clip, returning false when an existing clip with the same hash (and, scoped
+    /// per app, the same source app) was bumped instead
+    pub fn insert_clip(&self, item: &ClipItem, dedup: DedupScope) -> Result<bool, rusqlite::Error> {
         let conn = self.conn.lock().unwrap();
-        // Check for duplicate by hash
-        let exists: bool = conn.query_row(
-            "SELECT EXISTS(SELECT 1 FROM clip_items WHERE content_hash = ?1)",
-            [&item.content_hash],
-            |row| row.get(0),
-        )?;
-        if exists {
-            // Update created_at to bump it to top
-            conn.execute(
-                "UPDATE clip_items SET created_at = ?1 WHERE content_hash = ?2",
-                rusqlite::params![&item.created_at, &item.content_hash],
-            )?;
-            return Ok(false);
+        if dedup != DedupScope::Off {
+            // Check for duplicate by hash
+            let existing: Option<String> = {
+                let mut stmt = conn.prepare(
+                    "SELECT id FROM clip_items WHERE content_hash = ?1 AND (?2 = 0 OR source_app IS ?3)
+                     ORDER BY created_at_ms DESC LIMIT 1",
+                )?;
+                let per_source = dedup == DedupScope::PerSourceApp;
+                let mut rows = stmt.query(rusqlite::params![&item.content_hash, per_source, &item.source_app])?;
+                match rows.next()? {
+                    Some(row) => Some(row.get(0)?),
+                    None => None,
+                }
+            };
+            if let Some(id) = existing {
+                // Update created_at to bump the newest copy to top (and pull it back out of the trash)
+                conn.execute(
+                    "UPDATE clip_items SET created_at = ?1, created_at_ms = ?2, deleted_at = NULL, copy_count = copy_count + 1 WHERE id = ?3",
+                    rusqlite::params![&item.created_at, timestamp_ms(&item.created_at), &id],
+                )?;
+                // Each re-copy is also recorded with its time, for the trending view
+                conn.execute(
+                    "INSERT INTO copy_events (clip_id, copied_at_ms) VALUES (?1, ?2)",
+                    rusqlite::params![&id, timestamp_ms(&item.created_at)],
+                )?;
+                return Ok(false);
+            }
         }
         conn.execute(
-            "INSERT INTO clip_items (id, content, content_hash, clip_type, source_app, image_path, is_pinned, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            "INSERT INTO clip_items (id, content, content_hash, clip_type, source_app, image_path, is_pinned, created_at, image_width, image_height, image_bytes, code_language, is_favorite, phash, original_length, created_at_ms) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
             rusqlite::params![
                 &item.id,
                 &item.content,
@@ -111,69 +589,641 @@ impl Database {
                 &item.image_path,
                 item.is_pinned as i32,
                 &item.created_at,
+                item.image_width,
+                item.image_height,
+                item.image_bytes,
+                &item.code_language,
+                item.is_favorite as i32,
+                item.phash,
+                item.original_length,
+                timestamp_ms(&item.created_at),
             ],
         )?;
         Ok(true)
     }
 
+    /// Finds a live image clip whose perceptual hash is within `max_distance` bits; scoped per
+    /// app, only among images copied from `source_app`
+    pub fn find_similar_image(
+        &self,
+        phash: i64,
+        max_distance: u32,
+        dedup: DedupScope,
+        source_app: Option<&str>,
+    ) -> Result<Option<String>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, phash FROM clip_items WHERE phash IS NOT NULL AND deleted_at IS NULL
+             AND (?1 = 0 OR source_app IS ?2)",
+        )?;
+        let per_source = dedup == DedupScope::PerSourceApp;
+        let mut best: Option<(String, u32)> = None;
+        let rows = stmt.query_map(rusqlite::params![per_source, source_app], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+        for row in rows {
+            let (id, other) = row?;
+            let distance = (phash ^ other).count_ones();
+            if distance <= max_distance && best.as_ref().map_or(true, |(_, d)| distance < *d) {
+                best = Some((id, distance));
+            }
+        }
+        Ok(best.map(|(id, _)| id))
+    }
+
+    /// Attaches an image to a clip that has none, e.g. the rendering that came with a rich text
+    /// copy. Returns false when the clip is gone or already has an image
+    pub fn attach_image(&self, id: &str, image: &ClipImage) -> Result<bool, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE clip_items SET image_path = ?1, image_width = ?2, image_height = ?3, image_bytes = ?4, phash = ?5
+             WHERE id = ?6 AND image_path IS NULL",
+            rusqlite::params![&image.path, image.width, image.height, image.bytes, image.phash, id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Distinct image files of bitmap clips, including clips in the trash. GIFs are left out
+    /// because re-encoding them would drop the animation
+    pub fn image_clip_paths(&self) -> Result<Vec<String>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT image_path FROM clip_items WHERE image_path IS NOT NULL AND clip_type != 'gif'",
+        )?;
+        let paths = stmt.query_map([], |row| row.get(0))?.collect::<Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+
+    /// Points every clip that uses `old_path` at a re-encoded file. Returns the number of clips updated
+    pub fn replace_image_path(&self, old_path: &str, new_path: &str, bytes: i64) -> Result<usize, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE clip_items SET image_path = ?1, image_bytes = ?2 WHERE image_path = ?3",
+            rusqlite::params![new_path, bytes, old_path],
+        )
+    }
+
+    /// Moves an existing clip to the top of the history without changing its content
+    pub fn bump_clip(&self, id: &str, created_at: &str) -> Result<bool, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE clip_items SET created_at = ?1, created_at_ms = ?2 WHERE id = ?3",
+            rusqlite::params![created_at, timestamp_ms(created_at), id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Records a re-copy of an existing clip, e.g. a near-duplicate image: moves it to the top and
+    /// counts it the same way `insert_clip` counts an exact duplicate
+    pub fn recopy_clip(&self, id: &str, created_at: &str) -> Result<bool, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE clip_items SET created_at = ?1, created_at_ms = ?2, copy_count = copy_count + 1 WHERE id = ?3",
+            rusqlite::params![created_at, timestamp_ms(created_at), id],
+        )?;
+        if updated > 0 {
+            conn.execute(
+                "INSERT INTO copy_events (clip_id, copied_at_ms) VALUES (?1, ?2)",
+                rusqlite::params![id, timestamp_ms(created_at)],
+            )?;
+        }
+        Ok(updated > 0)
+    }
+
     pub fn get_clips(
         &self,
-        search: Option<&str>,
-        clip_type: Option<&str>,
+        filter: &ClipFilter,
         limit: usize,
         offset: usize,
     ) -> Result<Vec<ClipItem>, rusqlite::Error> {
-        let conn = self.conn.lock().unwrap();
-        let mut sql = String::from(
-            "SELECT id, content, content_hash, clip_type, source_app, image_path, is_pinned, created_at FROM clip_items WHERE 1=1",
-        );
+        let conn = self.reader.lock().unwrap();
+        let mut sql = format!("SELECT {} FROM clip_items WHERE deleted_at IS NULL", CLIP_LIST_COLUMNS);
         let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+        push_filter_conditions(filter, &mut sql, &mut params);
+        match filter.sort {
+            Some("size") => sql.push_str(" ORDER BY is_pinned DESC, pin_order, image_bytes DESC, created_at_ms DESC"),
+            Some("most_used") => sql.push_str(" ORDER BY is_pinned DESC, pin_order, copy_count DESC, created_at_ms DESC"),
+            Some("frecency") => {
+                // created_at_ms moves forward on every re-copy, so it is the last time the clip was used.
+                // Hyperbolic decay keeps this in plain SQL, which lacks exp() without the math extension
+                sql.push_str(
+                    " ORDER BY is_pinned DESC, pin_order,
+                      copy_count * 1.0 / (1.0 + MAX(? - COALESCE(created_at_ms, 0), 0) * 1.0 / ?) DESC,
+                      created_at_ms DESC",
+                );
+                params.push(Box::new(chrono::Utc::now().timestamp_millis()));
+                params.push(Box::new(FRECENCY_HALF_LIFE_MS));
+            }
+            _ => sql.push_str(" ORDER BY is_pinned DESC, pin_order, created_at_ms DESC"),
+        }
+        sql.push_str(" LIMIT ? OFFSET ?");
+        params.push(Box::new(limit as i64));
+        params.push(Box::new(offset as i64));
 
-        if let Some(s) = search {
-            if !s.is_empty() {
-                sql.push_str(" AND content LIKE ?");
-                params.push(Box::new(format!("%{}%", s)));
+        let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let mut stmt = conn.prepare(&sql)?;
+        let mut items = stmt
+            .query_map(param_refs.as_slice(), clip_summary_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        let now = chrono::Utc::now();
+        // The preview has its whitespace collapsed, so the term is collapsed the same way to find it there
+        let search = filter
+            .search
+            .filter(|s| !s.is_empty())
+            .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" "));
+        for item in &mut items {
+            item.age_seconds = age_seconds(&item.created_at, now);
+            if let (Some(search), Some(preview)) = (search.as_deref(), item.preview.as_deref()) {
+                item.match_ranges = Some(match_ranges(preview, search));
             }
         }
-        if let Some(t) = clip_type {
-            if !t.is_empty() && t != "all" {
-                sql.push_str(" AND clip_type = ?");
-                params.push(Box::new(t.to_string()));
+        Ok(items)
+    }
+
+    /// First page of the unfiltered list in the default order, the same as `get_clips` with an
+    /// empty filter. Pinned clips are read separately so the rest can walk the created_at_ms
+    /// index instead of sorting the whole table
+    pub fn get_default_view(&self, limit: usize) -> Result<Vec<ClipItem>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut items = Vec::with_capacity(limit);
+        for condition in [
+            "is_pinned = 1 ORDER BY pin_order, created_at_ms DESC",
+            "is_pinned = 0 ORDER BY created_at_ms DESC",
+        ] {
+            let remaining = limit - items.len();
+            if remaining == 0 {
+                break;
             }
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM clip_items WHERE deleted_at IS NULL AND {} LIMIT ?1",
+                CLIP_LIST_COLUMNS, condition
+            ))?;
+            let page = stmt
+                .query_map([remaining as i64], clip_summary_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            items.extend(page);
         }
-        sql.push_str(" ORDER BY is_pinned DESC, created_at DESC LIMIT ? OFFSET ?");
+        let now = chrono::Utc::now();
+        for item in &mut items {
+            item.age_seconds = age_seconds(&item.created_at, now);
+        }
+        Ok(items)
+    }
+
+    /// Live clips re-copied since `since_ms`, most re-copies in that window first (ties go to the
+    /// most recent re-copy), so what is being reused right now outranks all-time favorites
+    pub fn get_trending_clips(
+        &self,
+        filter: &ClipFilter,
+        since_ms: i64,
+        limit: usize,
+    ) -> Result<Vec<ClipItem>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut sql = format!(
+            "SELECT {} FROM clip_items JOIN (
+                SELECT clip_id, COUNT(*) AS recent_copies, MAX(copied_at_ms) AS last_copied_ms
+                FROM copy_events WHERE copied_at_ms >= ? GROUP BY clip_id
+             ) ON clip_id = id
+             WHERE deleted_at IS NULL",
+            CLIP_LIST_COLUMNS
+        );
+        let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(since_ms)];
+        push_filter_conditions(filter, &mut sql, &mut params);
+        sql.push_str(" ORDER BY recent_copies DESC, last_copied_ms DESC LIMIT ?");
         params.push(Box::new(limit as i64));
-        params.push(Box::new(offset as i64));
 
         let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
         let mut stmt = conn.prepare(&sql)?;
-        let items = stmt
+        let mut items = stmt
+            .query_map(param_refs.as_slice(), clip_summary_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        let now = chrono::Utc::now();
+        for item in &mut items {
+            item.age_seconds = age_seconds(&item.created_at, now);
+        }
+        Ok(items)
+    }
+
+    /// Number of listed clips per calendar day in the system's local timezone, newest day first,
+    /// so the UI can show day sections and load each one with `after`/`before`
+    pub fn get_clip_days(&self, filter: &ClipFilter) -> Result<Vec<ClipDay>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut sql = String::from(
+            "SELECT date(created_at_ms / 1000, 'unixepoch', 'localtime') AS day, COUNT(*) FROM clip_items
+             WHERE deleted_at IS NULL AND created_at_ms IS NOT NULL",
+        );
+        let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+        push_filter_conditions(filter, &mut sql, &mut params);
+        sql.push_str(" GROUP BY day ORDER BY day DESC");
+
+        let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let mut stmt = conn.prepare(&sql)?;
+        let days = stmt
             .query_map(param_refs.as_slice(), |row| {
-                Ok(ClipItem {
-                    id: row.get(0)?,
-                    content: row.get(1)?,
-                    content_hash: row.get(2)?,
-                    clip_type: row.get(3)?,
-                    source_app: row.get(4)?,
-                    image_path: row.get(5)?,
-                    is_pinned: row.get::<_, i32>(6)? != 0,
-                    created_at: row.get(7)?,
+                Ok(ClipDay {
+                    day: row.get(0)?,
+                    count: row.get(1)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
-        Ok(items)
+        Ok(days)
+    }
+
+    /// Number of listed clips per type filter, honoring everything in `filter` except `clip_type`
+    /// so each filter tab can show its count. GIFs are counted under "image" like the filter does
+    pub fn get_type_counts(&self, filter: &ClipFilter) -> Result<HashMap<String, i64>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut sql = String::from(
+            "SELECT CASE clip_type WHEN 'gif' THEN 'image' ELSE clip_type END AS kind, COUNT(*)
+             FROM clip_items WHERE deleted_at IS NULL",
+        );
+        let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+        let filter = ClipFilter { clip_type: None, ..*filter };
+        push_filter_conditions(&filter, &mut sql, &mut params);
+        sql.push_str(" GROUP BY kind");
+
+        let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let mut stmt = conn.prepare(&sql)?;
+        let counts = stmt
+            .query_map(param_refs.as_slice(), |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(counts)
+    }
+
+    /// A single clip with its full content, including clips in the trash; None when the id is unknown
+    pub fn get_clip(&self, id: &str) -> Result<Option<ClipItem>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM clip_items WHERE id = ?1", CLIP_COLUMNS))?;
+        let mut rows = stmt.query([id])?;
+        match rows.next()? {
+            Some(row) => {
+                let mut item = clip_from_row(row)?;
+                item.age_seconds = age_seconds(&item.created_at, chrono::Utc::now());
+                Ok(Some(item))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Full content of a single clip, for list rows that only carry a preview
+    pub fn get_clip_content(&self, id: &str) -> Result<Option<String>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT content FROM clip_items WHERE id = ?1")?;
+        let mut rows = stmt.query([id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
     }
 
+    /// Stores the fetched page title of a clip; returns false if the clip does not exist
+    pub fn set_clip_title(&self, id: &str, title: Option<&str>) -> Result<bool, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute(
+            "UPDATE clip_items SET title = ?1 WHERE id = ?2",
+            rusqlite::params![title, id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Sets or clears (`None`) a clip's expiry time; returns false if the clip does not exist
+    pub fn set_clip_expiry(&self, id: &str, expires_at: Option<&str>) -> Result<bool, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute(
+            "UPDATE clip_items SET expires_at = ?1 WHERE id = ?2",
+            rusqlite::params![expires_at, id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Permanently deletes clips whose expiry time has passed, pinned or not
+    pub fn purge_expired(&self) -> Result<Purged, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        purge_where(&conn, "expires_at IS NOT NULL AND expires_at <= ?1", &[&now])
+    }
+
+    /// Content hash of the most recently captured image (including GIF) or non-image clip,
+    /// used to seed the clipboard monitor's last-seen hashes on startup
+    pub fn latest_clip_hash(&self, images: bool) -> Result<Option<String>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT content_hash FROM clip_items
+             WHERE (clip_type IN ('image', 'gif')) = ?1
+             ORDER BY created_at_ms DESC LIMIT 1",
+        )?;
+        let mut rows = stmt.query([images])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The most recently copied live clip that is not an image, with its full content
+    pub fn latest_text_clip(&self) -> Result<Option<ClipItem>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM clip_items
+             WHERE deleted_at IS NULL AND clip_type NOT IN ('image', 'gif')
+             ORDER BY created_at_ms DESC LIMIT 1",
+            CLIP_COLUMNS
+        ))?;
+        let mut rows = stmt.query([])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(clip_from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Moves a clip to the trash; it can be restored until the trash is emptied
     pub fn delete_clip(&self, id: &str) -> Result<(), rusqlite::Error> {
         let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM clip_items WHERE id = ?1", [id])?;
+        conn.execute(
+            "UPDATE clip_items SET deleted_at = ?1 WHERE id = ?2",
+            rusqlite::params![chrono::Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
+
+    /// Moves every live clip that is neither pinned nor a favorite to the trash, returning how
+    /// many were moved
+    pub fn trash_unpinned_clips(&self) -> Result<usize, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE clip_items SET deleted_at = ?1
+             WHERE deleted_at IS NULL AND is_pinned = 0 AND is_favorite = 0",
+            [chrono::Utc::now().to_rfc3339()],
+        )
+    }
+
+    /// Whether any live clip would be moved by `trash_unpinned_clips`
+    pub fn has_unpinned_clips(&self) -> Result<bool, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM clip_items WHERE deleted_at IS NULL AND is_pinned = 0 AND is_favorite = 0)",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    /// Trashes several clips in one transaction, returning how many were actually moved
+    pub fn delete_clips(&self, ids: &[String]) -> Result<usize, rusqlite::Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut deleted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "UPDATE clip_items SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+            )?;
+            for id in ids {
+                deleted += stmt.execute(rusqlite::params![&now, id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    pub fn get_trash(&self, limit: usize, offset: usize) -> Result<Vec<ClipItem>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM clip_items WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC LIMIT ?1 OFFSET ?2",
+            CLIP_LIST_COLUMNS
+        ))?;
+        let items = stmt
+            .query_map([limit as i64, offset as i64], clip_summary_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(items)
+    }
+
+    pub fn restore_clip(&self, id: &str) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE clip_items SET deleted_at = NULL WHERE id = ?1", [id])?;
         Ok(())
     }
 
+    pub fn empty_trash(&self) -> Result<Purged, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        purge_where(&conn, "deleted_at IS NOT NULL", &[])
+    }
+
+    /// Permanently deletes every clip, trashed ones included, in one transaction.
+    /// Pinned clips are kept unless `include_pinned`
+    pub fn clear_all_clips(&self, include_pinned: bool) -> Result<Purged, rusqlite::Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let condition = if include_pinned { "1 = 1" } else { "is_pinned = 0" };
+        let purged = purge_where(&tx, condition, &[])?;
+        tx.commit()?;
+        Ok(purged)
+    }
+
+    /// Every image file name still referenced by a clip (trashed ones included)
+    pub fn referenced_image_names(&self) -> Result<HashSet<String>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT image_path FROM clip_items WHERE image_path IS NOT NULL")?;
+        let paths = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(paths
+            .iter()
+            .filter_map(|p| Path::new(p).file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .collect())
+    }
+
+    /// Re-classifies every non-image clip with `detect`, which returns the clip type and
+    /// code language for a content. Runs in one transaction and returns how many rows changed
+    pub fn redetect_types(
+        &self,
+        detect: impl Fn(&str) -> (String, Option<String>),
+    ) -> Result<usize, rusqlite::Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut updated = 0;
+        {
+            let mut select = tx.prepare(
+                "SELECT id, content, clip_type, code_language FROM clip_items
+                 WHERE clip_type NOT IN ('image', 'gif')",
+            )?;
+            let rows = select
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut update =
+                tx.prepare("UPDATE clip_items SET clip_type = ?1, code_language = ?2 WHERE id = ?3")?;
+            for (id, content, clip_type, code_language) in rows {
+                let (new_type, new_language) = detect(&content);
+                if new_type != clip_type || new_language != code_language {
+                    update.execute(rusqlite::params![new_type, new_language, id])?;
+                    updated += 1;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    /// Replaces a text clip's content, hash, type and language with those of `item` (matched by
+    /// id) and clears its fetched title; `touch` also moves it to the top with `item.created_at`.
+    /// Other clips that now share the hash within the `dedup` scope are merged into it, carrying
+    /// over their pin and favorite. Returns false if there is no text clip with that id
+    pub fn update_clip_content(&self, item: &ClipItem, touch: bool, dedup: DedupScope) -> Result<bool, rusqlite::Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let changed = tx.execute(
+            "UPDATE clip_items SET content = ?1, content_hash = ?2, clip_type = ?3, code_language = ?4,
+                original_length = ?5, title = NULL,
+                created_at = CASE WHEN ?6 THEN ?7 ELSE created_at END,
+                created_at_ms = CASE WHEN ?6 THEN ?8 ELSE created_at_ms END
+             WHERE id = ?9 AND clip_type NOT IN ('image', 'gif')",
+            rusqlite::params![
+                &item.content,
+                &item.content_hash,
+                &item.clip_type,
+                &item.code_language,
+                item.original_length,
+                touch,
+                &item.created_at,
+                timestamp_ms(&item.created_at),
+                &item.id,
+            ],
+        )?;
+        if changed == 0 {
+            return Ok(false);
+        }
+        if let Some(duplicates_of) = dedup.duplicates_of() {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT is_pinned, is_favorite, pin_order FROM clip_items WHERE {}",
+                duplicates_of
+            ))?;
+            let duplicates = stmt
+                .query_map(rusqlite::params![&item.content_hash, &item.id], |row| {
+                    Ok((
+                        row.get::<_, i32>(0)? != 0,
+                        row.get::<_, i32>(1)? != 0,
+                        row.get::<_, Option<i64>>(2)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            tx.execute(
+                &format!(
+                    "UPDATE clip_items SET copy_count = copy_count + (
+                        SELECT COALESCE(SUM(copy_count), 0) FROM clip_items WHERE {}
+                     ) WHERE id = ?2",
+                    duplicates_of
+                ),
+                rusqlite::params![&item.content_hash, &item.id],
+            )?;
+            for (pinned, favorite, pin_order) in duplicates {
+                if favorite {
+                    tx.execute("UPDATE clip_items SET is_favorite = 1 WHERE id = ?1", [&item.id])?;
+                }
+                if pinned {
+                    tx.execute(
+                        "UPDATE clip_items SET is_pinned = 1, pin_order = ?1 WHERE id = ?2 AND is_pinned = 0",
+                        rusqlite::params![pin_order, &item.id],
+                    )?;
+                }
+            }
+            tx.execute(
+                &format!(
+                    "UPDATE copy_events SET clip_id = ?2 WHERE clip_id IN (
+                        SELECT id FROM clip_items WHERE {}
+                     )",
+                    duplicates_of
+                ),
+                rusqlite::params![&item.content_hash, &item.id],
+            )?;
+            tx.execute(
+                &format!("DELETE FROM clip_items WHERE {}", duplicates_of),
+                rusqlite::params![&item.content_hash, &item.id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Live text clips in `scope`, oldest first. The pinned and favorite scopes leave out clips
+    /// that are set to expire, since those are meant to be temporary
+    pub fn export_clips(&self, scope: ExportScope) -> Result<Vec<ExportedClip>, rusqlite::Error> {
+        let condition = match scope {
+            ExportScope::All => "1",
+            ExportScope::Pinned => "is_pinned = 1 AND expires_at IS NULL",
+            ExportScope::Favorites => "is_favorite = 1 AND expires_at IS NULL",
+        };
+        let conn = self.reader.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT content, clip_type, code_language, title, is_pinned, is_favorite, created_at
+             FROM clip_items
+             WHERE deleted_at IS NULL AND clip_type NOT IN ('image', 'gif') AND {}
+             ORDER BY created_at_ms ASC",
+            condition
+        ))?;
+        let clips = stmt
+            .query_map([], |row| {
+                Ok(ExportedClip {
+                    content: row.get(0)?,
+                    clip_type: row.get(1)?,
+                    code_language: row.get(2)?,
+                    title: row.get(3)?,
+                    is_pinned: row.get::<_, i32>(4)? != 0,
+                    is_favorite: row.get::<_, i32>(5)? != 0,
+                    created_at: row.get(6)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(clips)
+    }
+
+    /// Adds a clip read from an export file unless a live clip with the same hash already exists.
+    /// Pinned clips go to the end of the pinned list. Returns whether the clip was added
+    pub fn import_clip(&self, item: &ClipItem) -> Result<bool, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM clip_items WHERE content_hash = ?1 AND deleted_at IS NULL)",
+            [&item.content_hash],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Ok(false);
+        }
+        conn.execute(
+            &format!(
+                "INSERT INTO clip_items (id, content, content_hash, clip_type, code_language, title, original_length,
+                    is_pinned, pin_order, is_favorite, created_at, created_at_ms)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, CASE WHEN ?8 THEN {} END, ?9, ?10, ?11)",
+                NEXT_PIN_ORDER
+            ),
+            rusqlite::params![
+                &item.id,
+                &item.content,
+                &item.content_hash,
+                &item.clip_type,
+                &item.code_language,
+                &item.title,
+                item.original_length,
+                item.is_pinned as i32,
+                item.is_favorite as i32,
+                &item.created_at,
+                timestamp_ms(&item.created_at),
+            ],
+        )?;
+        Ok(true)
+    }
+
     pub fn toggle_pin(&self, id: &str) -> Result<bool, rusqlite::Error> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "UPDATE clip_items SET is_pinned = CASE WHEN is_pinned = 0 THEN 1 ELSE 0 END WHERE id = ?1",
+            &format!(
+                "UPDATE clip_items SET is_pinned = CASE WHEN is_pinned = 0 THEN 1 ELSE 0 END,
+                 pin_order = CASE WHEN is_pinned = 0 THEN {} ELSE NULL END WHERE id = ?1",
+                NEXT_PIN_ORDER
+            ),
             [id],
         )?;
         let pinned: bool = conn.query_row(
@@ -184,20 +1234,197 @@ impl Database {
         Ok(pinned)
     }
 
-    pub fn clear_old_clips(&self, keep_days: i64) -> Result<usize, rusqlite::Error> {
+    pub fn toggle_favorite(&self, id: &str) -> Result<bool, rusqlite::Error> {
         let conn = self.conn.lock().unwrap();
-        let cutoff = chrono::Utc::now() - chrono::Duration::days(keep_days);
-        let cutoff_str = cutoff.to_rfc3339();
-        let deleted = conn.execute(
-            "DELETE FROM clip_items WHERE is_pinned = 0 AND created_at < ?1",
-            [&cutoff_str],
+        conn.execute(
+            "UPDATE clip_items SET is_favorite = CASE WHEN is_favorite = 0 THEN 1 ELSE 0 END WHERE id = ?1",
+            [id],
         )?;
-        Ok(deleted)
+        let favorite: bool = conn.query_row(
+            "SELECT is_favorite FROM clip_items WHERE id = ?1",
+            [id],
+            |row| row.get::<_, i32>(0).map(|v| v != 0),
+        )?;
+        Ok(favorite)
     }
 
-    pub fn get_templates(&self) -> Result<Vec<Template>, rusqlite::Error> {
+    /// Sets a definite pin state on several clips in one transaction, returning the ids that exist
+    pub fn set_pinned(&self, ids: &[String], pinned: bool) -> Result<Vec<String>, rusqlite::Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut updated = Vec::new();
+        {
+            let sql = if pinned {
+                format!(
+                    "UPDATE clip_items SET pin_order = CASE WHEN is_pinned = 1 THEN pin_order ELSE {} END,
+                     is_pinned = 1 WHERE id = ?1",
+                    NEXT_PIN_ORDER
+                )
+            } else {
+                "UPDATE clip_items SET is_pinned = 0, pin_order = NULL WHERE id = ?1".to_string()
+            };
+            let mut stmt = tx.prepare(&sql)?;
+            for id in ids {
+                if stmt.execute([id])? > 0 {
+                    updated.push(id.clone());
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    /// Puts the given pinned clips first, in order; other pinned clips keep their relative order after them
+    pub fn reorder_pins(&self, ordered_ids: &[String]) -> Result<usize, rusqlite::Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE clip_items SET pin_order = pin_order + ?1 WHERE is_pinned = 1",
+            [ordered_ids.len() as i64],
+        )?;
+        let mut updated = 0;
+        {
+            let mut stmt =
+                tx.prepare("UPDATE clip_items SET pin_order = ?1 WHERE id = ?2 AND is_pinned = 1")?;
+            for (i, id) in ordered_ids.iter().enumerate() {
+                updated += stmt.execute(rusqlite::params![i as i64, id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    /// Purges unpinned, unfavorited clips older than `keep_days` (all ages kept when None)
+    /// and trashed clips older than `trash_keep_days`
+    pub fn clear_old_clips(&self, keep_days: Option<i64>, trash_keep_days: i64) -> Result<Purged, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut purged = Purged::default();
+        if let Some(keep_days) = keep_days {
+            let cutoff = (chrono::Utc::now() - chrono::Duration::days(keep_days)).timestamp_millis();
+            purged.merge(purge_where(
+                &conn,
+                "is_pinned = 0 AND is_favorite = 0 AND created_at_ms < ?1",
+                &[&cutoff],
+            )?);
+        }
+        // Trashed items are purged once they've sat in the trash long enough, pinned or not
+        let trash_cutoff = (chrono::Utc::now() - chrono::Duration::days(trash_keep_days)).to_rfc3339();
+        purged.merge(purge_where(
+            &conn,
+            "deleted_at IS NOT NULL AND deleted_at < ?1",
+            &[&trash_cutoff],
+        )?);
+        Ok(purged)
+    }
+
+    /// Purges unpinned, unfavorited clips older than their type's retention: the entry in
+    /// `keep_days_by_type` if there is one, otherwise `default_keep_days` (0 or None keeps the
+    /// type forever). Returns what was removed per clip type, leaving out types with nothing removed
+    pub fn clear_old_clips_by_type(
+        &self,
+        default_keep_days: Option<i64>,
+        keep_days_by_type: &HashMap<String, i64>,
+    ) -> Result<HashMap<String, Purged>, rusqlite::Error> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, prompt, category, shortcut FROM templates ORDER BY category, name")?;
+        let clip_types = conn
+            .prepare("SELECT DISTINCT clip_type FROM clip_items")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let now = chrono::Utc::now();
+        let mut purged = HashMap::new();
+        for clip_type in clip_types {
+            let keep_days = keep_days_by_type.get(&clip_type).copied().or(default_keep_days);
+            let Some(keep_days) = keep_days.filter(|&days| days > 0) else {
+                continue;
+            };
+            let cutoff = (now - chrono::Duration::days(keep_days)).timestamp_millis();
+            let removed = purge_where(
+                &conn,
+                "clip_type = ?1 AND is_pinned = 0 AND is_favorite = 0 AND created_at_ms < ?2",
+                &[&clip_type, &cutoff],
+            )?;
+            if removed.count > 0 {
+                purged.insert(clip_type, removed);
+            }
+        }
+        Ok(purged)
+    }
+
+    /// Purges the oldest unpinned, unfavorited clips so that at most `max_items` clips
+    /// remain outside the trash; pinned and favorite clips count toward the limit
+    pub fn trim_to_limit(&self, max_items: i64) -> Result<Purged, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        purge_where(
+            &conn,
+            "is_pinned = 0 AND is_favorite = 0 AND deleted_at IS NULL AND id NOT IN (
+                SELECT id FROM clip_items WHERE deleted_at IS NULL ORDER BY created_at_ms DESC LIMIT ?1
+            )",
+            &[&max_items],
+        )
+    }
+
+    pub fn get_stats(&self) -> Result<ClipStats, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let label_counts = |sql: &str, params: &[&dyn rusqlite::types::ToSql]| -> Result<Vec<LabelCount>, rusqlite::Error> {
+            let mut stmt = conn.prepare(sql)?;
+            let items = stmt
+                .query_map(params, |row| {
+                    Ok(LabelCount {
+                        label: row.get(0)?,
+                        count: row.get(1)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(items)
+        };
+
+        let total: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM clip_items WHERE deleted_at IS NULL",
+            [],
+            |row| row.get(0),
+        )?;
+        let by_type = label_counts(
+            "SELECT clip_type, COUNT(*) FROM clip_items WHERE deleted_at IS NULL
+             GROUP BY clip_type ORDER BY COUNT(*) DESC",
+            &[],
+        )?;
+        let cutoff = (chrono::Utc::now() - chrono::Duration::days(30)).timestamp_millis();
+        let per_day = label_counts(
+            "SELECT substr(created_at, 1, 10) AS day, COUNT(*) FROM clip_items
+             WHERE deleted_at IS NULL AND created_at_ms >= ?1
+             GROUP BY day ORDER BY day",
+            &[&cutoff],
+        )?;
+        let top_sources = label_counts(
+            "SELECT source_app, COUNT(*) FROM clip_items
+             WHERE deleted_at IS NULL AND source_app IS NOT NULL AND source_app != ''
+             GROUP BY source_app ORDER BY COUNT(*) DESC LIMIT 10",
+            &[],
+        )?;
+        // Trashed images still occupy disk until the trash is purged
+        let image_bytes: i64 = conn.query_row(
+            "SELECT COALESCE(SUM(image_bytes), 0) FROM clip_items WHERE image_path IS NOT NULL",
+            [],
+            |row| row.get(0),
+        )?;
+
+        Ok(ClipStats {
+            total,
+            by_type,
+            per_day,
+            top_sources,
+            image_bytes,
+        })
+    }
+
+    pub fn get_templates(&self) -> Result<Vec<Template>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        // Categories with an explicit position come first, the rest alphabetically
+        let mut stmt = conn.prepare(
+            "SELECT t.id, t.name, t.prompt, t.category, t.shortcut, t.provider_id, t.post_process FROM templates t
+             LEFT JOIN template_categories c ON c.name = t.category
+             ORDER BY c.position IS NULL, c.position, t.category, t.name",
+        )?;
         let items = stmt
             .query_map([], |row| {
                 Ok(Template {
@@ -206,6 +1433,8 @@ impl Database {
                     prompt: row.get(2)?,
                     category: row.get(3)?,
                     shortcut: row.get(4)?,
+                    provider_id: row.get(5)?,
+                    post_process: PostProcess::from_name(&row.get::<_, String>(6)?),
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
@@ -215,44 +1444,129 @@ impl Database {
     pub fn upsert_template(&self, tpl: &Template) -> Result<(), rusqlite::Error> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "INSERT OR REPLACE INTO templates (id, name, prompt, category, shortcut) VALUES (?1, ?2, ?3, ?4, ?5)",
-            rusqlite::params![&tpl.id, &tpl.name, &tpl.prompt, &tpl.category, &tpl.shortcut],
+            "INSERT OR REPLACE INTO templates (id, name, prompt, category, shortcut, provider_id, post_process) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                &tpl.id,
+                &tpl.name,
+                &tpl.prompt,
+                &tpl.category,
+                &tpl.shortcut,
+                &tpl.provider_id,
+                tpl.post_process.as_str()
+            ],
         )?;
         Ok(())
     }
 
+    pub fn get_template(&self, id: &str) -> Result<Option<Template>, rusqlite::Error> {
+        Ok(self.get_templates()?.into_iter().find(|t| t.id == id))
+    }
+
     pub fn delete_template(&self, id: &str) -> Result<(), rusqlite::Error> {
         let conn = self.conn.lock().unwrap();
         conn.execute("DELETE FROM templates WHERE id = ?1", [id])?;
         Ok(())
     }
 
-    pub fn get_providers(&self) -> Result<Vec<AiProvider>, rusqlite::Error> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, kind, endpoint, model, api_key, is_default FROM ai_providers ORDER BY name")?;
+    /// Distinct template categories with their template counts, in display order
+    pub fn get_categories(&self) -> Result<Vec<TemplateCategory>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT t.category, COUNT(*), c.position FROM templates t
+             LEFT JOIN template_categories c ON c.name = t.category
+             GROUP BY t.category
+             ORDER BY c.position IS NULL, c.position, t.category",
+        )?;
         let items = stmt
             .query_map([], |row| {
-                Ok(AiProvider {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    kind: row.get(2)?,
-                    endpoint: row.get(3)?,
-                    model: row.get(4)?,
-                    api_key: row.get(5)?,
-                    is_default: row.get::<_, i32>(6)? != 0,
+                Ok(TemplateCategory {
+                    name: row.get(0)?,
+                    count: row.get(1)?,
+                    position: row.get(2)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
         Ok(items)
     }
 
+    /// Replaces the stored category order; categories not listed fall back to alphabetical order
+    pub fn set_category_order(&self, names: &[String]) -> Result<(), rusqlite::Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM template_categories", [])?;
+        {
+            let mut stmt =
+                tx.prepare("INSERT OR IGNORE INTO template_categories (name, position) VALUES (?1, ?2)")?;
+            for (i, name) in names.iter().enumerate() {
+                stmt.execute(rusqlite::params![name, i as i64])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Moves every template in `from` to `to`, returning how many templates changed.
+    /// The renamed category keeps its position unless `to` already has one.
+    pub fn rename_category(&self, from: &str, to: &str) -> Result<usize, rusqlite::Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let updated = tx.execute("UPDATE templates SET category = ?2 WHERE category = ?1", [from, to])?;
+        tx.execute(
+            "UPDATE OR IGNORE template_categories SET name = ?2 WHERE name = ?1",
+            [from, to],
+        )?;
+        tx.execute("DELETE FROM template_categories WHERE name = ?1", [from])?;
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    pub fn get_providers(&self) -> Result<Vec<AiProvider>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let sql = format!("SELECT {} FROM ai_providers ORDER BY name", PROVIDER_COLUMNS);
+        let mut stmt = conn.prepare(&sql)?;
+        let items = stmt
+            .query_map([], provider_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(items)
+    }
+
+    pub fn get_provider(&self, id: &str) -> Result<Option<AiProvider>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let sql = format!("SELECT {} FROM ai_providers WHERE id = ?1", PROVIDER_COLUMNS);
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query([id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(provider_from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The provider flagged as default. Fails with QueryReturnedNoRows when none is flagged
+    /// and QueryReturnedMoreThanOneRow when the flag is set on several providers
+    pub fn get_default_provider(&self) -> Result<AiProvider, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let sql = format!("SELECT {} FROM ai_providers WHERE is_default = 1 LIMIT 2", PROVIDER_COLUMNS);
+        let mut stmt = conn.prepare(&sql)?;
+        let mut providers = stmt
+            .query_map([], provider_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        match providers.len() {
+            0 => Err(rusqlite::Error::QueryReturnedNoRows),
+            1 => Ok(providers.remove(0)),
+            _ => Err(rusqlite::Error::QueryReturnedMoreThanOneRow),
+        }
+    }
+
+    /// Saves a provider; clearing the previous default and setting the new one happen in
+    /// one transaction so there is never more than one default
     pub fn upsert_provider(&self, provider: &AiProvider) -> Result<(), rusqlite::Error> {
-        let conn = self.conn.lock().unwrap();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
         if provider.is_default {
-            conn.execute("UPDATE ai_providers SET is_default = 0", [])?;
+            tx.execute("UPDATE ai_providers SET is_default = 0 WHERE id != ?1", [&provider.id])?;
         }
-        conn.execute(
-            "INSERT OR REPLACE INTO ai_providers (id, name, kind, endpoint, model, api_key, is_default) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        tx.execute(
+            "INSERT OR REPLACE INTO ai_providers (id, name, kind, endpoint, model, api_key, is_default, connect_timeout_secs, read_timeout_secs, proxy_url, extra_headers, path_template, max_concurrency, api_version) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
             rusqlite::params![
                 &provider.id,
                 &provider.name,
@@ -261,9 +1575,20 @@ impl Database {
                 &provider.model,
                 &provider.api_key,
                 provider.is_default as i32,
+                provider.connect_timeout_secs.map(|v| v as i64),
+                provider.read_timeout_secs.map(|v| v as i64),
+                &provider.proxy_url,
+                provider
+                    .extra_headers
+                    .as_ref()
+                    .filter(|headers| !headers.is_empty())
+                    .and_then(|headers| serde_json::to_string(headers).ok()),
+                &provider.path_template,
+                provider.max_concurrency,
+                &provider.api_version,
             ],
         )?;
-        Ok(())
+        tx.commit()
     }
 
     pub fn delete_provider(&self, id: &str) -> Result<(), rusqlite::Error> {
@@ -271,6 +1596,109 @@ impl Database {
         conn.execute("DELETE FROM ai_providers WHERE id = ?1", [id])?;
         Ok(())
     }
+
+    pub fn insert_ai_result(&self, result: &AiResult) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO ai_results (id, provider_id, prompt, content, prompt_tokens, completion_tokens, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                &result.id,
+                &result.provider_id,
+                &result.prompt,
+                &result.content,
+                result.prompt_tokens,
+                result.completion_tokens,
+                &result.created_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Token usage aggregated per UTC day and provider, newest day first
+    pub fn get_usage_stats(&self, days: i64) -> Result<Vec<UsageStat>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let cutoff = (chrono::Utc::now() - chrono::Duration::days(days)).to_rfc3339();
+        let mut stmt = conn.prepare(
+            "SELECT substr(r.created_at, 1, 10) AS day, r.provider_id, p.name, COUNT(*),
+                    COALESCE(SUM(r.prompt_tokens), 0), COALESCE(SUM(r.completion_tokens), 0)
+             FROM ai_results r LEFT JOIN ai_providers p ON p.id = r.provider_id
+             WHERE r.created_at >= ?1
+             GROUP BY day, r.provider_id
+             ORDER BY day DESC, p.name",
+        )?;
+        let items = stmt
+            .query_map([&cutoff], |row| {
+                Ok(UsageStat {
+                    day: row.get(0)?,
+                    provider_id: row.get(1)?,
+                    provider_name: row.get(2)?,
+                    generations: row.get(3)?,
+                    prompt_tokens: row.get(4)?,
+                    completion_tokens: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(items)
+    }
+
+    /// Runs VACUUM and an integrity check. VACUUM rewrites the whole file and holds
+    /// the lock until it finishes, so callers should show progress while it runs.
+    pub fn maintain(&self) -> Result<MaintenanceReport, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let db_size = |conn: &rusqlite::Connection| -> Result<i64, rusqlite::Error> {
+            conn.query_row(
+                "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+                [],
+                |row| row.get(0),
+            )
+        };
+        let bytes_before = db_size(&conn)?;
+        conn.execute_batch("VACUUM")?;
+        let bytes_after = db_size(&conn)?;
+
+        let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+        let integrity_errors = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|msg| msg != "ok")
+            .collect();
+
+        Ok(MaintenanceReport {
+            bytes_before,
+            bytes_after,
+            reclaimed_bytes: (bytes_before - bytes_after).max(0),
+            integrity_errors,
+        })
+    }
+
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT value FROM settings WHERE key = ?1")?;
+        let mut rows = stmt.query([key])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_settings(&self) -> Result<HashMap<String, String>, rusqlite::Error> {
+        let conn = self.reader.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT key, value FROM settings")?;
+        let items = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(items)
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+            [key, value],
+        )?;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -280,15 +1708,143 @@ pub struct Template {
     pub prompt: String,
     pub category: String,
     pub shortcut: Option<String>,
+    #[serde(default)]
+    pub provider_id: Option<String>,
+    /// Applied to the final AI output generated with this template
+    #[serde(default)]
+    pub post_process: PostProcess,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateCategory {
+    pub name: String,
+    pub count: i64,
+    /// Explicit display position, None when the category was never ordered
+    pub position: Option<i64>,
+}
+
+/// Debug output masks the API key and header values so providers can be logged safely
+#[derive(Clone, Serialize, Deserialize)]
 pub struct AiProvider {
     pub id: String,
     pub name: String,
-    pub kind: String, // openai, claude, ollama, kimi, minimax
+    pub kind: String, // openai, claude, ollama, kimi, minimax, azure
     pub endpoint: String,
     pub model: String,
     pub api_key: String,
     pub is_default: bool,
+    #[serde(default)]
+    pub connect_timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub read_timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    /// Extra request headers, e.g. HTTP-Referer for OpenRouter or api-key for Azure
+    #[serde(default)]
+    pub extra_headers: Option<HashMap<String, String>>,
+    /// Overrides the generation path; `{model}` is replaced with the model name
+    #[serde(default)]
+    pub path_template: Option<String>,
+    /// Concurrent requests allowed against this provider; extra requests wait their turn
+    #[serde(default)]
+    pub max_concurrency: Option<u32>,
+    /// Azure OpenAI `api-version` query parameter; the default version is used when unset
+    #[serde(default)]
+    pub api_version: Option<String>,
+}
+
+impl std::fmt::Debug for AiProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AiProvider")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("kind", &self.kind)
+            .field("endpoint", &self.endpoint)
+            .field("model", &self.model)
+            .field("api_key", &crate::error::redact(&self.api_key))
+            .field("is_default", &self.is_default)
+            .field("connect_timeout_secs", &self.connect_timeout_secs)
+            .field("read_timeout_secs", &self.read_timeout_secs)
+            .field("proxy_url", &self.proxy_url.as_ref().map(|_| "****"))
+            .field("extra_headers", &self.extra_headers.as_ref().map(|h| h.keys().collect::<Vec<_>>()))
+            .field("path_template", &self.path_template)
+            .field("max_concurrency", &self.max_concurrency)
+            .field("api_version", &self.api_version)
+            .finish()
+    }
+}
+
+const PROVIDER_COLUMNS: &str = "id, name, kind, endpoint, model, api_key, is_default, connect_timeout_secs, read_timeout_secs, proxy_url, extra_headers, path_template, max_concurrency, api_version";
+
+fn provider_from_row(row: &rusqlite::Row) -> Result<AiProvider, rusqlite::Error> {
+    Ok(AiProvider {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        kind: row.get(2)?,
+        endpoint: row.get(3)?,
+        model: row.get(4)?,
+        api_key: row.get(5)?,
+        is_default: row.get::<_, i32>(6)? != 0,
+        connect_timeout_secs: row.get::<_, Option<i64>>(7)?.map(|v| v as u64),
+        read_timeout_secs: row.get::<_, Option<i64>>(8)?.map(|v| v as u64),
+        proxy_url: row.get(9)?,
+        extra_headers: row
+            .get::<_, Option<String>>(10)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        path_template: row.get(11)?,
+        max_concurrency: row.get(12)?,
+        api_version: row.get(13)?,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiResult {
+    pub id: String,
+    pub provider_id: Option<String>,
+    pub prompt: String,
+    pub content: String,
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageStat {
+    pub day: String,
+    pub provider_id: Option<String>,
+    pub provider_name: Option<String>,
+    pub generations: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub bytes_before: i64,
+    pub bytes_after: i64,
+    pub reclaimed_bytes: i64,
+    pub integrity_errors: Vec<String>,
+}
+
+/// Clips created on one local calendar day (`YYYY-MM-DD`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipDay {
+    pub day: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelCount {
+    pub label: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipStats {
+    pub total: i64,
+    pub by_type: Vec<LabelCount>,
+    /// Clips per UTC day over the last 30 days
+    pub per_day: Vec<LabelCount>,
+    pub top_sources: Vec<LabelCount>,
+    pub image_bytes: i64,
 }
@@ -0,0 +1,40 @@
+use tauri::Url;
+
+/// 注册的 URL scheme，与 tauri.conf.json 中 deep-link 插件的配置一致
+pub const SCHEME: &str = "pastego";
+
+/// pastego:// 链接对应的操作，供 Shortcuts、Raycast、Alfred 等调用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLink {
+    /// pastego://paste-last：把最近一条文本记录粘贴到当前应用
+    PasteLast,
+    /// pastego://template/<id>：对当前选中的文本运行模板，与模板快捷键相同
+    Template(String),
+    /// pastego://search?q=...：打开窗口并搜索历史
+    Search(String),
+}
+
+/// 解析链接；scheme 不符或操作未知时返回 None。
+/// pastego://paste-last 这类链接中操作名位于 host 部分，参数在路径和查询串中
+pub fn parse(url: &Url) -> Option<DeepLink> {
+    if url.scheme() != SCHEME {
+        return None;
+    }
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    match (url.host_str()?, segments.as_slice()) {
+        ("paste-last", []) => Some(DeepLink::PasteLast),
+        ("template", [id]) => Some(DeepLink::Template(id.to_string())),
+        ("search", []) => {
+            let query = url
+                .query_pairs()
+                .find(|(key, _)| key == "q")
+                .map(|(_, value)| value.into_owned())
+                .unwrap_or_default();
+            Some(DeepLink::Search(query))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,97 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+/// 命令返回给前端的错误，以 ErrorBody 序列化为 { code, message }，前端可按 code 做不同处理
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("No AI provider configured")]
+    NoProvider,
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error("Database is busy, please try again")]
+    DbLocked,
+    #[error("Database error: {0}")]
+    Database(rusqlite::Error),
+    #[error("{0}")]
+    Timeout(String),
+    #[error("{0}")]
+    Network(String),
+    #[error("API error {status}: {body}")]
+    Api { status: u16, body: String },
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NoProvider => "NoProvider",
+            AppError::NotFound(_) => "NotFound",
+            AppError::InvalidInput(_) => "InvalidInput",
+            AppError::DbLocked => "DbLocked",
+            AppError::Database(_) => "Database",
+            AppError::Timeout(_) => "Timeout",
+            AppError::Network(_) => "Network",
+            AppError::Api { .. } => "Api",
+            AppError::InvalidResponse(_) => "InvalidResponse",
+            AppError::Io(_) => "Io",
+            AppError::Other(_) => "Other",
+        }
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::DatabaseBusy) | Some(rusqlite::ErrorCode::DatabaseLocked) => {
+                AppError::DbLocked
+            }
+            _ => AppError::Database(e),
+        }
+    }
+}
+
+/// 错误返回给前端和本地 API 时的 { code, message } 格式
+pub struct ErrorBody<'a>(pub &'a AppError);
+
+impl Serialize for ErrorBody<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 2)?;
+        state.serialize_field("code", self.0.code())?;
+        state.serialize_field("message", &self.0.to_string())?;
+        state.end()
+    }
+}
+
+/// 命令返回的错误都经过这里交给前端，在这里统一记录一次日志
+impl From<AppError> for tauri::ipc::InvokeError {
+    fn from(e: AppError) -> Self {
+        log::warn!("Command failed ({}): {}", e.code(), e);
+        tauri::ipc::InvokeError::from(ErrorBody(&e))
+    }
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+/// 日志和错误信息中隐藏密钥，只保留末 4 位便于辨认是哪一个；过短的密钥全部隐藏
+pub fn redact(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() < 12 {
+        return "****".to_string();
+    }
+    format!("****{}", chars[chars.len() - 4..].iter().collect::<String>())
+}
+
+/// 把文本中出现的每个密钥替换为 redact 后的形式，空值忽略
+pub fn redact_all(text: &str, secrets: &[&str]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(text.to_string(), |text, secret| text.replace(secret, &redact(secret)))
+}
@@ -0,0 +1,145 @@
+use crate::db::Database;
+use crate::settings;
+
+/// 后端生成文本（占位符、托盘菜单、默认模板）使用的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    Zh,
+    En,
+}
+
+/// 所有支持的语言，用于识别未修改过的默认模板
+const ALL: [Locale; 2] = [Locale::Zh, Locale::En];
+
+/// 默认翻译模板的 id，与数据库初始化时插入的一致
+const TRANSLATE_TEMPLATE_ID: &str = "tpl-translate";
+
+impl Locale {
+    /// 解析 locale 设置值，无法识别时使用中文
+    pub fn from_setting(value: &str) -> Self {
+        match value {
+            "en" => Locale::En,
+            _ => Locale::Zh,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Locale::Zh => "zh",
+            Locale::En => "en",
+        }
+    }
+
+    /// 由系统语言标签（如 zh-Hans-CN、en_US.UTF-8）得到语言，中文之外都按英文处理
+    fn from_language_tag(tag: &str) -> Self {
+        if tag.to_ascii_lowercase().starts_with("zh") {
+            Locale::Zh
+        } else {
+            Locale::En
+        }
+    }
+}
+
+/// 当前设置的语言
+pub fn current(db: &Database) -> Locale {
+    Locale::from_setting(&settings::get(db, settings::LOCALE))
+}
+
+/// 系统首选语言：macOS 读取 NSLocale.preferredLanguages，其他平台读取 LANG 环境变量
+pub fn detect_system_locale() -> Locale {
+    #[cfg(target_os = "macos")]
+    {
+        use cocoa::base::{id, nil};
+        use cocoa::foundation::NSString;
+        unsafe {
+            let languages: id = msg_send![class!(NSLocale), preferredLanguages];
+            let first: id = msg_send![languages, firstObject];
+            if first != nil {
+                let tag = std::ffi::CStr::from_ptr(first.UTF8String()).to_string_lossy();
+                return Locale::from_language_tag(&tag);
+            }
+        }
+    }
+    std::env::var("LANG")
+        .map(|tag| Locale::from_language_tag(&tag))
+        .unwrap_or(Locale::Zh)
+}
+
+/// 图片条目在历史中显示的文本
+pub fn image_placeholder(locale: Locale, width: usize, height: usize) -> String {
+    match locale {
+        Locale::Zh => format!("[图片 {}x{}]", width, height),
+        Locale::En => format!("[Image {}x{}]", width, height),
+    }
+}
+
+/// 托盘菜单“显示 PasteGo”项，附带快捷键提示
+pub fn tray_show(locale: Locale, shortcut_label: &str) -> String {
+    match locale {
+        Locale::Zh => format!("显示 PasteGo  {}", shortcut_label),
+        Locale::En => format!("Show PasteGo  {}", shortcut_label),
+    }
+}
+
+pub fn tray_paste_last(locale: Locale) -> &'static str {
+    match locale {
+        Locale::Zh => "粘贴最近",
+        Locale::En => "Paste Last",
+    }
+}
+
+pub fn tray_pause(locale: Locale) -> &'static str {
+    match locale {
+        Locale::Zh => "暂停监听",
+        Locale::En => "Pause Monitoring",
+    }
+}
+
+pub fn tray_clear(locale: Locale) -> &'static str {
+    match locale {
+        Locale::Zh => "清空历史",
+        Locale::En => "Clear History",
+    }
+}
+
+pub fn tray_quit(locale: Locale) -> &'static str {
+    match locale {
+        Locale::Zh => "退出",
+        Locale::En => "Quit",
+    }
+}
+
+pub fn tray_tooltip(locale: Locale) -> &'static str {
+    match locale {
+        Locale::Zh => "PasteGo - 剪贴板 AI 助手",
+        Locale::En => "PasteGo - Clipboard AI assistant",
+    }
+}
+
+/// 默认翻译模板的名称和提示词
+fn translate_template(locale: Locale) -> (&'static str, &'static str) {
+    match locale {
+        Locale::Zh => ("翻译", "请将以下内容翻译为中文（如已是中文则翻译为英文）：\n\n{{materials}}"),
+        Locale::En => (
+            "Translate",
+            "Translate the following into English (if it is already English, translate it into Chinese):\n\n{{materials}}",
+        ),
+    }
+}
+
+/// 把默认翻译模板换成 locale 对应的版本；用户改过名称或提示词时保持不变
+pub fn localize_default_template(db: &Database, locale: Locale) -> Result<(), rusqlite::Error> {
+    let Some(mut template) = db.get_template(TRANSLATE_TEMPLATE_ID)? else {
+        return Ok(());
+    };
+    let unmodified = ALL
+        .iter()
+        .any(|&l| translate_template(l) == (template.name.as_str(), template.prompt.as_str()));
+    let (name, prompt) = translate_template(locale);
+    if unmodified && (template.name != name || template.prompt != prompt) {
+        template.name = name.to_string();
+        template.prompt = prompt.to_string();
+        db.upsert_template(&template)?;
+    }
+    Ok(())
+}
@@ -4,84 +4,710 @@ extern crate objc;
 mod ai;
 mod clipboard;
 mod db;
+mod deep_link;
+mod error;
+mod i18n;
+mod link_preview;
+mod local_api;
+mod logging;
+mod prompt;
+mod settings;
+mod sse;
+mod stream_parse;
+mod transform;
 
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::write::EncoderStringWriter;
+use base64::Engine;
 use db::{AiProvider, Database, Template};
+use error::{AppError, AppResult};
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicI32, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 /// 记录 PasteGo 唤起前最前台应用的 PID，用于粘贴时切回
 static PREVIOUS_APP_PID: AtomicI32 = AtomicI32::new(0);
+/// 同一应用的 bundle id，PID 失效（应用已重启）时用于重新查找
+static PREVIOUS_APP_BUNDLE_ID: Mutex<Option<String>> = Mutex::new(None);
 use tauri::{
     image::Image,
-    menu::{MenuBuilder, MenuItemBuilder},
+    menu::{CheckMenuItem, CheckMenuItemBuilder, MenuBuilder, MenuItem, MenuItemBuilder},
     tray::{MouseButton, TrayIconBuilder, TrayIconEvent},
     Emitter, Manager, EventTarget,
 };
 
+/// 检查并删除到期条目的间隔
+const EXPIRY_SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+/// 粘贴前等待目标应用回到前台的最长时间
+const REACTIVATE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(1500);
+/// 目标应用回到前台后，等待其窗口获得键盘焦点的时间
+const FOCUS_SETTLE_DELAY: std::time::Duration = std::time::Duration::from_millis(50);
+/// 内联生成时合并输出的间隔：太短会频繁粘贴，太长则失去逐段输出的感觉
+const INLINE_FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+/// 内联粘贴模式下每次 Cmd+V 后留给目标应用读取剪贴板的时间，之后才能写入下一批
+const INLINE_PASTE_SETTLE: std::time::Duration = std::time::Duration::from_millis(80);
+/// 保持窗口打开粘贴时，Cmd+V 之后等待目标应用处理按键，再把焦点切回 PasteGo
+const KEEP_OPEN_REFOCUS_DELAY: std::time::Duration = std::time::Duration::from_millis(150);
+/// 在托盘图标下方显示窗口时，窗口与图标之间的间距
+const TRAY_WINDOW_GAP: f64 = 4.0;
+/// 后台清理检查是否到期的间隔，也是启动后第一次清理的延迟
+const CLEANUP_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
 struct AppState {
     db: Arc<Database>,
+    app_data_dir: std::path::PathBuf,
+    db_path: std::path::PathBuf,
+    images_dir: std::path::PathBuf,
+    limiter: ai::ProviderLimiter,
+    local_api: local_api::LocalApi,
     #[allow(dead_code)]
     monitor: clipboard::ClipboardMonitor,
 }
 
+/// 托盘菜单中需要动态更新的菜单项
+struct TrayMenuState {
+    show: MenuItem<tauri::Wry>,
+    paste_last: MenuItem<tauri::Wry>,
+    pause: CheckMenuItem<tauri::Wry>,
+    clear: MenuItem<tauri::Wry>,
+    quit: MenuItem<tauri::Wry>,
+}
+
+/// 托盘图标的 id，用于切换语言时更新提示文字
+const TRAY_ID: &str = "main";
+
+/// 解析 RFC3339 时间参数为 Unix 毫秒，与 created_at_ms 列直接比较，不受时区写法影响
+fn parse_time_bound(name: &str, value: Option<String>) -> AppResult<Option<i64>> {
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(v) => chrono::DateTime::parse_from_rfc3339(v)
+            .map(|t| Some(t.timestamp_millis()))
+            .map_err(|e| {
+                AppError::InvalidInput(format!("Invalid {} \"{}\": expected RFC3339 ({})", name, v, e))
+            }),
+    }
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn get_clips(
     state: tauri::State<AppState>,
     search: Option<String>,
     clip_type: Option<String>,
+    favorites_only: Option<bool>,
+    sort: Option<String>,
+    after: Option<String>,
+    before: Option<String>,
     limit: Option<usize>,
     offset: Option<usize>,
-) -> Result<Vec<db::ClipItem>, String> {
+) -> AppResult<Vec<db::ClipItem>> {
+    let after = parse_time_bound("after", after)?;
+    let before = parse_time_bound("before", before)?;
+    let filter = db::ClipFilter {
+        search: search.as_deref(),
+        clip_type: clip_type.as_deref(),
+        favorites_only: favorites_only.unwrap_or(false),
+        sort: sort.as_deref(),
+        after,
+        before,
+    };
+    state
+        .db
+        .get_clips(&filter, limit.unwrap_or(100), offset.unwrap_or(0))
+        .map_err(AppError::from)
+}
+
+/// 默认视图一次返回的条数，与前端列表每次加载的数量一致
+const DEFAULT_VIEW_PAGE_SIZE: usize = 200;
+
+/// 默认视图（无搜索、无筛选、按时间排序）的第一页
+#[tauri::command]
+fn get_default_view(state: tauri::State<AppState>) -> AppResult<Vec<db::ClipItem>> {
+    Ok(state.db.get_default_view(DEFAULT_VIEW_PAGE_SIZE)?)
+}
+
+/// 趋势视图默认统计的天数
+const TRENDING_DAYS: i64 = 7;
+
+/// 最近 days 天（默认 7 天）内被重复复制最多的条目，筛选条件与 get_clips 相同
+#[tauri::command]
+fn get_trending_clips(
+    state: tauri::State<AppState>,
+    search: Option<String>,
+    clip_type: Option<String>,
+    favorites_only: Option<bool>,
+    days: Option<i64>,
+    limit: Option<usize>,
+) -> AppResult<Vec<db::ClipItem>> {
+    let days = days.unwrap_or(TRENDING_DAYS);
+    let since = chrono::Duration::try_days(days)
+        .filter(|_| days > 0)
+        .and_then(|d| chrono::Utc::now().checked_sub_signed(d))
+        .ok_or_else(|| AppError::InvalidInput("days must be a positive integer".to_string()))?;
+    let filter = db::ClipFilter {
+        search: search.as_deref(),
+        clip_type: clip_type.as_deref(),
+        favorites_only: favorites_only.unwrap_or(false),
+        ..Default::default()
+    };
     state
         .db
-        .get_clips(
-            search.as_deref(),
-            clip_type.as_deref(),
-            limit.unwrap_or(100),
-            offset.unwrap_or(0),
-        )
-        .map_err(|e| e.to_string())
+        .get_trending_clips(&filter, since.timestamp_millis(), limit.unwrap_or(100))
+        .map_err(AppError::from)
 }
 
+/// 按本地时区的日期统计条目数（新的在前），筛选条件与 get_clips 相同，供界面按天分组并分段加载
 #[tauri::command]
-fn delete_clip(state: tauri::State<AppState>, id: String) -> Result<(), String> {
-    state.db.delete_clip(&id).map_err(|e| e.to_string())
+fn get_clip_days(
+    state: tauri::State<AppState>,
+    search: Option<String>,
+    clip_type: Option<String>,
+    favorites_only: Option<bool>,
+) -> AppResult<Vec<db::ClipDay>> {
+    let filter = db::ClipFilter {
+        search: search.as_deref(),
+        clip_type: clip_type.as_deref(),
+        favorites_only: favorites_only.unwrap_or(false),
+        ..Default::default()
+    };
+    state.db.get_clip_days(&filter).map_err(AppError::from)
 }
 
+/// 各类型筛选标签对应的条目数（图片包含 GIF），与当前搜索词一致
 #[tauri::command]
-fn toggle_pin(state: tauri::State<AppState>, id: String) -> Result<bool, String> {
-    state.db.toggle_pin(&id).map_err(|e| e.to_string())
+fn get_type_counts(
+    state: tauri::State<AppState>,
+    search: Option<String>,
+    favorites_only: Option<bool>,
+) -> AppResult<HashMap<String, i64>> {
+    let filter = db::ClipFilter {
+        search: search.as_deref(),
+        favorites_only: favorites_only.unwrap_or(false),
+        ..Default::default()
+    };
+    state.db.get_type_counts(&filter).map_err(AppError::from)
 }
 
+/// 按 id 获取单个条目（含完整内容，回收站中的条目也能取到），不存在时返回 null
 #[tauri::command]
-fn clear_old_clips(state: tauri::State<AppState>, keep_days: i64) -> Result<usize, String> {
+fn get_clip(state: tauri::State<AppState>, id: String) -> AppResult<Option<db::ClipItem>> {
+    Ok(state.db.get_clip(&id)?)
+}
+
+#[tauri::command]
+fn get_clip_content(state: tauri::State<AppState>, id: String) -> AppResult<String> {
     state
         .db
-        .clear_old_clips(keep_days)
-        .map_err(|e| e.to_string())
+        .get_clip_content(&id)?
+        .ok_or_else(|| AppError::NotFound(format!("Clip {}", id)))
+}
+
+/// 手动添加一条记录，clip_type 为空时自动识别；返回是否新增（重复内容按去重设置合并）
+#[tauri::command]
+fn create_clip(
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    content: String,
+    clip_type: Option<String>,
+) -> AppResult<bool> {
+    clipboard::create_clip(&app, &state.db, content, clip_type)
 }
 
+/// 修改文本条目的内容，重新计算哈希并识别类型；touch 为 true 时同时移到最前。
+/// 开启去重时，与修改后内容相同的其他条目会合并进来（保留其置顶和收藏）。成功后发出 clip-updated 事件
 #[tauri::command]
-fn get_templates(state: tauri::State<AppState>) -> Result<Vec<db::Template>, String> {
-    state.db.get_templates().map_err(|e| e.to_string())
+fn update_clip_content(
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    id: String,
+    new_content: String,
+    touch: Option<bool>,
+) -> AppResult<()> {
+    let mut item = clipboard::prepare_text_clip(&state.db, new_content)?;
+    item.id = id;
+    let dedup = settings::get_dedup_scope(&state.db);
+    if !state.db.update_clip_content(&item, touch.unwrap_or(false), dedup)? {
+        return Err(AppError::NotFound(format!("Text clip {}", item.id)));
+    }
+    let _ = app.emit("clip-updated", &item.id);
+    Ok(())
 }
 
+/// 把条目移到历史最前（更新创建时间），不经过系统剪贴板，成功后发出 clipboard-bumped 事件
 #[tauri::command]
-fn get_providers(state: tauri::State<AppState>) -> Result<Vec<db::AiProvider>, String> {
-    state.db.get_providers().map_err(|e| e.to_string())
+fn bump_clip(app: tauri::AppHandle, state: tauri::State<AppState>, id: String) -> AppResult<()> {
+    if !state.db.bump_clip(&id, &chrono::Utc::now().to_rfc3339())? {
+        return Err(AppError::NotFound(format!("Clip {}", id)));
+    }
+    let _ = app.emit("clipboard-bumped", &id);
+    Ok(())
 }
 
+/// 抓取链接条目的网页标题并保存，返回标题（页面没有标题时为 null）
 #[tauri::command]
-fn save_provider(state: tauri::State<AppState>, provider: AiProvider) -> Result<(), String> {
+async fn fetch_url_metadata(state: tauri::State<'_, AppState>, id: String) -> AppResult<Option<String>> {
+    let url = state
+        .db
+        .get_clip_content(&id)?
+        .ok_or_else(|| AppError::NotFound(format!("Clip {}", id)))?;
+    let title = link_preview::fetch_title(&url).await?;
+    state.db.set_clip_title(&id, title.as_deref())?;
+    Ok(title)
+}
+
+#[tauri::command]
+fn delete_clip(state: tauri::State<AppState>, id: String) -> AppResult<()> {
+    Ok(state.db.delete_clip(&id)?)
+}
+
+#[tauri::command]
+fn delete_clips(state: tauri::State<AppState>, ids: Vec<String>) -> AppResult<usize> {
+    Ok(state.db.delete_clips(&ids)?)
+}
+
+#[tauri::command]
+fn get_trash(
+    state: tauri::State<AppState>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> AppResult<Vec<db::ClipItem>> {
     state
         .db
-        .upsert_provider(&provider)
-        .map_err(|e| e.to_string())
+        .get_trash(limit.unwrap_or(100), offset.unwrap_or(0))
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+fn restore_clip(state: tauri::State<AppState>, id: String) -> AppResult<()> {
+    Ok(state.db.restore_clip(&id)?)
+}
+
+#[tauri::command]
+fn empty_trash(state: tauri::State<AppState>) -> AppResult<usize> {
+    let purged = state.db.empty_trash()?;
+    clipboard::remove_image_files(&state.images_dir, &purged.image_paths);
+    Ok(purged.count)
+}
+
+/// clear_all_clips 要求前端传入的确认口令，防止误调用清空全部历史
+const CLEAR_ALL_CONFIRMATION: &str = "DELETE ALL";
+
+/// 导出文件的格式版本，导入时拒绝版本更高的文件
+const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// 导出文件的内容，JSON 格式
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ClipExportFile {
+    version: u32,
+    exported_at: String,
+    clips: Vec<db::ExportedClip>,
+}
+
+/// 导入结果：新增的条目数，以及因已有相同内容而跳过的条目数
+#[derive(serde::Serialize)]
+struct ImportSummary {
+    imported: usize,
+    skipped: usize,
+}
+
+/// 解析导出范围：all、pinned、favorites；默认 all
+fn parse_export_scope(scope: Option<&str>) -> AppResult<db::ExportScope> {
+    match scope.unwrap_or("all") {
+        "all" => Ok(db::ExportScope::All),
+        "pinned" => Ok(db::ExportScope::Pinned),
+        "favorites" => Ok(db::ExportScope::Favorites),
+        s if s.starts_with("tag:") => Err(AppError::InvalidInput(
+            "Exporting by tag is not supported: clips have no tags".to_string(),
+        )),
+        s => Err(AppError::InvalidInput(format!("Unknown export scope: {}", s))),
+    }
+}
+
+/// 把文本条目导出到 path。scope 为 pinned 或 favorites 时只导出置顶或收藏的条目（不含会过期的条目），
+/// 可作为常用片段包在其他电脑上导入。图片条目不导出。返回导出的条目数
+#[tauri::command]
+fn export_clips(state: tauri::State<AppState>, path: String, scope: Option<String>) -> AppResult<usize> {
+    let scope = parse_export_scope(scope.as_deref())?;
+    let file = ClipExportFile {
+        version: EXPORT_FORMAT_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        clips: state.db.export_clips(scope)?,
+    };
+    let json = serde_json::to_string_pretty(&file).map_err(|e| AppError::Other(e.to_string()))?;
+    std::fs::write(&path, json)?;
+    Ok(file.clips.len())
+}
+
+/// 从 export_clips 导出的文件导入条目，已有相同内容（按哈希）的条目跳过，保留置顶和收藏状态。
+/// 有新增时发出 clips-imported 事件，负载为新增的条目数
+#[tauri::command]
+fn import_clips(app: tauri::AppHandle, state: tauri::State<AppState>, path: String) -> AppResult<ImportSummary> {
+    let file: ClipExportFile = serde_json::from_slice(&std::fs::read(&path)?)
+        .map_err(|e| AppError::InvalidInput(format!("Not a PasteGo export file: {}", e)))?;
+    if file.version > EXPORT_FORMAT_VERSION {
+        return Err(AppError::InvalidInput(format!(
+            "Export file version {} is newer than supported ({})",
+            file.version, EXPORT_FORMAT_VERSION
+        )));
+    }
+    let mut summary = ImportSummary { imported: 0, skipped: 0 };
+    for exported in file.clips {
+        // 空内容或超出大小限制的条目跳过，不影响其余条目
+        let item = match clipboard::imported_clip(&state.db, exported) {
+            Ok(item) => item,
+            Err(_) => {
+                summary.skipped += 1;
+                continue;
+            }
+        };
+        if state.db.import_clip(&item)? {
+            summary.imported += 1;
+        } else {
+            summary.skipped += 1;
+        }
+    }
+    if summary.imported > 0 {
+        let _ = app.emit("clips-imported", summary.imported);
+    }
+    Ok(summary)
 }
 
+/// 永久删除全部历史（含回收站）及其图片，用于转让或交接电脑；include_pinned 为 false 时保留置顶条目。
+/// 完成后发出 clipboard-cleared 事件，返回删除的条目数
 #[tauri::command]
-fn delete_provider(state: tauri::State<AppState>, id: String) -> Result<(), String> {
-    state.db.delete_provider(&id).map_err(|e| e.to_string())
+fn clear_all_clips(
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    include_pinned: bool,
+    confirm: String,
+) -> AppResult<usize> {
+    if confirm != CLEAR_ALL_CONFIRMATION {
+        return Err(AppError::InvalidInput(format!(
+            "Type \"{}\" to confirm deleting the whole history",
+            CLEAR_ALL_CONFIRMATION
+        )));
+    }
+    let purged = state.db.clear_all_clips(include_pinned)?;
+    clipboard::remove_image_files(&state.images_dir, &purged.image_paths);
+    let _ = app.emit("clipboard-cleared", purged.count);
+    Ok(purged.count)
+}
+
+/// 暂停或恢复剪贴板监听；暂停期间复制的内容不会记录
+#[tauri::command]
+fn set_monitoring_paused(app: tauri::AppHandle, paused: bool) {
+    apply_monitoring_paused(&app, paused);
+}
+
+#[tauri::command]
+fn is_monitoring_paused() -> bool {
+    clipboard::is_monitoring_paused()
+}
+
+/// 清空历史：把未置顶、未收藏的条目移到回收站，返回移动的条目数
+#[tauri::command]
+fn trash_unpinned_clips(app: tauri::AppHandle, state: tauri::State<AppState>) -> AppResult<usize> {
+    trash_history(&app, &state.db)
+}
+
+/// 清理图片目录中不再被任何条目引用的文件
+#[tauri::command]
+fn cleanup_orphan_images(state: tauri::State<AppState>) -> AppResult<usize> {
+    let referenced = state.db.referenced_image_names()?;
+    Ok(clipboard::cleanup_orphan_images(&state.images_dir, &referenced)?)
+}
+
+#[tauri::command]
+fn toggle_pin(state: tauri::State<AppState>, id: String) -> AppResult<bool> {
+    Ok(state.db.toggle_pin(&id)?)
+}
+
+#[tauri::command]
+fn toggle_favorite(state: tauri::State<AppState>, id: String) -> AppResult<bool> {
+    Ok(state.db.toggle_favorite(&id)?)
+}
+
+#[tauri::command]
+fn set_pinned(
+    state: tauri::State<AppState>,
+    ids: Vec<String>,
+    pinned: bool,
+) -> AppResult<Vec<String>> {
+    Ok(state.db.set_pinned(&ids, pinned)?)
+}
+
+#[tauri::command]
+fn reorder_pins(state: tauri::State<AppState>, ordered_ids: Vec<String>) -> AppResult<usize> {
+    Ok(state.db.reorder_pins(&ordered_ids)?)
+}
+
+#[tauri::command]
+fn clear_old_clips(state: tauri::State<AppState>, keep_days: i64) -> AppResult<usize> {
+    let trash_keep_days = settings::get_i64(&state.db, settings::TRASH_KEEP_DAYS);
+    let purged = state
+        .db
+        .clear_old_clips(Some(keep_days), trash_keep_days)?;
+    clipboard::remove_image_files(&state.images_dir, &purged.image_paths);
+    Ok(purged.count)
+}
+
+/// 设置条目在 seconds 秒后自动删除（置顶也会删除），seconds 为空时取消；返回到期时间
+#[tauri::command]
+fn set_clip_expiry(
+    state: tauri::State<AppState>,
+    id: String,
+    seconds: Option<i64>,
+) -> AppResult<Option<String>> {
+    let expires_at = match seconds {
+        Some(s) => {
+            let at = chrono::Duration::try_seconds(s)
+                .filter(|_| s > 0)
+                .and_then(|d| chrono::Utc::now().checked_add_signed(d))
+                .ok_or_else(|| AppError::InvalidInput("seconds must be a positive integer".to_string()))?;
+            Some(at.to_rfc3339())
+        }
+        None => None,
+    };
+    if !state.db.set_clip_expiry(&id, expires_at.as_deref())? {
+        return Err(AppError::NotFound(format!("Clip {}", id)));
+    }
+    Ok(expires_at)
+}
+
+/// 定期删除已到期的条目，有删除时发出 clips-expired 事件（负载为删除数量）
+fn spawn_expiry_sweep(app: tauri::AppHandle, db: Arc<Database>, images_dir: std::path::PathBuf) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(EXPIRY_SWEEP_INTERVAL);
+        match db.purge_expired() {
+            Ok(purged) if purged.count > 0 => {
+                clipboard::remove_image_files(&images_dir, &purged.image_paths);
+                let _ = app.emit("clips-expired", purged.count);
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Failed to purge expired clips: {}", e),
+        }
+    });
+}
+
+/// cleanup-done 事件的负载：按保留天数（含回收站）和按条数上限删除的条目数
+#[derive(Clone, serde::Serialize)]
+struct CleanupDone {
+    old: usize,
+    over_limit: usize,
+    /// 按保留天数删除的条目按类型计数（不含回收站），没有删除的类型不出现
+    by_type: HashMap<String, usize>,
+}
+
+/// 按设置执行一次保留策略清理。每步分别加锁，中间不占用数据库，监听线程可以照常写入
+fn run_cleanup(db: &Database, images_dir: &std::path::Path) -> Result<CleanupDone, rusqlite::Error> {
+    let keep_days = settings::get_i64(db, settings::HISTORY_KEEP_DAYS);
+    let keep_days_by_type = settings::get_keep_days_by_type(db);
+    let old = db.clear_old_clips_by_type((keep_days > 0).then_some(keep_days), &keep_days_by_type)?;
+    let mut by_type = HashMap::new();
+    for (clip_type, purged) in old {
+        clipboard::remove_image_files(images_dir, &purged.image_paths);
+        by_type.insert(clip_type, purged.count);
+    }
+
+    let trash_keep_days = settings::get_i64(db, settings::TRASH_KEEP_DAYS);
+    let trashed = db.clear_old_clips(None, trash_keep_days)?;
+    clipboard::remove_image_files(images_dir, &trashed.image_paths);
+
+    let max_items = settings::get_i64(db, settings::MAX_HISTORY_ITEMS);
+    let over_limit = if max_items > 0 {
+        db.trim_to_limit(max_items)?
+    } else {
+        db::Purged::default()
+    };
+    clipboard::remove_image_files(images_dir, &over_limit.image_paths);
+
+    Ok(CleanupDone {
+        old: by_type.values().sum::<usize>() + trashed.count,
+        over_limit: over_limit.count,
+        by_type,
+    })
+}
+
+/// 后台定期清理，间隔按 CLEANUP_INTERVAL_HOURS 设置（每次检查时重新读取），
+/// 启动后稍等片刻执行第一次，避开启动时的数据库访问
+fn spawn_cleanup_task(app: tauri::AppHandle, db: Arc<Database>, images_dir: std::path::PathBuf) {
+    std::thread::spawn(move || {
+        let mut last_run: Option<std::time::Instant> = None;
+        loop {
+            std::thread::sleep(CLEANUP_CHECK_INTERVAL);
+            let hours = settings::get_i64(&db, settings::CLEANUP_INTERVAL_HOURS).max(1) as u64;
+            let interval = std::time::Duration::from_secs(hours * 3600);
+            if last_run.is_some_and(|t| t.elapsed() < interval) {
+                continue;
+            }
+            last_run = Some(std::time::Instant::now());
+            match run_cleanup(&db, &images_dir) {
+                Ok(done) => {
+                    if done.old > 0 || done.over_limit > 0 {
+                        log::info!("Cleanup removed {} old and {} over-limit clips", done.old, done.over_limit);
+                    }
+                    let _ = app.emit("cleanup-done", done);
+                }
+                Err(e) => log::warn!("Periodic cleanup failed: {}", e),
+            }
+        }
+    });
+}
+
+/// 数据存储位置和占用的字节数
+#[derive(serde::Serialize)]
+struct StorageInfo {
+    app_data_dir: String,
+    db_path: String,
+    images_dir: String,
+    db_bytes: u64,
+    images_bytes: u64,
+}
+
+/// 返回数据目录、数据库和图片目录的路径及大小，数据库大小包含 WAL 文件
+#[tauri::command]
+fn get_storage_info(state: tauri::State<AppState>) -> AppResult<StorageInfo> {
+    let file_size = |path: &std::path::Path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let mut wal_path = state.db_path.clone().into_os_string();
+    wal_path.push("-wal");
+    let images_bytes = match std::fs::read_dir(&state.images_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok()?.metadata().ok())
+            .filter(|meta| meta.is_file())
+            .map(|meta| meta.len())
+            .sum(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+        Err(e) => return Err(e.into()),
+    };
+    Ok(StorageInfo {
+        app_data_dir: state.app_data_dir.to_string_lossy().to_string(),
+        db_path: state.db_path.to_string_lossy().to_string(),
+        images_dir: state.images_dir.to_string_lossy().to_string(),
+        db_bytes: file_size(&state.db_path) + file_size(std::path::Path::new(&wal_path)),
+        images_bytes,
+    })
+}
+
+#[tauri::command]
+fn get_stats(state: tauri::State<AppState>) -> AppResult<db::ClipStats> {
+    Ok(state.db.get_stats()?)
+}
+
+/// 压缩数据库并检查完整性。VACUUM 执行期间数据库被锁定，前端应显示进度
+#[tauri::command]
+async fn maintain_db(state: tauri::State<'_, AppState>) -> AppResult<db::MaintenanceReport> {
+    let db = state.db.clone();
+    tokio::task::spawn_blocking(move || db.maintain())
+        .await
+        .map_err(|e| AppError::Other(e.to_string()))?
+        .map_err(AppError::from)
+}
+
+/// reencode-progress 事件的负载：已处理的图片数和总数
+#[derive(Clone, serde::Serialize)]
+struct ReencodeProgress {
+    done: usize,
+    total: usize,
+}
+
+/// 批量转换图片格式的结果，字节数只统计转换成功的图片
+#[derive(serde::Serialize)]
+struct ReencodeSummary {
+    converted: usize,
+    failed: usize,
+    bytes_before: u64,
+    bytes_after: u64,
+}
+
+/// 把已保存的图片（GIF 除外）转换为 format 格式：逐个写出新文件、更新数据库中的路径后再删除旧文件，
+/// 每处理一张发送一次 reencode-progress。单张失败时保留原文件，继续处理其余图片
+#[tauri::command]
+async fn reencode_images(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    format: String,
+    quality: Option<u8>,
+) -> AppResult<ReencodeSummary> {
+    if !["png", "jpeg", "webp"].contains(&format.as_str()) {
+        return Err(AppError::InvalidInput(format!("Unsupported image format: {}", format)));
+    }
+    let quality = quality
+        .map(i64::from)
+        .unwrap_or_else(|| settings::get_i64(&state.db, settings::IMAGE_QUALITY))
+        .clamp(1, 100) as u8;
+    let db = state.db.clone();
+    let images_dir = state.images_dir.clone();
+    tokio::task::spawn_blocking(move || -> AppResult<ReencodeSummary> {
+        let ext = clipboard::image_extension(&format);
+        let paths: Vec<String> = db
+            .image_clip_paths()?
+            .into_iter()
+            .filter(|path| std::path::Path::new(path).extension().and_then(|e| e.to_str()) != Some(ext))
+            .collect();
+        let total = paths.len();
+        let mut summary = ReencodeSummary { converted: 0, failed: 0, bytes_before: 0, bytes_after: 0 };
+        for (i, old_path) in paths.iter().enumerate() {
+            let old_size = std::fs::metadata(old_path).map(|m| m.len()).unwrap_or(0);
+            match clipboard::reencode_image(old_path, &format, quality) {
+                Some((new_path, size)) => match db.replace_image_path(old_path, &new_path, size) {
+                    Ok(_) => {
+                        clipboard::remove_image_files(&images_dir, std::slice::from_ref(old_path));
+                        summary.converted += 1;
+                        summary.bytes_before += old_size;
+                        summary.bytes_after += size as u64;
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to update re-encoded image {}: {}", old_path, e);
+                        clipboard::remove_image_files(&images_dir, &[new_path]);
+                        summary.failed += 1;
+                    }
+                },
+                None => {
+                    log::warn!("Failed to re-encode image {}", old_path);
+                    summary.failed += 1;
+                }
+            }
+            let _ = app.emit("reencode-progress", ReencodeProgress { done: i + 1, total });
+        }
+        Ok(summary)
+    })
+    .await
+    .map_err(|e| AppError::Other(e.to_string()))?
+}
+
+#[tauri::command]
+fn get_templates(state: tauri::State<AppState>) -> AppResult<Vec<db::Template>> {
+    Ok(state.db.get_templates()?)
+}
+
+#[tauri::command]
+fn get_providers(state: tauri::State<AppState>) -> AppResult<Vec<db::AiProvider>> {
+    Ok(state.db.get_providers()?)
+}
+
+/// 常用服务的供应商预设（地址、推荐模型、是否需要 API Key）
+#[tauri::command]
+fn get_provider_presets() -> Vec<ai::ProviderPreset> {
+    ai::PROVIDER_PRESETS.to_vec()
+}
+
+#[tauri::command]
+fn save_provider(state: tauri::State<AppState>, mut provider: AiProvider) -> AppResult<()> {
+    provider.endpoint = ai::normalize_endpoint(&provider.endpoint)?;
+    if let Some(proxy_url) = &provider.proxy_url {
+        ai::validate_proxy_url(proxy_url)?;
+    }
+    ai::validate_request_options(&provider)?;
+    state.db.upsert_provider(&provider)?;
+    logging::refresh_secrets(&state.db);
+    Ok(())
+}
+
+#[tauri::command]
+fn delete_provider(state: tauri::State<AppState>, id: String) -> AppResult<()> {
+    state.db.delete_provider(&id)?;
+    logging::refresh_secrets(&state.db);
+    Ok(())
 }
 
 #[tauri::command]
@@ -89,11 +715,11 @@ fn save_template(
     app: tauri::AppHandle,
     state: tauri::State<AppState>,
     template: Template,
-) -> Result<(), String> {
-    state
-        .db
-        .upsert_template(&template)
-        .map_err(|e| e.to_string())?;
+) -> AppResult<()> {
+    if let Some(shortcut) = template.shortcut.as_deref().filter(|s| !s.is_empty()) {
+        ensure_shortcut_free(&state.db, shortcut, None, Some(&template.id))?;
+    }
+    state.db.upsert_template(&template)?;
     register_template_shortcuts(&app, &state.db);
     Ok(())
 }
@@ -103,68 +729,436 @@ fn delete_template(
     app: tauri::AppHandle,
     state: tauri::State<AppState>,
     id: String,
-) -> Result<(), String> {
-    state.db.delete_template(&id).map_err(|e| e.to_string())?;
+) -> AppResult<()> {
+    state.db.delete_template(&id)?;
     register_template_shortcuts(&app, &state.db);
     Ok(())
 }
 
+/// 复制模板：新 ID、名称加“(副本)”，不复制快捷键以免冲突
 #[tauri::command]
-async fn ai_generate(
+fn duplicate_template(state: tauri::State<AppState>, id: String) -> AppResult<db::Template> {
+    let original = state
+        .db
+        .get_template(&id)?
+        .ok_or_else(|| AppError::NotFound(format!("Template {}", id)))?;
+    let copy = db::Template {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: format!("{} (副本)", original.name),
+        shortcut: None,
+        ..original
+    };
+    state.db.upsert_template(&copy)?;
+    Ok(copy)
+}
+
+#[tauri::command]
+fn get_categories(state: tauri::State<AppState>) -> AppResult<Vec<db::TemplateCategory>> {
+    Ok(state.db.get_categories()?)
+}
+
+#[tauri::command]
+fn set_category_order(state: tauri::State<AppState>, names: Vec<String>) -> AppResult<()> {
+    Ok(state.db.set_category_order(&names)?)
+}
+
+#[tauri::command]
+fn rename_category(state: tauri::State<AppState>, from: String, to: String) -> AppResult<usize> {
+    let to = to.trim();
+    if to.is_empty() {
+        return Err(AppError::InvalidInput("Category name cannot be empty".to_string()));
+    }
+    Ok(state.db.rename_category(&from, to)?)
+}
+
+#[tauri::command]
+fn get_settings(state: tauri::State<AppState>) -> AppResult<HashMap<String, String>> {
+    let mut values = settings::defaults();
+    values.extend(state.db.get_settings()?);
+    Ok(values)
+}
+
+#[tauri::command]
+fn set_setting(
     app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    key: String,
+    value: String,
+) -> AppResult<()> {
+    let value = settings::validate(&key, &value).map_err(AppError::InvalidInput)?;
+    let is_shortcut = key == settings::TOGGLE_SHORTCUT || key == settings::RESET_VIEW_SHORTCUT;
+    if is_shortcut && !value.is_empty() {
+        ensure_shortcut_free(&state.db, &value, Some(&key), None)?;
+    }
+    state.db.set_setting(&key, &value)?;
+    if is_shortcut {
+        register_template_shortcuts(&app, &state.db);
+    }
+    if key == settings::LOCALE {
+        i18n::localize_default_template(&state.db, i18n::Locale::from_setting(&value))?;
+    }
+    if key == settings::TOGGLE_SHORTCUT || key == settings::LOCALE {
+        update_tray_labels(&app, &state.db);
+    }
+    if key == settings::TRAY_CLICK_ACTION {
+        if let Some(tray) = app.tray_by_id(TRAY_ID) {
+            let _ = tray.set_show_menu_on_left_click(value == "menu");
+        }
+    }
+    if key == settings::ENABLE_LOCAL_API || key == settings::LOCAL_API_PORT {
+        local_api::restart(&app);
+    }
+    if key == settings::LOG_LEVEL {
+        logging::apply_level(&state.db);
+    }
+    Ok(())
+}
+
+/// 快捷键已被其他全局快捷键占用时返回错误；setting 和 template_id 指明正在修改的一方，不与自身比较
+fn ensure_shortcut_free(
+    db: &Database,
+    shortcut: &str,
+    setting: Option<&str>,
+    template_id: Option<&str>,
+) -> AppResult<()> {
+    for key in [settings::TOGGLE_SHORTCUT, settings::RESET_VIEW_SHORTCUT] {
+        if setting != Some(key) && settings::get(db, key) == shortcut {
+            return Err(AppError::InvalidInput(format!(
+                "Shortcut {} is already used by setting {}",
+                shortcut, key
+            )));
+        }
+    }
+    let templates = db.get_templates()?;
+    if let Some(tpl) = templates
+        .iter()
+        .find(|t| Some(t.id.as_str()) != template_id && t.shortcut.as_deref() == Some(shortcut))
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Shortcut {} is already used by template \"{}\"",
+            shortcut, tpl.name
+        )));
+    }
+    Ok(())
+}
+
+/// 日志文件的路径，便于用户附在问题反馈中；日志关闭时文件可能不存在
+#[tauri::command]
+fn get_log_path(state: tauri::State<AppState>) -> String {
+    logging::log_path(&state.app_data_dir).to_string_lossy().to_string()
+}
+
+/// 更换本地 API 的访问令牌，旧令牌立即失效；返回新令牌
+#[tauri::command]
+fn regenerate_local_api_token(state: tauri::State<AppState>) -> AppResult<String> {
+    let token = local_api::generate_token();
+    state.db.set_setting(settings::LOCAL_API_TOKEN, &token)?;
+    Ok(token)
+}
+
+#[tauri::command]
+async fn ai_generate(
+    window: tauri::WebviewWindow,
     state: tauri::State<'_, AppState>,
     provider_id: Option<String>,
+    template_id: Option<String>,
+    request_id: Option<String>,
+    image_paths: Option<Vec<String>>,
     prompt: String,
-) -> Result<String, String> {
-    let providers = state.db.get_providers().map_err(|e| e.to_string())?;
-    let provider = if let Some(pid) = provider_id {
-        providers.iter().find(|p| p.id == pid).cloned()
-    } else {
-        providers.iter().find(|p| p.is_default).cloned()
-    };
+) -> AppResult<String> {
+    let provider = resolve_provider(&state.db, provider_id, template_id.clone())?;
+    let images = image_paths
+        .unwrap_or_default()
+        .iter()
+        .map(|path| load_image_input(&state.images_dir, path))
+        .collect::<Result<Vec<_>, _>>()?;
+    // 流式事件只发给发起请求的窗口
+    let emitter = ai::StreamEmitter::new(window.app_handle().clone(), window.label(), request_id);
+    let _permit = state.limiter.acquire(&provider, || emitter.queued(&provider.id)).await;
+    let mut generation = ai::stream_generate(emitter, &provider, &prompt, &images).await?;
+    generation.content = post_process_output(&state.db, template_id.as_deref(), generation.content);
+    record_generation(&state.db, &provider, prompt, &generation);
+    Ok(generation.content)
+}
 
-    let provider = provider.ok_or("No AI provider configured. Please add one in Settings.")?;
+/// 高级模式：生成的同时把输出直接送入之前的前台应用，target 为 paste（剪贴板 + Cmd+V）或 type（模拟按键）。
+/// 需要在设置中开启 INLINE_GENERATION；主窗口先隐藏，ai-stream 事件照常发送，全部输出完成后返回完整结果
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn ai_generate_inline(
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, AppState>,
+    provider_id: Option<String>,
+    template_id: Option<String>,
+    target: InlineTarget,
+    request_id: Option<String>,
+    prompt: String,
+) -> AppResult<String> {
+    if !settings::get_bool(&state.db, settings::INLINE_GENERATION) {
+        return Err(AppError::InvalidInput("Inline generation is disabled in settings".to_string()));
+    }
+    let provider = resolve_provider(&state.db, provider_id, template_id)?;
+    let _ = window.hide();
 
-    ai::stream_generate(
-        app,
-        &provider.kind,
-        &provider.endpoint,
-        &provider.model,
-        &provider.api_key,
-        &prompt,
-    )
-    .await
+    let (tx, writer) = spawn_inline_writer(target);
+    let emitter = ai::StreamEmitter::new(window.app_handle().clone(), window.label(), request_id)
+        .with_sink(move |text| {
+            let _ = tx.send(text.to_string());
+        });
+    let _permit = state.limiter.acquire(&provider, || emitter.queued(&provider.id)).await;
+    // emitter 在生成结束时释放，写入线程随之输出剩余文本并退出
+    let generation = ai::stream_generate(emitter, &provider, &prompt, &[]).await;
+    let _ = tauri::async_runtime::spawn_blocking(move || writer.join()).await;
+    let generation = generation?;
+    record_generation(&state.db, &provider, prompt, &generation);
+    Ok(generation.content)
 }
 
+/// 非流式生成，适合只需要最终结果的简短模板输出
 #[tauri::command]
-fn read_image_base64(path: String) -> Result<String, String> {
-    use std::fs;
-    let data = fs::read(&path).map_err(|e| format!("Failed to read image: {}", e))?;
-    Ok(base64_encode(&data))
+async fn ai_generate_sync(
+    state: tauri::State<'_, AppState>,
+    provider_id: Option<String>,
+    template_id: Option<String>,
+    prompt: String,
+) -> AppResult<String> {
+    let provider = resolve_provider(&state.db, provider_id, template_id.clone())?;
+    let _permit = state.limiter.acquire(&provider, || {}).await;
+    let mut generation = ai::generate(&provider, &prompt).await?;
+    generation.content = post_process_output(&state.db, template_id.as_deref(), generation.content);
+    record_generation(&state.db, &provider, prompt, &generation);
+    Ok(generation.content)
 }
 
-fn base64_encode(data: &[u8]) -> String {
-    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-    let mut result = String::new();
-    for chunk in data.chunks(3) {
-        let b0 = chunk[0] as u32;
-        let b1 = if chunk.len() > 1 { chunk[1] as u32 } else { 0 };
-        let b2 = if chunk.len() > 2 { chunk[2] as u32 } else { 0 };
-        let n = (b0 << 16) | (b1 << 8) | b2;
-        result.push(CHARS[(n >> 18 & 63) as usize] as char);
-        result.push(CHARS[(n >> 12 & 63) as usize] as char);
-        if chunk.len() > 1 {
-            result.push(CHARS[(n >> 6 & 63) as usize] as char);
-        } else {
-            result.push('=');
+/// 批量生成中单个条目的结果，失败时 content 为空、error 为原因
+#[derive(Clone, serde::Serialize)]
+struct BatchItemResult {
+    clip_id: String,
+    content: Option<String>,
+    error: Option<String>,
+}
+
+/// ai-batch-progress 事件的负载：每完成一个条目发送一次，带上该条目的结果
+#[derive(Clone, serde::Serialize)]
+struct BatchProgress {
+    request_id: Option<String>,
+    index: usize,
+    done: usize,
+    total: usize,
+    result: BatchItemResult,
+}
+
+/// 对每个条目单独运行模板，而不是把所有素材合并进同一个 {{materials}}。
+/// 各条目并发请求，受供应商的并发上限约束；单个条目失败不影响其余条目。
+/// 返回结果与 clip_ids 顺序一致
+#[tauri::command]
+async fn ai_generate_batch(
+    window: tauri::WebviewWindow,
+    state: tauri::State<'_, AppState>,
+    template_id: String,
+    clip_ids: Vec<String>,
+    provider_id: Option<String>,
+    request_id: Option<String>,
+) -> AppResult<Vec<BatchItemResult>> {
+    let template = state
+        .db
+        .get_template(&template_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Template {}", template_id)))?;
+    let provider = resolve_provider(&state.db, provider_id, Some(template_id.clone()))?;
+    let total = clip_ids.len();
+    let done = std::sync::atomic::AtomicUsize::new(0);
+
+    let tasks = clip_ids.into_iter().enumerate().map(|(index, clip_id)| {
+        let (state, template, provider, done) = (&*state, &template, &provider, &done);
+        let (window, request_id) = (&window, &request_id);
+        async move {
+            let result = match generate_for_clip(state, template, provider, &clip_id).await {
+                Ok(content) => BatchItemResult { clip_id, content: Some(content), error: None },
+                Err(e) => {
+                    // 单个条目的错误随结果返回而不经过命令边界，需要单独记录
+                    log::warn!("Batch generation for clip {} with {} failed: {}", clip_id, provider.name, e);
+                    BatchItemResult { clip_id, content: None, error: Some(e.to_string()) }
+                }
+            };
+            let progress = BatchProgress {
+                request_id: request_id.clone(),
+                index,
+                done: done.fetch_add(1, Ordering::SeqCst) + 1,
+                total,
+                result: result.clone(),
+            };
+            let _ = window.emit_to(EventTarget::webview_window(window.label()), "ai-batch-progress", progress);
+            result
         }
-        if chunk.len() > 2 {
-            result.push(CHARS[(n & 63) as usize] as char);
-        } else {
-            result.push('=');
+    });
+    Ok(futures_util::future::join_all(tasks).await)
+}
+
+/// 以单个条目作为素材运行模板，供 ai_generate_batch 使用
+async fn generate_for_clip(
+    state: &AppState,
+    template: &Template,
+    provider: &AiProvider,
+    clip_id: &str,
+) -> AppResult<String> {
+    let clip = state
+        .db
+        .get_clip(clip_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Clip {}", clip_id)))?;
+    if matches!(clip.clip_type.as_str(), "image" | "gif") {
+        return Err(AppError::InvalidInput("Image clips cannot be generated in a batch".to_string()));
+    }
+    let material = prompt::Material { clip_type: clip.clip_type, content: clip.content };
+    let prompt = prompt::render_template(Some(&template.prompt), &[material], "");
+    let _permit = state.limiter.acquire(provider, || {}).await;
+    let mut generation = ai::generate(provider, &prompt).await?;
+    generation.content = post_process_output(&state.db, Some(&template.id), generation.content);
+    record_generation(&state.db, provider, prompt, &generation);
+    Ok(generation.content)
+}
+
+/// 按模板设置对最终输出做后处理；处理失败（如输出不是合法 JSON）时记录警告并保留原文。
+/// 内联生成的输出在生成过程中已经写入目标应用，因此不做后处理
+fn post_process_output(db: &Database, template_id: Option<&str>, content: String) -> String {
+    let post_process = match template_id.map(|id| db.get_template(id)) {
+        Some(Ok(Some(template))) => template.post_process,
+        _ => return content,
+    };
+    match post_process.apply(&content) {
+        Ok(processed) => processed,
+        Err(e) => {
+            log::warn!("Post-processing ({}) skipped: {}", post_process.as_str(), e);
+            content
         }
     }
-    result
+}
+
+/// 选择供应商：显式指定 > 模板绑定（已删除则忽略）> 默认供应商
+fn resolve_provider(
+    db: &Database,
+    provider_id: Option<String>,
+    template_id: Option<String>,
+) -> AppResult<AiProvider> {
+    if let Some(pid) = provider_id {
+        return db.get_provider(&pid)?.ok_or(AppError::NoProvider);
+    }
+    let bound = match template_id {
+        Some(tid) => db.get_template(&tid)?.and_then(|t| t.provider_id),
+        None => None,
+    };
+    if let Some(provider) = bound.map(|pid| db.get_provider(&pid)).transpose()?.flatten() {
+        return Ok(provider);
+    }
+    default_provider(db)
+}
+
+/// 默认供应商：没有设置时返回 NoProvider，同时存在多个默认时报错而不是任选一个
+fn default_provider(db: &Database) -> AppResult<AiProvider> {
+    db.get_default_provider().map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => AppError::NoProvider,
+        rusqlite::Error::QueryReturnedMoreThanOneRow => {
+            AppError::InvalidInput("More than one default AI provider is configured".to_string())
+        }
+        e => AppError::from(e),
+    })
+}
+
+#[tauri::command]
+fn get_default_provider(state: tauri::State<AppState>) -> AppResult<AiProvider> {
+    default_provider(&state.db)
+}
+
+fn record_generation(db: &Database, provider: &AiProvider, prompt: String, generation: &ai::Generation) {
+    let _ = db.insert_ai_result(&db::AiResult {
+        id: uuid::Uuid::new_v4().to_string(),
+        provider_id: Some(provider.id.clone()),
+        prompt,
+        content: generation.content.clone(),
+        prompt_tokens: generation.usage.prompt_tokens,
+        completion_tokens: generation.usage.completion_tokens,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+#[tauri::command]
+async fn test_provider(provider: AiProvider) -> AppResult<String> {
+    ai::test_provider(&provider).await
+}
+
+#[tauri::command]
+async fn list_ollama_models(endpoint: String) -> AppResult<Vec<String>> {
+    ai::list_ollama_models(&endpoint).await
+}
+
+/// 用当前的识别规则重新判断所有文本条目的类型，返回发生变化的条目数
+#[tauri::command]
+fn redetect_types(state: tauri::State<AppState>) -> AppResult<usize> {
+    Ok(state.db.redetect_types(clipboard::classify)?)
+}
+
+#[tauri::command]
+fn get_usage_stats(
+    state: tauri::State<AppState>,
+    days: Option<i64>,
+) -> AppResult<Vec<db::UsageStat>> {
+    state
+        .db
+        .get_usage_stats(days.unwrap_or(30))
+        .map_err(AppError::from)
+}
+
+/// 读取图片并返回 data URL，MIME 类型由文件扩展名决定
+#[tauri::command]
+fn read_image_base64(path: String) -> AppResult<String> {
+    let mut file = std::fs::File::open(&path)?;
+    Ok(base64_encode_reader(format!("data:{};base64,", image_mime(&path)), &mut file)?)
+}
+
+/// 读取图片剪贴条目作为视觉输入，只允许读取图片目录中的文件
+fn load_image_input(images_dir: &std::path::Path, path: &str) -> AppResult<ai::ImageInput> {
+    let dir = images_dir.canonicalize()?;
+    let file = std::path::Path::new(path).canonicalize()?;
+    if !file.starts_with(&dir) {
+        return Err(AppError::InvalidInput(format!("Image is not a saved clip: {}", path)));
+    }
+    let data = std::fs::read(&file)?;
+    Ok(ai::ImageInput {
+        media_type: image_mime(path).to_string(),
+        data: base64_encode(&data),
+    })
+}
+
+fn image_mime(path: &str) -> &'static str {
+    let ext = std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "gif" => "image/gif",
+        "jpg" | "jpeg" => "image/jpeg",
+        "webp" => "image/webp",
+        _ => "image/png",
+    }
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    BASE64.encode(data)
+}
+
+/// 以流的方式编码 reader 中的数据并追加到 prefix 之后，避免先把整个文件读入内存再复制
+fn base64_encode_reader(prefix: String, reader: &mut impl std::io::Read) -> std::io::Result<String> {
+    let mut writer = EncoderStringWriter::from_consumer(prefix, &BASE64);
+    std::io::copy(reader, &mut writer)?;
+    Ok(writer.into_inner())
+}
+
+/// base64_encode 的逆操作，用于测试编码结果可以原样还原
+#[cfg(test)]
+fn base64_decode(text: &str) -> AppResult<Vec<u8>> {
+    BASE64
+        .decode(text.trim())
+        .map_err(|e| AppError::InvalidInput(format!("Invalid base64: {}", e)))
 }
 
 /// Simulate Cmd+V keypress using macOS CGEvent API
@@ -187,41 +1181,403 @@ fn simulate_cmd_v() {
     }
 }
 
+/// 用 CGEvent 模拟键盘输入文本。每个事件携带的字符数有限，按 UTF-16 长度分段发送，换行用回车键输入
+fn simulate_typing(text: &str) {
+    use core_graphics::event::{CGEvent, CGEventTapLocation, CGKeyCode};
+    use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+
+    // 单个键盘事件可靠携带的最多 UTF-16 单元数
+    const MAX_UNITS_PER_EVENT: usize = 20;
+    const KEY_RETURN: CGKeyCode = 36;
+
+    let Ok(source) = CGEventSource::new(CGEventSourceStateID::HIDSystemState) else {
+        return;
+    };
+    let post_key = |key: CGKeyCode, text: Option<&str>| {
+        for down in [true, false] {
+            if let Ok(event) = CGEvent::new_keyboard_event(source.clone(), key, down) {
+                if let Some(text) = text {
+                    event.set_string(text);
+                }
+                event.post(CGEventTapLocation::HID);
+            }
+        }
+    };
+
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            post_key(KEY_RETURN, None);
+        }
+        let mut segment = String::new();
+        let mut units = 0;
+        for c in line.chars().filter(|&c| c != '\r') {
+            if units + c.len_utf16() > MAX_UNITS_PER_EVENT {
+                post_key(0, Some(&segment));
+                segment.clear();
+                units = 0;
+            }
+            segment.push(c);
+            units += c.len_utf16();
+        }
+        if !segment.is_empty() {
+            post_key(0, Some(&segment));
+        }
+    }
+}
+
+/// 播放系统提示音 Tink，提示粘贴已执行；非 macOS 不做任何事
+fn play_paste_sound() {
+    #[cfg(target_os = "macos")]
+    unsafe {
+        use cocoa::foundation::NSString;
+        let name = NSString::alloc(cocoa::base::nil).init_str("Tink");
+        let sound: cocoa::base::id = msg_send![class!(NSSound), soundNamed: name];
+        let _: () = msg_send![name, release];
+        if sound != cocoa::base::nil {
+            let _: cocoa::base::BOOL = msg_send![sound, play];
+        }
+    }
+}
+
+#[tauri::command]
+async fn copy_and_paste(app: tauri::AppHandle, content: String) -> AppResult<()> {
+    paste_to_previous_app(app, &content, false)
+}
+
+/// 粘贴但不隐藏窗口：切到之前的应用粘贴后再回到 PasteGo，便于连续粘贴多个条目
 #[tauri::command]
-async fn copy_and_paste(app: tauri::AppHandle, content: String) -> Result<(), String> {
+async fn copy_paste_keep_open(app: tauri::AppHandle, content: String) -> AppResult<()> {
+    paste_to_previous_app(app, &content, true)
+}
+
+/// 粘贴前将 JSON/XML 内容重新缩进，其他内容原样粘贴
+#[tauri::command]
+async fn copy_and_paste_pretty(app: tauri::AppHandle, content: String) -> AppResult<()> {
+    paste_to_previous_app(app, &transform::pretty_print(&content), false)
+}
+
+/// 渲染将要发送给 AI 的提示词但不发起请求；生成时前端也通过它拼装提示词，预览与实际发送一致
+#[tauri::command]
+fn preview_prompt(
+    state: tauri::State<AppState>,
+    template_id: Option<String>,
+    materials: Vec<prompt::Material>,
+    custom_prompt: Option<String>,
+) -> AppResult<String> {
+    let template = match template_id {
+        Some(id) => Some(
+            state
+                .db
+                .get_template(&id)?
+                .ok_or_else(|| AppError::NotFound(format!("Template {}", id)))?,
+        ),
+        None => None,
+    };
+    Ok(prompt::render_template(
+        template.as_ref().map(|t| t.prompt.as_str()),
+        &materials,
+        custom_prompt.as_deref().unwrap_or(""),
+    ))
+}
+
+/// 将 AI 输出的 Markdown 转为纯文本
+#[tauri::command]
+fn strip_markdown(text: String) -> String {
+    transform::strip_markdown(&text)
+}
+
+/// 打开系统剪贴板，失败时（例如被其他应用占用）返回说明原因的错误
+fn open_clipboard() -> AppResult<arboard::Clipboard> {
+    arboard::Clipboard::new().map_err(|e| AppError::Other(format!("Cannot access the system clipboard: {}", e)))
+}
+
+/// keep_open 为 true 时窗口保持显示：每次都先把焦点交还给记住的目标应用，粘贴后再切回 PasteGo
+fn paste_to_previous_app(app: tauri::AppHandle, content: &str, keep_open: bool) -> AppResult<()> {
+    // 空内容会清掉用户剪贴板里原有的东西，粘贴过去也什么都没有
+    if content.trim().is_empty() {
+        return Err(AppError::InvalidInput("Nothing to paste: the content is empty".to_string()));
+    }
+
     // 写入系统剪贴板
-    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
-    clipboard.set_text(&content).map_err(|e| e.to_string())?;
+    let mut clipboard = open_clipboard()?;
+    clipboard
+        .set_text(content)
+        .map_err(|e| AppError::Other(format!("Failed to write the system clipboard: {}", e)))?;
+
+    let setting = |key: &str| {
+        app.try_state::<AppState>()
+            .map(|state| settings::get_bool(&state.db, key))
+    };
+    let auto_paste = setting(settings::AUTO_PASTE).unwrap_or(true);
+    let feedback = setting(settings::PASTE_FEEDBACK).unwrap_or(false);
 
     // 在原生线程中执行隐藏 + 切回 + 粘贴（CGEvent 需要在原生线程中运行）
     std::thread::spawn(move || {
+        if keep_open {
+            // 关闭自动粘贴时只复制到剪贴板：窗口保持显示，也不切换到目标应用
+            if !auto_paste {
+                if feedback {
+                    play_paste_sound();
+                }
+                return;
+            }
+            focus_previous_app();
+            simulate_cmd_v();
+            if feedback {
+                play_paste_sound();
+            }
+            std::thread::sleep(KEEP_OPEN_REFOCUS_DELAY);
+            let handle = app.clone();
+            let _ = app.run_on_main_thread(move || {
+                if let Some(win) = handle.get_webview_window("main") {
+                    show_and_focus_window(&win);
+                }
+            });
+            return;
+        }
+
         // 隐藏主窗口
         if let Some(win) = app.get_webview_window("main") {
             let _ = win.hide();
         }
 
-        // 显式激活之前的前台应用
-        reactivate_previous_app();
+        // 关闭自动粘贴时只切回之前的应用，由用户自己粘贴
+        if !auto_paste {
+            reactivate_previous_app();
+            if feedback {
+                play_paste_sound();
+            }
+            return;
+        }
 
-        // 等待目标应用完成激活
-        std::thread::sleep(std::time::Duration::from_millis(300));
+        focus_previous_app();
 
         // 模拟 Cmd+V 粘贴
         simulate_cmd_v();
+        if feedback {
+            play_paste_sound();
+        }
     });
 
     Ok(())
 }
 
+/// 显式激活之前的前台应用，等它真正回到前台再继续，而不是固定等待一段时间
+fn focus_previous_app() {
+    match reactivate_previous_app() {
+        Some(pid) => {
+            if !wait_until_frontmost(pid, REACTIVATE_TIMEOUT) {
+                log::warn!("Previous app (pid {}) did not become frontmost in time", pid);
+            }
+            std::thread::sleep(FOCUS_SETTLE_DELAY);
+        }
+        // 没有记录目标应用时，等待系统把焦点交还给下一个应用
+        None => std::thread::sleep(std::time::Duration::from_millis(300)),
+    }
+}
+
+/// 内联生成把输出送入目标应用的方式
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum InlineTarget {
+    /// 每批文本写入剪贴板后模拟 Cmd+V，结束后恢复原来的剪贴板文本；期间暂停记录文本条目
+    Paste,
+    /// 模拟键盘直接输入 Unicode 文本，不占用剪贴板，但部分应用的自动补全可能干扰输入
+    Type,
+}
+
+/// 在原生线程中把流式输出送入之前的前台应用：先切回并等待其获得焦点，
+/// 之后每隔 INLINE_FLUSH_INTERVAL 把收到的文本合并成一批输出。发送端全部释放后输出剩余文本并结束
+fn spawn_inline_writer(target: InlineTarget) -> (std::sync::mpsc::Sender<String>, std::thread::JoinHandle<()>) {
+    use std::sync::mpsc::RecvTimeoutError;
+
+    let (tx, rx) = std::sync::mpsc::channel::<String>();
+    let handle = std::thread::spawn(move || {
+        let mut board = match target {
+            InlineTarget::Paste => match arboard::Clipboard::new() {
+                Ok(board) => Some(board),
+                Err(e) => {
+                    log::warn!("Inline generation cannot access the clipboard: {}", e);
+                    return;
+                }
+            },
+            InlineTarget::Type => None,
+        };
+        let saved = board.as_mut().and_then(|c| c.get_text().ok());
+        if board.is_some() {
+            clipboard::set_text_recording_paused(true);
+        }
+
+        focus_previous_app();
+
+        let mut pending = String::new();
+        let mut last_flush = std::time::Instant::now();
+        loop {
+            let finished = match rx.recv_timeout(INLINE_FLUSH_INTERVAL) {
+                Ok(text) => {
+                    pending.push_str(&text);
+                    false
+                }
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => true,
+            };
+            if !pending.is_empty() && (finished || last_flush.elapsed() >= INLINE_FLUSH_INTERVAL) {
+                match board.as_mut() {
+                    Some(board) => {
+                        if let Err(e) = board.set_text(pending.as_str()) {
+                            log::warn!("Inline generation failed to write the clipboard: {}", e);
+                        } else {
+                            simulate_cmd_v();
+                            std::thread::sleep(INLINE_PASTE_SETTLE);
+                        }
+                    }
+                    None => simulate_typing(&pending),
+                }
+                pending.clear();
+                last_flush = std::time::Instant::now();
+            }
+            if finished {
+                break;
+            }
+        }
+
+        if let Some(board) = board.as_mut() {
+            if let Some(saved) = saved {
+                let _ = board.set_text(saved);
+            }
+            // 等监听线程读到恢复后的内容再恢复记录，否则它会被当作新复制的条目
+            std::thread::sleep(clipboard::MONITOR_POLL_INTERVAL * 2);
+            clipboard::set_text_recording_paused(false);
+        }
+    });
+    (tx, handle)
+}
+
+/// 快捷模板复制流程的等待时间：(模拟复制前的延迟, 等待剪贴板变化的超时)
+fn quick_copy_timing(app: &tauri::AppHandle) -> (std::time::Duration, std::time::Duration) {
+    let ms = |key: &str| {
+        let value = match app.try_state::<AppState>() {
+            Some(state) => settings::get_i64(&state.db, key),
+            None => settings::defaults()[key].parse().unwrap_or_default(),
+        };
+        std::time::Duration::from_millis(value.max(0) as u64)
+    };
+    (ms(settings::QUICK_COPY_DELAY_MS), ms(settings::QUICK_COPY_TIMEOUT_MS))
+}
+
+/// quick-template 事件的负载：模板 id 和模拟复制后剪贴板中的文本
+#[derive(Clone, serde::Serialize)]
+struct QuickTemplatePayload {
+    template_id: String,
+    text: String,
+}
+
+/// 对前台应用中选中的文本运行模板：模拟 Cmd+C 复制选区，再显示窗口并把文本通过 quick-template 事件交给前端
+fn run_quick_template(handle: tauri::AppHandle, tid: String) {
+    // Simulate Cmd+C then emit after a short delay
+    std::thread::spawn(move || {
+        // Hide window so target app regains focus
+        if let Some(win) = handle.get_webview_window("main") {
+            let _ = win.hide();
+        }
+        let (delay, timeout) = quick_copy_timing(&handle);
+        // Wait for user to release shortcut keys + app focus switch
+        std::thread::sleep(delay);
+        let before = clipboard::pasteboard_change_count();
+        simulate_cmd_c();
+        // Continue as soon as the copy lands instead of a fixed sleep
+        let copied = clipboard::wait_for_pasteboard_change(before, timeout);
+        if let Some(win) = handle.get_webview_window("main") {
+            position_window_near_mouse(&win);
+            show_and_focus_window(&win);
+        }
+        // 剪贴板没有变化说明没有选中内容，不要拿旧内容运行模板
+        if !copied {
+            let _ = handle.emit_to(EventTarget::webview_window("main"), "quick-template-empty", &tid);
+            return;
+        }
+        // 直接带上复制到的文本，前端不必再读剪贴板
+        let text = arboard::Clipboard::new()
+            .and_then(|mut cb| cb.get_text())
+            .unwrap_or_default();
+        let _ = handle.emit_to(
+            EventTarget::webview_window("main"),
+            "quick-template",
+            QuickTemplatePayload { template_id: tid, text },
+        );
+    });
+}
+
+/// 把最近一条文本记录粘贴到当前的前台应用；没有文本记录时什么也不做
+fn paste_latest_clip(app: &tauri::AppHandle) -> AppResult<()> {
+    let Some(clip) = app.state::<AppState>().db.latest_text_clip()? else {
+        return Ok(());
+    };
+    remember_frontmost_app();
+    paste_to_previous_app(app.clone(), &clip.content, false)
+}
+
+/// 处理 pastego:// 链接，各操作复用对应命令和快捷键的逻辑
+fn handle_deep_link(app: &tauri::AppHandle, url: &tauri::Url) {
+    let Some(link) = deep_link::parse(url) else {
+        log::warn!("Unsupported deep link: {}", url);
+        return;
+    };
+    let state = app.state::<AppState>();
+    match link {
+        deep_link::DeepLink::PasteLast => {
+            if let Err(e) = paste_latest_clip(app) {
+                log::warn!("Deep link paste failed: {}", e);
+            }
+        }
+        deep_link::DeepLink::Template(id) => {
+            if !matches!(state.db.get_template(&id), Ok(Some(_))) {
+                log::warn!("Deep link refers to unknown template {}", id);
+                return;
+            }
+            // 打开链接可能激活了 PasteGo，先把焦点还给选中文本所在的应用
+            remember_frontmost_app();
+            reactivate_previous_app();
+            run_quick_template(app.clone(), id);
+        }
+        deep_link::DeepLink::Search(query) => {
+            if let Some(win) = app.get_webview_window("main") {
+                position_window_near_mouse(&win);
+                show_and_focus_window(&win);
+            }
+            let _ = app.emit_to(EventTarget::webview_window("main"), "deep-link-search", query);
+        }
+    }
+}
+
+/// 清空搜索和筛选：默认视图的第一页随 reset-view 事件一起发给主窗口，前端不必再查询一次
+fn reset_view(app: &tauri::AppHandle) {
+    let clips = match app.state::<AppState>().db.get_default_view(DEFAULT_VIEW_PAGE_SIZE) {
+        Ok(clips) => clips,
+        Err(e) => {
+            log::warn!("Failed to load default view: {}", e);
+            return;
+        }
+    };
+    if let Some(win) = app.get_webview_window("main") {
+        if !win.is_visible().unwrap_or(false) {
+            position_window_near_mouse(&win);
+            show_and_focus_window(&win);
+        }
+    }
+    let _ = app.emit_to(EventTarget::webview_window("main"), "reset-view", clips);
+}
+
 fn register_template_shortcuts(app: &tauri::AppHandle, db: &Database) {
     use tauri_plugin_global_shortcut::GlobalShortcutExt;
 
     let _ = app.global_shortcut().unregister_all();
 
-    // Re-register the static toggle shortcut
+    // Re-register the configurable toggle shortcut
+    let toggle_shortcut = settings::get(db, settings::TOGGLE_SHORTCUT);
     let app_handle = app.clone();
     let _ = app.global_shortcut().on_shortcut(
-        "CmdOrCtrl+Shift+V",
+        toggle_shortcut.as_str(),
         move |_app, _shortcut, event| {
             if event.state == tauri_plugin_global_shortcut::ShortcutState::Pressed {
                 toggle_main_window(&app_handle);
@@ -229,6 +1585,19 @@ fn register_template_shortcuts(app: &tauri::AppHandle, db: &Database) {
         },
     );
 
+    let reset_shortcut = settings::get(db, settings::RESET_VIEW_SHORTCUT);
+    if !reset_shortcut.is_empty() {
+        let app_handle = app.clone();
+        let _ = app.global_shortcut().on_shortcut(
+            reset_shortcut.as_str(),
+            move |_app, _shortcut, event| {
+                if event.state == tauri_plugin_global_shortcut::ShortcutState::Pressed {
+                    reset_view(&app_handle);
+                }
+            },
+        );
+    }
+
     // Register template shortcuts
     if let Ok(templates) = db.get_templates() {
         for tpl in templates {
@@ -242,28 +1611,7 @@ fn register_template_shortcuts(app: &tauri::AppHandle, db: &Database) {
                     shortcut.as_str(),
                     move |_app, _shortcut, event| {
                         if event.state == tauri_plugin_global_shortcut::ShortcutState::Pressed {
-                            let handle = app_handle.clone();
-                            let tid = template_id.clone();
-                            // Simulate Cmd+C then emit after a short delay
-                            std::thread::spawn(move || {
-                                // Hide window so target app regains focus
-                                if let Some(win) = handle.get_webview_window("main") {
-                                    let _ = win.hide();
-                                }
-                                // Wait for user to release shortcut keys + app focus switch
-                                std::thread::sleep(std::time::Duration::from_millis(300));
-                                simulate_cmd_c();
-                                std::thread::sleep(std::time::Duration::from_millis(200));
-                                if let Some(win) = handle.get_webview_window("main") {
-                                    position_window_near_mouse(&win);
-                                    show_and_focus_window(&win);
-                                }
-                                let _ = handle.emit_to(
-                                    EventTarget::webview_window("main"),
-                                    "quick-template",
-                                    &tid,
-                                );
-                            });
+                            run_quick_template(app_handle.clone(), template_id.clone());
                         }
                     },
                 );
@@ -272,6 +1620,54 @@ fn register_template_shortcuts(app: &tauri::AppHandle, db: &Database) {
     }
 }
 
+/// 按当前的快捷键和语言设置更新托盘菜单文字和提示
+fn update_tray_labels(app: &tauri::AppHandle, db: &Database) {
+    let locale = i18n::current(db);
+    if let Some(tray) = app.try_state::<TrayMenuState>() {
+        let _ = tray.show.set_text(tray_show_label(db, locale));
+        let _ = tray.paste_last.set_text(i18n::tray_paste_last(locale));
+        let _ = tray.pause.set_text(i18n::tray_pause(locale));
+        let _ = tray.clear.set_text(i18n::tray_clear(locale));
+        let _ = tray.quit.set_text(i18n::tray_quit(locale));
+    }
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let _ = tray.set_tooltip(Some(i18n::tray_tooltip(locale)));
+    }
+}
+
+/// 按当前状态更新托盘菜单：没有文本记录时禁用“粘贴最近”，没有可清空的条目时禁用“清空历史”，
+/// “暂停监听”的勾选与监听状态一致。菜单没有打开前的回调，因此在鼠标移到托盘图标上或点击时更新
+fn refresh_tray_menu(app: &tauri::AppHandle) {
+    let (Some(tray), Some(state)) = (app.try_state::<TrayMenuState>(), app.try_state::<AppState>()) else {
+        return;
+    };
+    let has_text = matches!(state.db.latest_text_clip(), Ok(Some(_)));
+    let _ = tray.paste_last.set_enabled(has_text);
+    let _ = tray.clear.set_enabled(state.db.has_unpinned_clips().unwrap_or(true));
+    let _ = tray.pause.set_checked(clipboard::is_monitoring_paused());
+}
+
+/// 暂停或恢复剪贴板监听，同步托盘菜单并发出 monitoring-paused 事件
+fn apply_monitoring_paused(app: &tauri::AppHandle, paused: bool) {
+    clipboard::set_monitoring_paused(paused);
+    if let Some(tray) = app.try_state::<TrayMenuState>() {
+        let _ = tray.pause.set_checked(paused);
+    }
+    let _ = app.emit("monitoring-paused", paused);
+}
+
+/// 把未置顶、未收藏的条目全部移到回收站（可恢复），完成后发出 clipboard-cleared 事件
+fn trash_history(app: &tauri::AppHandle, db: &Database) -> AppResult<usize> {
+    let count = db.trash_unpinned_clips()?;
+    let _ = app.emit("clipboard-cleared", count);
+    Ok(count)
+}
+
+fn tray_show_label(db: &Database, locale: i18n::Locale) -> String {
+    let shortcut = settings::get(db, settings::TOGGLE_SHORTCUT);
+    i18n::tray_show(locale, &settings::shortcut_label(&shortcut))
+}
+
 /// Simulate Cmd+C keypress using macOS CGEvent API
 fn simulate_cmd_c() {
     use core_graphics::event::{CGEvent, CGEventFlags, CGKeyCode};
@@ -292,39 +1688,84 @@ fn simulate_cmd_c() {
     }
 }
 
-/// 保存当前最前台应用的 PID（在显示 PasteGo 之前调用）
-fn save_frontmost_app_pid() {
-    unsafe {
+/// 记住当前最前台应用的 PID 和 bundle id（在显示 PasteGo 之前调用，主窗口切换和快捷模板
+/// 都经过 show_and_focus_window）。前台已经是 PasteGo 自己时保留之前的记录
+fn remember_frontmost_app() {
+    let pid: i32 = unsafe {
         let workspace: cocoa::base::id = msg_send![class!(NSWorkspace), sharedWorkspace];
         let front_app: cocoa::base::id = msg_send![workspace, frontmostApplication];
-        if front_app != cocoa::base::nil {
-            let pid: i32 = msg_send![front_app, processIdentifier];
-            PREVIOUS_APP_PID.store(pid, Ordering::SeqCst);
+        if front_app == cocoa::base::nil {
+            return;
         }
+        msg_send![front_app, processIdentifier]
+    };
+    if pid == std::process::id() as i32 {
+        return;
     }
+    PREVIOUS_APP_PID.store(pid, Ordering::SeqCst);
+    *PREVIOUS_APP_BUNDLE_ID.lock().unwrap() = clipboard::get_frontmost_app_bundle_id();
 }
 
-/// 重新激活之前保存的前台应用
-fn reactivate_previous_app() {
+/// 重新激活之前保存的前台应用：优先按 PID 查找，找不到时按 bundle id 查找。
+/// 返回被激活应用的 PID，没有可激活的应用时返回 None
+fn reactivate_previous_app() -> Option<i32> {
     let pid = PREVIOUS_APP_PID.load(Ordering::SeqCst);
-    if pid > 0 {
-        unsafe {
-            let app: cocoa::base::id = msg_send![
+    unsafe {
+        let mut app: cocoa::base::id = cocoa::base::nil;
+        if pid > 0 {
+            app = msg_send![
                 class!(NSRunningApplication),
                 runningApplicationWithProcessIdentifier: pid
             ];
-            if app != cocoa::base::nil {
-                // NSApplicationActivateIgnoringOtherApps = 1 << 1
-                let _: cocoa::base::BOOL = msg_send![app, activateWithOptions: 2u64];
+        }
+        if app == cocoa::base::nil {
+            use cocoa::foundation::NSString;
+            let bundle_id = PREVIOUS_APP_BUNDLE_ID.lock().unwrap().clone()?;
+            let ns_bundle_id = NSString::alloc(cocoa::base::nil).init_str(&bundle_id);
+            let apps: cocoa::base::id = msg_send![
+                class!(NSRunningApplication),
+                runningApplicationsWithBundleIdentifier: ns_bundle_id
+            ];
+            let _: () = msg_send![ns_bundle_id, release];
+            let count: usize = msg_send![apps, count];
+            if count == 0 {
+                return None;
             }
+            app = msg_send![apps, objectAtIndex: 0usize];
         }
+        // NSApplicationActivateIgnoringOtherApps = 1 << 1
+        let _: cocoa::base::BOOL = msg_send![app, activateWithOptions: 2u64];
+        Some(msg_send![app, processIdentifier])
+    }
+}
+
+/// 轮询直到指定 PID 的应用成为前台应用，超时返回 false
+fn wait_until_frontmost(pid: i32, timeout: std::time::Duration) -> bool {
+    let deadline = std::time::Instant::now() + timeout;
+    loop {
+        let front_pid: i32 = unsafe {
+            let workspace: cocoa::base::id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let front_app: cocoa::base::id = msg_send![workspace, frontmostApplication];
+            if front_app == cocoa::base::nil {
+                0
+            } else {
+                msg_send![front_app, processIdentifier]
+            }
+        };
+        if front_pid == pid {
+            return true;
+        }
+        if std::time::Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
     }
 }
 
 /// 显示窗口并立即激活，确保无需额外点击即可交互
 fn show_and_focus_window(window: &tauri::WebviewWindow) {
     // 先记住当前前台应用，再激活 PasteGo
-    save_frontmost_app_pid();
+    remember_frontmost_app();
     unsafe {
         let ns_app: cocoa::base::id = msg_send![class!(NSApplication), sharedApplication];
         let _: () = msg_send![ns_app, activateIgnoringOtherApps: cocoa::base::YES];
@@ -410,23 +1851,55 @@ fn position_window_near_mouse(window: &tauri::WebviewWindow) {
     let _ = window.set_position(LogicalPosition::new(x, y));
 }
 
-fn toggle_main_window(app: &tauri::AppHandle) {
+/// 把窗口放在托盘图标正下方并水平居中，左右超出屏幕时贴边
+fn position_window_at_tray(window: &tauri::WebviewWindow, rect: &tauri::Rect) {
+    use tauri::LogicalPosition;
+
+    let scale = window.scale_factor().unwrap_or(1.0);
+    let icon_pos = rect.position.to_logical::<f64>(scale);
+    let icon_size = rect.size.to_logical::<f64>(scale);
+    let win_width = window
+        .outer_size()
+        .map(|s| s.width as f64 / scale)
+        .unwrap_or(400.0);
+
+    let mut x = icon_pos.x + icon_size.width / 2.0 - win_width / 2.0;
+    let y = icon_pos.y + icon_size.height + TRAY_WINDOW_GAP;
+    if let Ok(Some(monitor)) = window.current_monitor() {
+        let screen_x = monitor.position().x as f64 / monitor.scale_factor();
+        let screen_w = monitor.size().width as f64 / monitor.scale_factor();
+        x = x.min(screen_x + screen_w - win_width).max(screen_x);
+    }
+
+    let _ = window.set_position(LogicalPosition::new(x, y));
+}
+
+/// 显示或隐藏主窗口；传入托盘图标位置时显示在图标下方，否则显示在鼠标旁
+fn toggle_main_window_at(app: &tauri::AppHandle, tray_rect: Option<&tauri::Rect>) {
     if let Some(window) = app.get_webview_window("main") {
         if window.is_visible().unwrap_or(false) {
             let _ = window.hide();
         } else {
-            position_window_near_mouse(&window);
+            match tray_rect {
+                Some(rect) => position_window_at_tray(&window, rect),
+                None => position_window_near_mouse(&window),
+            }
             show_and_focus_window(&window);
         }
     }
 }
 
+fn toggle_main_window(app: &tauri::AppHandle) {
+    toggle_main_window_at(app, None);
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_deep_link::init())
         .setup(|app| {
             // Hide from Dock (agent mode)
             unsafe {
@@ -461,28 +1934,82 @@ pub fn run() {
             let images_dir = app_dir.join("images");
             let db = Arc::new(Database::new(&db_path).expect("Failed to open database"));
 
+            // 日志：级别由设置决定，密钥在写入前隐藏
+            logging::refresh_secrets(&db);
+            app.handle().plugin(logging::plugin(&app_dir))?;
+            logging::apply_level(&db);
+
+            // 首次启动时按系统语言设置 locale，并同步默认模板的语言
+            if db.get_setting(settings::LOCALE).ok().flatten().is_none() {
+                let locale = i18n::detect_system_locale();
+                let _ = db.set_setting(settings::LOCALE, locale.as_str());
+                if let Err(e) = i18n::localize_default_template(&db, locale) {
+                    log::warn!("Failed to localize default template: {}", e);
+                }
+            }
+
             // Clipboard monitor
             let monitor = clipboard::ClipboardMonitor::new();
-            monitor.start(app.handle().clone(), db.clone(), images_dir);
+            monitor.start(app.handle().clone(), db.clone(), images_dir.clone());
+            spawn_expiry_sweep(app.handle().clone(), db.clone(), images_dir.clone());
+            spawn_cleanup_task(app.handle().clone(), db.clone(), images_dir.clone());
+
+            app.manage(AppState {
+                db: db.clone(),
+                app_data_dir: app_dir,
+                db_path,
+                images_dir,
+                limiter: ai::ProviderLimiter::default(),
+                local_api: local_api::LocalApi::default(),
+                monitor,
+            });
 
-            app.manage(AppState { db: db.clone(), monitor });
+            if let Err(e) = local_api::ensure_token(&db) {
+                log::warn!("Failed to create the local API token: {}", e);
+            }
+            local_api::restart(app.handle());
 
             // Register global shortcuts (static + template-based)
             register_template_shortcuts(app.handle(), &db);
 
+            // pastego:// 链接
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+                let handle = app.handle().clone();
+                app.deep_link().on_open_url(move |event| {
+                    for url in event.urls() {
+                        handle_deep_link(&handle, &url);
+                    }
+                });
+            }
+
             // System tray
-            let show = MenuItemBuilder::with_id("show", "显示 PasteGo  Cmd+Shift+V").build(app)?;
-            let quit = MenuItemBuilder::with_id("quit", "退出").build(app)?;
+            let locale = i18n::current(&db);
+            let show = MenuItemBuilder::with_id("show", tray_show_label(&db, locale)).build(app)?;
+            let paste_last = MenuItemBuilder::with_id("paste_last", i18n::tray_paste_last(locale)).build(app)?;
+            let pause = CheckMenuItemBuilder::with_id("pause", i18n::tray_pause(locale))
+                .checked(clipboard::is_monitoring_paused())
+                .build(app)?;
+            let clear = MenuItemBuilder::with_id("clear", i18n::tray_clear(locale)).build(app)?;
+            let quit = MenuItemBuilder::with_id("quit", i18n::tray_quit(locale)).build(app)?;
             let menu = MenuBuilder::new(app)
                 .item(&show)
                 .separator()
+                .item(&paste_last)
+                .item(&pause)
+                .item(&clear)
+                .separator()
                 .item(&quit)
                 .build()?;
+            app.manage(TrayMenuState { show, paste_last, pause, clear, quit });
+            refresh_tray_menu(app.handle());
 
-            let _tray = TrayIconBuilder::new()
+            let tray_click_action = settings::get(&db, settings::TRAY_CLICK_ACTION);
+            let _tray = TrayIconBuilder::with_id(TRAY_ID)
                 .icon(Image::from_bytes(include_bytes!("../icons/32x32.png"))?)
                 .menu(&menu)
-                .tooltip("PasteGo - 剪贴板 AI 助手")
+                .show_menu_on_left_click(tray_click_action == "menu")
+                .tooltip(i18n::tray_tooltip(locale))
                 .on_menu_event(
                     |app: &tauri::AppHandle, event: tauri::menu::MenuEvent| {
                         match event.id().as_ref() {
@@ -492,6 +2019,20 @@ pub fn run() {
                                     show_and_focus_window(&window);
                                 }
                             }
+                            "paste_last" => {
+                                if let Err(e) = paste_latest_clip(app) {
+                                    log::warn!("Tray paste failed: {}", e);
+                                }
+                            }
+                            "pause" => {
+                                apply_monitoring_paused(app, !clipboard::is_monitoring_paused());
+                            }
+                            "clear" => {
+                                let state = app.state::<AppState>();
+                                if let Err(e) = trash_history(app, &state.db) {
+                                    log::warn!("Tray clear failed: {}", e);
+                                }
+                            }
                             "quit" => {
                                 app.exit(0);
                             }
@@ -501,42 +2042,131 @@ pub fn run() {
                 )
                 .on_tray_icon_event(
                     |tray: &tauri::tray::TrayIcon, event: TrayIconEvent| {
+                        if matches!(event, TrayIconEvent::Enter { .. } | TrayIconEvent::Click { .. }) {
+                            refresh_tray_menu(tray.app_handle());
+                        }
                         if let TrayIconEvent::Click {
                             button: MouseButton::Left,
+                            rect,
                             ..
                         } = event
                         {
-                            toggle_main_window(tray.app_handle());
+                            // 每次点击时读取设置，修改后立即生效；menu 模式由托盘自己弹出菜单
+                            let app = tray.app_handle();
+                            let action = match app.try_state::<AppState>() {
+                                Some(state) => settings::get(&state.db, settings::TRAY_CLICK_ACTION),
+                                None => String::new(),
+                            };
+                            match action.as_str() {
+                                "menu" => {}
+                                "toggle-at-tray" => toggle_main_window_at(app, Some(&rect)),
+                                _ => toggle_main_window(app),
+                            }
                         }
                     },
                 )
                 .build(app)?;
 
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
-            }
-
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_clips,
+            get_default_view,
+            get_clip,
+            get_clip_content,
+            get_clip_days,
+            get_type_counts,
+            get_trending_clips,
+            fetch_url_metadata,
+            create_clip,
+            update_clip_content,
+            bump_clip,
             delete_clip,
+            delete_clips,
+            get_trash,
+            restore_clip,
+            empty_trash,
+            clear_all_clips,
+            cleanup_orphan_images,
             toggle_pin,
+            set_pinned,
+            reorder_pins,
+            toggle_favorite,
             clear_old_clips,
+            set_clip_expiry,
+            get_stats,
+            get_storage_info,
+            maintain_db,
+            reencode_images,
             get_templates,
             save_template,
             delete_template,
+            duplicate_template,
+            get_categories,
+            set_category_order,
+            rename_category,
             get_providers,
+            get_default_provider,
+            redetect_types,
+            get_provider_presets,
             save_provider,
             delete_provider,
+            test_provider,
+            list_ollama_models,
+            get_settings,
+            set_setting,
+            regenerate_local_api_token,
+            get_log_path,
+            set_monitoring_paused,
+            is_monitoring_paused,
+            trash_unpinned_clips,
+            export_clips,
+            import_clips,
             ai_generate,
+            ai_generate_sync,
+            ai_generate_batch,
+            ai_generate_inline,
+            get_usage_stats,
             read_image_base64,
             copy_and_paste,
+            copy_paste_keep_open,
+            copy_and_paste_pretty,
+            strip_markdown,
+            preview_prompt,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_round_trip() {
+        for len in 0..=10 {
+            let data: Vec<u8> = (0..len).map(|i| (i * 37 + 11) as u8).collect();
+            let encoded = base64_encode(&data);
+            assert_eq!(encoded.len() % 4, 0);
+            assert_eq!(base64_decode(&encoded).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn base64_known_values() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_decode("Zm9vYg==").unwrap(), b"foob");
+        assert_eq!(base64_decode("").unwrap(), b"");
+        assert!(base64_decode("Zm9v!").is_err());
+    }
+
+    #[test]
+    fn base64_reader_matches_encode() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(100_003).collect();
+        let streamed = base64_encode_reader("data:image/png;base64,".to_string(), &mut data.as_slice()).unwrap();
+        assert_eq!(streamed, format!("data:image/png;base64,{}", base64_encode(&data)));
+    }
+}
@@ -0,0 +1,126 @@
+use crate::error::{AppError, AppResult};
+use std::time::Duration;
+
+/// 抓取网页标题的整体超时
+const FETCH_TIMEOUT: Duration = Duration::from_secs(8);
+/// 最多读取的响应字节数，<title> 通常在 <head> 开头，不必下载整个页面
+const MAX_BODY_BYTES: usize = 512 * 1024;
+/// 保存的标题最多字符数
+const MAX_TITLE_CHARS: usize = 200;
+
+/// 请求 URL 并解析页面 <title>，只支持 http/https；页面没有标题时返回 None
+pub async fn fetch_title(url: &str) -> AppResult<Option<String>> {
+    let url = reqwest::Url::parse(url.trim())
+        .map_err(|e| AppError::InvalidInput(format!("Invalid URL: {}", e)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(AppError::InvalidInput(format!(
+            "Unsupported URL scheme: {}",
+            url.scheme()
+        )));
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .map_err(|e| AppError::Other(format!("Failed to build HTTP client: {}", e)))?;
+    let response = client
+        .get(url)
+        .header("Accept", "text/html,application/xhtml+xml")
+        .send()
+        .await
+        .map_err(fetch_error)?;
+    if !response.status().is_success() {
+        return Err(AppError::Api {
+            status: response.status().as_u16(),
+            body: String::new(),
+        });
+    }
+
+    let mut body = Vec::new();
+    let mut stream = response.bytes_stream();
+    use futures_util::StreamExt;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(fetch_error)?;
+        let room = MAX_BODY_BYTES - body.len();
+        body.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        if body.len() >= MAX_BODY_BYTES {
+            break;
+        }
+    }
+    Ok(extract_title(&String::from_utf8_lossy(&body)))
+}
+
+fn fetch_error(e: reqwest::Error) -> AppError {
+    if e.is_timeout() {
+        AppError::Timeout(format!("Fetching page timed out: {}", e))
+    } else {
+        AppError::Network(format!("Failed to fetch page: {}", e))
+    }
+}
+
+/// 取出第一个 <title> 的文本：解码常见实体并合并空白，空标题返回 None
+pub fn extract_title(html: &str) -> Option<String> {
+    // ASCII 小写不改变字节偏移，可以直接用于切原文
+    let lower = html.to_ascii_lowercase();
+    let mut from = 0;
+    let open = loop {
+        let pos = from + lower[from..].find("<title")?;
+        let after = lower.as_bytes().get(pos + 6).copied();
+        // 跳过 <titlefoo> 之类的其他标签
+        if matches!(after, Some(b'>' | b'/' | b' ' | b'\t' | b'\n' | b'\r')) {
+            break pos;
+        }
+        from = pos + 6;
+    };
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+
+    let title = decode_entities(&html[start..end])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if title.is_empty() {
+        return None;
+    }
+    Some(title.chars().take(MAX_TITLE_CHARS).collect())
+}
+
+/// 解码命名实体（常见几种）和数字实体，无法识别的保持原样
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..].find(';').filter(|&semi| semi <= 10).and_then(|semi| {
+            let entity = &rest[1..semi + 1];
+            let ch = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            ch.map(|c| (c, semi + 2))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,394 @@
+use crate::error::{AppError, AppResult, ErrorBody};
+use crate::{db, settings, AppState};
+use serde::Deserialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Manager, Url};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// 请求行和请求头的总长度上限
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+/// 请求体上限，粘贴的文本可能较长
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+/// 读取整个请求的最长时间，避免空闲连接一直占用
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// /clips 未指定 limit 时返回的条数，与 get_clips 命令一致
+const DEFAULT_LIMIT: usize = 100;
+
+/// 本地 API 服务的后台任务，重启或关闭时先中止旧任务
+#[derive(Default)]
+pub struct LocalApi(Mutex<Option<tauri::async_runtime::JoinHandle<()>>>);
+
+/// 解析后的 HTTP 请求
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    /// 以 http://127.0.0.1 为基准解析的请求目标，便于读取路径和查询参数
+    pub url: Url,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// 按名称（不区分大小写）读取请求头
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn query(&self, name: &str) -> Option<String> {
+        self.url
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    }
+}
+
+/// JSON 响应；body 为空时返回 204 之类的无内容响应
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    fn json<T: serde::Serialize>(value: &T) -> Self {
+        match serde_json::to_string(value) {
+            Ok(body) => Response { status: 200, body },
+            Err(e) => Response::error(&AppError::Other(e.to_string())),
+        }
+    }
+
+    fn no_content() -> Self {
+        Response {
+            status: 204,
+            body: String::new(),
+        }
+    }
+
+    /// 错误沿用命令的 { code, message } 格式
+    fn error(err: &AppError) -> Self {
+        let status = match err {
+            AppError::NotFound(_) => 404,
+            AppError::InvalidInput(_) => 400,
+            AppError::DbLocked => 503,
+            _ => 500,
+        };
+        Response {
+            status,
+            body: serde_json::to_string(&ErrorBody(err)).unwrap_or_default(),
+        }
+    }
+
+    fn unauthorized() -> Self {
+        Response::error_with_status(401, "Unauthorized", "Missing or invalid bearer token")
+    }
+
+    fn error_with_status(status: u16, code: &str, message: &str) -> Self {
+        Response {
+            status,
+            body: serde_json::json!({ "code": code, "message": message }).to_string(),
+        }
+    }
+
+    /// 序列化为完整的 HTTP/1.1 响应，每个连接只处理一个请求
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\n",
+            self.status,
+            reason_phrase(self.status)
+        );
+        if !self.body.is_empty() {
+            head.push_str("Content-Type: application/json; charset=utf-8\r\n");
+        }
+        head.push_str(&format!(
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            self.body.len()
+        ));
+        let mut bytes = head.into_bytes();
+        bytes.extend_from_slice(self.body.as_bytes());
+        bytes
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+/// 读取一个请求。只支持带 Content-Length 的请求体，不支持分块传输
+pub async fn read_request<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Request, Response> {
+    let bad_request = |message: &str| Response::error_with_status(400, "InvalidInput", message);
+    let mut head_bytes = 0;
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        let n = reader
+            .read_line(&mut line)
+            .await
+            .map_err(|_| bad_request("Malformed request"))?;
+        head_bytes += n;
+        if head_bytes > MAX_HEAD_BYTES {
+            return Err(Response::error_with_status(
+                413,
+                "InvalidInput",
+                "Request headers too large",
+            ));
+        }
+        if n == 0 {
+            return Err(bad_request("Incomplete request"));
+        }
+        let line = line.trim_end_matches(['\r', '\n']).to_string();
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+
+    let mut request_line = lines
+        .first()
+        .map(|l| l.split(' '))
+        .ok_or_else(|| bad_request("Empty request"))?;
+    let (method, target) = match (
+        request_line.next(),
+        request_line.next(),
+        request_line.next(),
+    ) {
+        (Some(method), Some(target), Some(version)) if version.starts_with("HTTP/1.") => {
+            (method, target)
+        }
+        _ => return Err(bad_request("Malformed request line")),
+    };
+    if !target.starts_with('/') {
+        return Err(bad_request("Request target must be a path"));
+    }
+    let url = Url::parse(&format!("http://127.0.0.1{}", target))
+        .map_err(|_| bad_request("Malformed request target"))?;
+
+    let headers = lines[1..]
+        .iter()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect::<Vec<_>>();
+    let mut request = Request {
+        method: method.to_ascii_uppercase(),
+        url,
+        headers,
+        body: Vec::new(),
+    };
+
+    if request.header("Transfer-Encoding").is_some() {
+        return Err(bad_request("Chunked request bodies are not supported"));
+    }
+    let length = match request.header("Content-Length") {
+        Some(value) => value
+            .parse::<usize>()
+            .map_err(|_| bad_request("Invalid Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY_BYTES {
+        return Err(Response::error_with_status(
+            413,
+            "InvalidInput",
+            "Request body too large",
+        ));
+    }
+    request.body = vec![0; length];
+    reader
+        .read_exact(&mut request.body)
+        .await
+        .map_err(|_| bad_request("Incomplete request body"))?;
+    Ok(request)
+}
+
+/// 校验 Authorization: Bearer <token>；令牌为空时拒绝所有请求
+pub fn is_authorized(request: &Request, token: &str) -> bool {
+    let Some(given) = request
+        .header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    !token.is_empty() && constant_time_eq(given.trim().as_bytes(), token.as_bytes())
+}
+
+/// 比较耗时与内容无关，避免通过响应时间逐字节猜出令牌
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 生成新的访问令牌
+pub fn generate_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// 首次启动时生成访问令牌，之后沿用已保存的令牌
+pub fn ensure_token(db: &db::Database) -> Result<String, rusqlite::Error> {
+    if let Some(token) = db
+        .get_setting(settings::LOCAL_API_TOKEN)?
+        .filter(|t| !t.is_empty())
+    {
+        return Ok(token);
+    }
+    let token = generate_token();
+    db.set_setting(settings::LOCAL_API_TOKEN, &token)?;
+    Ok(token)
+}
+
+/// 按当前设置启动、重启或关闭本地 API；监听地址固定为 127.0.0.1，只接受本机连接
+pub fn restart(app: &tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    let mut task = state.local_api.0.lock().unwrap();
+    if let Some(old) = task.take() {
+        old.abort();
+    }
+    if !settings::get_bool(&state.db, settings::ENABLE_LOCAL_API) {
+        return;
+    }
+    let port = settings::get_i64(&state.db, settings::LOCAL_API_PORT) as u16;
+    let handle = app.clone();
+    *task = Some(tauri::async_runtime::spawn(async move {
+        let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::warn!("Local API failed to listen on 127.0.0.1:{}: {}", port, e);
+                return;
+            }
+        };
+        log::info!("Local API listening on 127.0.0.1:{}", port);
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tauri::async_runtime::spawn(handle_connection(handle.clone(), stream));
+                }
+                Err(e) => log::warn!("Local API accept failed: {}", e),
+            }
+        }
+    }));
+}
+
+async fn handle_connection(app: tauri::AppHandle, stream: TcpStream) {
+    let mut reader = BufReader::new(stream);
+    let response = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut reader)).await {
+        Ok(Ok(request)) => {
+            // 令牌每次从设置读取，重新生成后立即生效
+            let token = settings::get(&app.state::<AppState>().db, settings::LOCAL_API_TOKEN);
+            if is_authorized(&request, &token) {
+                route(&app, &request)
+            } else {
+                Response::unauthorized()
+            }
+        }
+        Ok(Err(response)) => response,
+        Err(_) => return,
+    };
+    let mut stream = reader.into_inner();
+    let _ = stream.write_all(&response.to_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// 请求体：粘贴指定 id 的条目，或直接粘贴给定文本
+#[derive(Deserialize)]
+struct PasteBody {
+    id: Option<String>,
+    content: Option<String>,
+}
+
+/// 路由与对应命令一一对应：
+/// GET /clips → get_clips，GET /clips/<id> → get_clip，POST /paste → copy_and_paste
+fn route(app: &tauri::AppHandle, request: &Request) -> Response {
+    let segments: Vec<&str> = request
+        .url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["clips"]) => list_clips(app, request).map(|clips| Response::json(&clips)),
+        ("GET", ["clips", id]) => get_clip(app, id).map(|clip| Response::json(&clip)),
+        ("POST", ["paste"]) => paste(app, request).map(|_| Response::no_content()),
+        (_, ["clips"]) | (_, ["clips", _]) | (_, ["paste"]) => {
+            return Response::error_with_status(405, "InvalidInput", "Method not allowed")
+        }
+        _ => Err(AppError::NotFound(format!(
+            "Endpoint {}",
+            request.url.path()
+        ))),
+    };
+    result.unwrap_or_else(|e| {
+        log::warn!("Local API {} {} failed ({}): {}", request.method, request.url.path(), e.code(), e);
+        Response::error(&e)
+    })
+}
+
+fn list_clips(app: &tauri::AppHandle, request: &Request) -> AppResult<Vec<db::ClipItem>> {
+    let number = |name: &str, default: usize| match request.query(name) {
+        Some(value) => value.parse::<usize>().map_err(|_| {
+            AppError::InvalidInput(format!("{} must be a non-negative integer", name))
+        }),
+        None => Ok(default),
+    };
+    let limit = number("limit", DEFAULT_LIMIT)?;
+    let offset = number("offset", 0)?;
+    let search = request.query("search");
+    let clip_type = request.query("type");
+    let sort = request.query("sort");
+    let after = crate::parse_time_bound("after", request.query("after"))?;
+    let before = crate::parse_time_bound("before", request.query("before"))?;
+    let filter = db::ClipFilter {
+        search: search.as_deref(),
+        clip_type: clip_type.as_deref(),
+        favorites_only: request.query("favorites").as_deref() == Some("true"),
+        sort: sort.as_deref(),
+        after,
+        before,
+    };
+    Ok(app
+        .state::<AppState>()
+        .db
+        .get_clips(&filter, limit, offset)?)
+}
+
+fn get_clip(app: &tauri::AppHandle, id: &str) -> AppResult<db::ClipItem> {
+    app.state::<AppState>()
+        .db
+        .get_clip(id)?
+        .ok_or_else(|| AppError::NotFound(format!("Clip {}", id)))
+}
+
+fn paste(app: &tauri::AppHandle, request: &Request) -> AppResult<()> {
+    let body: PasteBody = serde_json::from_slice(&request.body).map_err(|_| {
+        AppError::InvalidInput("Body must be JSON with an id or content field".to_string())
+    })?;
+    let content = match (body.id, body.content) {
+        (Some(id), None) => app
+            .state::<AppState>()
+            .db
+            .get_clip_content(&id)?
+            .ok_or_else(|| AppError::NotFound(format!("Clip {}", id)))?,
+        (None, Some(content)) => content,
+        _ => {
+            return Err(AppError::InvalidInput(
+                "Specify exactly one of id or content".to_string(),
+            ))
+        }
+    };
+    // 粘贴到发起请求时的前台应用
+    crate::remember_frontmost_app();
+    crate::paste_to_previous_app(app.clone(), &content, false)
+}
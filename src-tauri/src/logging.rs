@@ -0,0 +1,82 @@
+use crate::db::Database;
+use crate::error::redact_all;
+use crate::{ai, settings};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+
+/// 日志目录（位于应用数据目录下）和日志文件名（不含 .log）
+const LOG_DIR: &str = "logs";
+const LOG_FILE_NAME: &str = "pastego";
+/// 单个日志文件的大小上限，超过后轮转，只保留上一份
+const MAX_LOG_FILE_BYTES: u128 = 2 * 1024 * 1024;
+
+/// 写入日志前要隐藏的密钥（各供应商的 API Key 和认证请求头），供应商变化时由 refresh_secrets 更新
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+pub fn log_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(LOG_DIR).join(format!("{}.log", LOG_FILE_NAME))
+}
+
+/// LOG_LEVEL 设置对应的级别；调试构建至少输出 info
+pub fn level_filter(value: &str) -> log::LevelFilter {
+    let level = value.parse().unwrap_or(log::LevelFilter::Off);
+    if cfg!(debug_assertions) {
+        level.max(log::LevelFilter::Info)
+    } else {
+        level
+    }
+}
+
+/// 按当前设置调整日志级别，立即生效
+pub fn apply_level(db: &Database) {
+    log::set_max_level(level_filter(&settings::get(db, settings::LOG_LEVEL)));
+}
+
+/// 重新读取所有供应商的密钥
+pub fn refresh_secrets(db: &Database) {
+    let providers = db.get_providers().unwrap_or_default();
+    let secrets = providers
+        .iter()
+        .flat_map(ai::provider_secrets)
+        .map(str::to_string)
+        .collect();
+    *SECRETS.write().unwrap() = secrets;
+}
+
+fn scrub(message: &str) -> String {
+    let secrets = SECRETS.read().unwrap();
+    let secrets: Vec<&str> = secrets.iter().map(String::as_str).collect();
+    redact_all(message, &secrets)
+}
+
+/// 日志插件：写入应用数据目录下的 logs/pastego.log，达到大小上限后轮转；调试构建同时输出到终端。
+/// 插件按最详细的级别构建，实际输出由 apply_level 设置的全局级别控制，修改设置无需重启。
+/// 依赖库（reqwest、hyper 等）只记录警告以上
+pub fn plugin<R: tauri::Runtime>(app_data_dir: &Path) -> tauri::plugin::TauriPlugin<R> {
+    let mut builder = tauri_plugin_log::Builder::new()
+        .clear_targets()
+        .target(Target::new(TargetKind::Folder {
+            path: app_data_dir.join(LOG_DIR),
+            file_name: Some(LOG_FILE_NAME.to_string()),
+        }))
+        .max_file_size(MAX_LOG_FILE_BYTES)
+        .rotation_strategy(RotationStrategy::KeepOne)
+        .level(log::LevelFilter::Trace)
+        .filter(|metadata| {
+            metadata.target().starts_with(env!("CARGO_CRATE_NAME")) || metadata.level() <= log::Level::Warn
+        })
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "[{}][{}][{}] {}",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                record.level(),
+                record.target(),
+                scrub(&message.to_string())
+            ))
+        });
+    if cfg!(debug_assertions) {
+        builder = builder.target(Target::new(TargetKind::Stdout));
+    }
+    builder.build()
+}
@@ -0,0 +1,38 @@
+use serde::Deserialize;
+
+/// 模板中插入素材的占位符
+pub const MATERIALS_PLACEHOLDER: &str = "{{materials}}";
+
+/// 一条素材：条目类型和完整内容
+#[derive(Debug, Clone, Deserialize)]
+pub struct Material {
+    pub clip_type: String,
+    pub content: String,
+}
+
+/// 按顺序编号拼接素材，素材之间用分隔线隔开
+pub fn format_materials(materials: &[Material]) -> String {
+    materials
+        .iter()
+        .enumerate()
+        .map(|(i, m)| format!("【素材 {}】({})\n{}", i + 1, m.clip_type, m.content))
+        .collect::<Vec<_>>()
+        .join("\n\n---\n\n")
+}
+
+/// 渲染最终发送给 AI 的提示词。有模板时用素材替换 {{materials}}，自定义指令作为额外要求追加；
+/// 没有模板时以自定义指令开头、素材随后，指令为空则只发送素材
+pub fn render_template(template: Option<&str>, materials: &[Material], custom_prompt: &str) -> String {
+    let materials = format_materials(materials);
+    match template {
+        Some(template) => {
+            let mut prompt = template.replace(MATERIALS_PLACEHOLDER, &materials);
+            if !custom_prompt.trim().is_empty() {
+                prompt.push_str(&format!("\n\n额外要求：{}", custom_prompt));
+            }
+            prompt
+        }
+        None if custom_prompt.is_empty() => materials,
+        None => format!("{}\n\n以下是参考素材：\n\n{}", custom_prompt, materials),
+    }
+}
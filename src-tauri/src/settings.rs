@@ -0,0 +1,282 @@
+use crate::db::{Database, DedupScope};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+/// 唤起/隐藏主窗口的全局快捷键
+pub const TOGGLE_SHORTCUT: &str = "toggle_shortcut";
+
+pub const DEFAULT_TOGGLE_SHORTCUT: &str = "CmdOrCtrl+Shift+V";
+
+/// 清空搜索和筛选、回到默认列表的全局快捷键，为空时不注册
+pub const RESET_VIEW_SHORTCUT: &str = "reset_view_shortcut";
+
+/// 回收站中的条目保留天数，超过后由 clear_old_clips 永久删除
+pub const TRASH_KEEP_DAYS: &str = "trash_keep_days";
+
+/// 自动清理：历史记录保留天数，0 表示不按时间清理（置顶和收藏不受影响）
+pub const HISTORY_KEEP_DAYS: &str = "history_keep_days";
+/// 自动清理：按类型单独设置的保留天数，JSON 对象如 {"image": 7, "text": 90}；
+/// 未列出的类型沿用 HISTORY_KEEP_DAYS，0 表示该类型不按时间清理
+pub const HISTORY_KEEP_DAYS_BY_TYPE: &str = "history_keep_days_by_type";
+/// 自动清理：最多保留的条目数，0 表示不限制
+pub const MAX_HISTORY_ITEMS: &str = "max_history_items";
+/// 自动清理的执行间隔（小时）
+pub const CLEANUP_INTERVAL_HOURS: &str = "cleanup_interval_hours";
+
+/// 是否合并重复内容：关闭后每次复制都记录为新条目，便于按时间线回看
+pub const DEDUP_ENABLED: &str = "dedup_enabled";
+
+/// 去重范围：global 相同内容只保留一条；source_app 按来源应用分别去重，
+/// 从不同应用复制的相同内容各自成为一条记录
+pub const DEDUP_SCOPE: &str = "dedup_scope";
+
+/// 计算文本去重哈希前的规范化方式：none 原样，trim 去掉首尾空白，
+/// whitespace 在 trim 基础上把连续空白合并为一个空格。只影响哈希，保存的内容保持原样
+pub const HASH_NORMALIZATION: &str = "hash_normalization";
+/// 是否把文本中的 \r\n 换行统一为 \n 后再计算哈希和保存，使从 Windows 应用复制的文本
+/// 与相同内容的 \n 版本合并；关闭后保存原始内容
+pub const NORMALIZE_LINE_ENDINGS: &str = "normalize_line_endings";
+
+/// 是否按感知哈希合并相似图片（如不同缩放比例的同一截图）
+pub const PHASH_DEDUP: &str = "phash_dedup";
+/// 感知哈希的最大汉明距离，越大越容易误合并
+pub const PHASH_THRESHOLD: &str = "phash_threshold";
+/// 距上一张图片不到这么多毫秒、且感知哈希在 PHASH_THRESHOLD 以内的图片直接忽略，
+/// 减少录屏或连续截图时的重复条目；0 表示关闭
+pub const IMAGE_BURST_WINDOW_MS: &str = "image_burst_window_ms";
+
+/// 单条文本的最大字节数，超过后按 OVERSIZE_ACTION 处理
+pub const MAX_CONTENT_BYTES: &str = "max_content_bytes";
+/// 超大文本的处理方式：skip 跳过不记录，truncate 截断并追加标记
+pub const OVERSIZE_ACTION: &str = "oversize_action";
+
+/// 截图等图片的存储格式：png 无损（默认，适合含文字的截图），jpeg 适合照片，webp 为无损 WebP
+pub const IMAGE_FORMAT: &str = "image_format";
+/// JPEG 压缩质量（1-100）
+pub const IMAGE_QUALITY: &str = "image_quality";
+
+/// 快捷模板：隐藏窗口后等待按键松开、焦点切回目标应用的毫秒数（0-2000）
+pub const QUICK_COPY_DELAY_MS: &str = "quick_copy_delay_ms";
+/// 快捷模板：模拟 Cmd+C 后等待剪贴板变化的最长毫秒数（50-5000），变化后立即继续
+pub const QUICK_COPY_TIMEOUT_MS: &str = "quick_copy_timeout_ms";
+
+/// 选择条目后是否自动粘贴：关闭时只写入剪贴板并隐藏窗口，不模拟 Cmd+V
+pub const AUTO_PASTE: &str = "auto_paste";
+
+/// 是否允许 AI 生成时把输出直接输入到之前的应用。需要模拟按键，干扰较大，默认关闭
+pub const INLINE_GENERATION: &str = "inline_generation";
+
+/// 粘贴（或仅复制）完成后是否播放一声轻提示音
+pub const PASTE_FEEDBACK: &str = "paste_feedback";
+
+/// 后端生成文本（图片占位符、托盘菜单、默认模板）的语言：zh 或 en，首次启动时按系统语言设置
+pub const LOCALE: &str = "locale";
+
+/// 复制链接时是否自动请求网页获取标题。默认关闭，避免在用户不知情时访问复制的地址
+pub const LINK_PREVIEW: &str = "link_preview";
+
+/// 左键点击托盘图标的行为：toggle-at-mouse 在鼠标旁显示/隐藏窗口，
+/// toggle-at-tray 在托盘图标下方显示/隐藏窗口，menu 打开托盘菜单
+pub const TRAY_CLICK_ACTION: &str = "tray_click_action";
+
+/// 是否开启本地 HTTP API（仅监听 127.0.0.1，需要携带访问令牌）。默认关闭
+pub const ENABLE_LOCAL_API: &str = "enable_local_api";
+/// 本地 API 监听的端口
+pub const LOCAL_API_PORT: &str = "local_api_port";
+/// 本地 API 的访问令牌，首次启动时生成，只能通过 regenerate_local_api_token 更换
+pub const LOCAL_API_TOKEN: &str = "local_api_token";
+
+/// 写入日志文件的级别：off、error、warn、info、debug。默认关闭，排查问题时再开启
+pub const LOG_LEVEL: &str = "log_level";
+
+/// 可以单独设置保留天数的条目类型
+const RETENTION_CLIP_TYPES: &[&str] = &["text", "code", "url", "color", "json", "xml", "image", "gif"];
+
+/// 所有设置项的默认值，数据库中没有记录时使用
+pub fn defaults() -> HashMap<String, String> {
+    [
+        (TOGGLE_SHORTCUT, DEFAULT_TOGGLE_SHORTCUT),
+        (RESET_VIEW_SHORTCUT, ""),
+        (TRASH_KEEP_DAYS, "30"),
+        (HISTORY_KEEP_DAYS, "0"),
+        (HISTORY_KEEP_DAYS_BY_TYPE, "{}"),
+        (MAX_HISTORY_ITEMS, "0"),
+        (CLEANUP_INTERVAL_HOURS, "6"),
+        (DEDUP_ENABLED, "true"),
+        (DEDUP_SCOPE, "global"),
+        (HASH_NORMALIZATION, "none"),
+        (NORMALIZE_LINE_ENDINGS, "true"),
+        (PHASH_DEDUP, "true"),
+        (PHASH_THRESHOLD, "5"),
+        (IMAGE_BURST_WINDOW_MS, "2000"),
+        (MAX_CONTENT_BYTES, "1048576"),
+        (OVERSIZE_ACTION, "truncate"),
+        (IMAGE_FORMAT, "png"),
+        (IMAGE_QUALITY, "85"),
+        (QUICK_COPY_DELAY_MS, "300"),
+        (QUICK_COPY_TIMEOUT_MS, "1000"),
+        (LINK_PREVIEW, "false"),
+        (AUTO_PASTE, "true"),
+        (PASTE_FEEDBACK, "false"),
+        (INLINE_GENERATION, "false"),
+        (LOCALE, "zh"),
+        (TRAY_CLICK_ACTION, "toggle-at-mouse"),
+        (ENABLE_LOCAL_API, "false"),
+        (LOCAL_API_PORT, "21847"),
+        (LOG_LEVEL, "off"),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect()
+}
+
+/// 读取设置，缺失或读取失败时回退到默认值
+pub fn get(db: &Database, key: &str) -> String {
+    db.get_setting(key)
+        .ok()
+        .flatten()
+        .or_else(|| defaults().remove(key))
+        .unwrap_or_default()
+}
+
+/// 读取整数设置，无法解析时回退到默认值
+pub fn get_i64(db: &Database, key: &str) -> i64 {
+    get(db, key)
+        .parse()
+        .ok()
+        .or_else(|| defaults().get(key).and_then(|v| v.parse().ok()))
+        .unwrap_or_default()
+}
+
+/// 读取按类型的保留天数，格式错误时视为没有单独设置
+pub fn get_keep_days_by_type(db: &Database) -> HashMap<String, i64> {
+    serde_json::from_str(&get(db, HISTORY_KEEP_DAYS_BY_TYPE)).unwrap_or_default()
+}
+
+/// 按去重开关和去重范围得到写入条目时的合并方式
+pub fn get_dedup_scope(db: &Database) -> DedupScope {
+    if !get_bool(db, DEDUP_ENABLED) {
+        DedupScope::Off
+    } else if get(db, DEDUP_SCOPE) == "source_app" {
+        DedupScope::PerSourceApp
+    } else {
+        DedupScope::Global
+    }
+}
+
+/// 读取布尔设置（"true"/"false"）
+pub fn get_bool(db: &Database, key: &str) -> bool {
+    get(db, key) == "true"
+}
+
+/// 校验设置值，返回规范化后的值
+pub fn validate(key: &str, value: &str) -> Result<String, String> {
+    let value = value.trim();
+    match key {
+        TOGGLE_SHORTCUT => {
+            validate_shortcut(value)?;
+            Ok(value.to_string())
+        }
+        RESET_VIEW_SHORTCUT => {
+            if !value.is_empty() {
+                validate_shortcut(value)?;
+            }
+            Ok(value.to_string())
+        }
+        TRASH_KEEP_DAYS => validate_positive_int(key, value),
+        HISTORY_KEEP_DAYS => validate_int_range(key, value, 0, 36500),
+        HISTORY_KEEP_DAYS_BY_TYPE => validate_keep_days_by_type(key, value),
+        MAX_HISTORY_ITEMS => validate_int_range(key, value, 0, 1_000_000),
+        CLEANUP_INTERVAL_HOURS => validate_int_range(key, value, 1, 168),
+        DEDUP_ENABLED => validate_bool(key, value),
+        DEDUP_SCOPE => validate_choice(key, value, &["global", "source_app"]),
+        HASH_NORMALIZATION => validate_choice(key, value, &["none", "trim", "whitespace"]),
+        NORMALIZE_LINE_ENDINGS => validate_bool(key, value),
+        PHASH_DEDUP => validate_bool(key, value),
+        PHASH_THRESHOLD => validate_int_range(key, value, 0, 32),
+        IMAGE_BURST_WINDOW_MS => validate_int_range(key, value, 0, 60000),
+        MAX_CONTENT_BYTES => validate_positive_int(key, value),
+        OVERSIZE_ACTION => validate_choice(key, value, &["skip", "truncate"]),
+        IMAGE_FORMAT => validate_choice(key, value, &["png", "jpeg", "webp"]),
+        IMAGE_QUALITY => validate_int_range(key, value, 1, 100),
+        QUICK_COPY_DELAY_MS => validate_int_range(key, value, 0, 2000),
+        QUICK_COPY_TIMEOUT_MS => validate_int_range(key, value, 50, 5000),
+        LINK_PREVIEW => validate_bool(key, value),
+        AUTO_PASTE => validate_bool(key, value),
+        PASTE_FEEDBACK => validate_bool(key, value),
+        INLINE_GENERATION => validate_bool(key, value),
+        LOCALE => validate_choice(key, value, &["zh", "en"]),
+        TRAY_CLICK_ACTION => validate_choice(key, value, &["toggle-at-mouse", "toggle-at-tray", "menu"]),
+        ENABLE_LOCAL_API => validate_bool(key, value),
+        LOCAL_API_PORT => validate_int_range(key, value, 1024, 65535),
+        LOG_LEVEL => validate_choice(key, value, &["off", "error", "warn", "info", "debug"]),
+        _ => Err(format!("Unknown setting: {}", key)),
+    }
+}
+
+fn validate_positive_int(key: &str, value: &str) -> Result<String, String> {
+    match value.parse::<i64>() {
+        Ok(n) if n > 0 => Ok(n.to_string()),
+        _ => Err(format!("{} must be a positive integer", key)),
+    }
+}
+
+fn validate_int_range(key: &str, value: &str, min: i64, max: i64) -> Result<String, String> {
+    match value.parse::<i64>() {
+        Ok(n) if (min..=max).contains(&n) => Ok(n.to_string()),
+        _ => Err(format!("{} must be an integer between {} and {}", key, min, max)),
+    }
+}
+
+fn validate_keep_days_by_type(key: &str, value: &str) -> Result<String, String> {
+    let map: BTreeMap<String, i64> = serde_json::from_str(value).map_err(|_| {
+        format!("{} must be a JSON object of clip type to days, e.g. {{\"image\": 7}}", key)
+    })?;
+    for (clip_type, days) in &map {
+        if !RETENTION_CLIP_TYPES.contains(&clip_type.as_str()) {
+            return Err(format!("{}: unknown clip type \"{}\"", key, clip_type));
+        }
+        if !(0..=36500).contains(days) {
+            return Err(format!("{}: days for {} must be between 0 and 36500", key, clip_type));
+        }
+    }
+    serde_json::to_string(&map).map_err(|e| e.to_string())
+}
+
+fn validate_bool(key: &str, value: &str) -> Result<String, String> {
+    match value {
+        "true" | "false" => Ok(value.to_string()),
+        _ => Err(format!("{} must be true or false", key)),
+    }
+}
+
+fn validate_choice(key: &str, value: &str, choices: &[&str]) -> Result<String, String> {
+    if choices.contains(&value) {
+        Ok(value.to_string())
+    } else {
+        Err(format!("{} must be one of: {}", key, choices.join(", ")))
+    }
+}
+
+pub fn validate_shortcut(shortcut: &str) -> Result<(), String> {
+    if shortcut.is_empty() {
+        return Err("Shortcut cannot be empty".to_string());
+    }
+    tauri_plugin_global_shortcut::Shortcut::from_str(shortcut)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid shortcut \"{}\": {}", shortcut, e))
+}
+
+/// 将快捷键转换为托盘菜单中显示的形式，例如 CmdOrCtrl+Shift+V -> Cmd+Shift+V
+pub fn shortcut_label(shortcut: &str) -> String {
+    shortcut
+        .split('+')
+        .map(|part| match part {
+            "CmdOrCtrl" | "CommandOrControl" | "CommandOrCtrl" | "CmdOrControl" | "Command"
+            | "Super" => "Cmd",
+            "Option" => "Alt",
+            other => other,
+        })
+        .collect::<Vec<_>>()
+        .join("+")
+}
@@ -0,0 +1,208 @@
+/// 把字节流切成行，兼容 \n、\r\n 和 \r 结尾。按完整的行解码 UTF-8，
+/// 因此跨网络块的多字节字符（中文等）不会被拆成乱码
+#[derive(Debug, Default)]
+pub struct LineDecoder {
+    buffer: Vec<u8>,
+}
+
+impl LineDecoder {
+    /// 追加一块数据，返回其中已完整的行（不含换行符）
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < self.buffer.len() {
+            match self.buffer[i] {
+                b'\n' => {
+                    lines.push(String::from_utf8_lossy(&self.buffer[start..i]).into_owned());
+                    i += 1;
+                    start = i;
+                }
+                b'\r' => {
+                    // 块末尾的 \r 可能是 \r\n 的前半部分，等下一块再决定
+                    if i + 1 == self.buffer.len() {
+                        break;
+                    }
+                    lines.push(String::from_utf8_lossy(&self.buffer[start..i]).into_owned());
+                    i += if self.buffer[i + 1] == b'\n' { 2 } else { 1 };
+                    start = i;
+                }
+                _ => i += 1,
+            }
+        }
+        self.buffer.drain(..start);
+        lines
+    }
+
+    /// 流结束时取出剩余的最后一行（没有以换行结尾）
+    pub fn finish(&mut self) -> Option<String> {
+        let mut rest = std::mem::take(&mut self.buffer);
+        if rest.last() == Some(&b'\r') {
+            rest.pop();
+        }
+        if rest.is_empty() {
+            None
+        } else {
+            Some(String::from_utf8_lossy(&rest).into_owned())
+        }
+    }
+}
+
+/// 一个完整的 Server-Sent Event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// event: 字段，未指定时为 None
+    pub event: Option<String>,
+    /// 所有 data: 行按换行拼接后的内容
+    pub data: String,
+}
+
+/// 按 SSE 规范把字节流重组为事件：空行结束一个事件，多行 data 用换行拼接，
+/// 冒号后的单个空格可有可无，":" 开头的注释（心跳）以及 id:、retry: 等字段被忽略
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    lines: LineDecoder,
+    event: Option<String>,
+    data: String,
+    has_data: bool,
+}
+
+impl SseDecoder {
+    /// 追加一块数据，返回其中已完整的事件
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        self.lines
+            .push(bytes)
+            .iter()
+            .filter_map(|line| self.line(line))
+            .collect()
+    }
+
+    /// 流结束时取出最后一个事件；有的服务器在最后一个事件后不发送空行
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if let Some(event) = self.lines.finish().and_then(|line| self.line(&line)) {
+            return Some(event);
+        }
+        self.dispatch()
+    }
+
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.find(':') {
+            Some(colon) => (&line[..colon], &line[colon + 1..]),
+            None => (line, ""),
+        };
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "data" => {
+                if self.has_data {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "event" => self.event = Some(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if !std::mem::take(&mut self.has_data) {
+            return None;
+        }
+        Some(SseEvent { event, data: std::mem::take(&mut self.data) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(events: &[SseEvent]) -> Vec<&str> {
+        events.iter().map(|e| e.data.as_str()).collect()
+    }
+
+    /// 逐字节喂入，模拟事件在任意位置被网络分块切开
+    fn decode_bytewise(input: &[u8]) -> Vec<SseEvent> {
+        let mut decoder = SseDecoder::default();
+        let mut events: Vec<SseEvent> = input.iter().flat_map(|b| decoder.push(&[*b])).collect();
+        events.extend(decoder.finish());
+        events
+    }
+
+    #[test]
+    fn splits_events_on_blank_lines() {
+        let mut decoder = SseDecoder::default();
+        let events = decoder.push(b"data: {\"a\":1}\n\ndata: {\"a\":2}\n\n");
+        assert_eq!(data(&events), vec!["{\"a\":1}", "{\"a\":2}"]);
+    }
+
+    #[test]
+    fn reassembles_events_across_chunks() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b"da").is_empty());
+        assert!(decoder.push(b"ta: {\"text\":").is_empty());
+        assert!(decoder.push(b"\"hi\"}\n").is_empty());
+        assert_eq!(data(&decoder.push(b"\ndata: [DONE]\n")), vec!["{\"text\":\"hi\"}"]);
+        assert_eq!(data(&decoder.push(b"\n")), vec!["[DONE]"]);
+    }
+
+    #[test]
+    fn handles_crlf_split_between_chunks() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b"data: one\r").is_empty());
+        assert!(decoder.push(b"\n\r").is_empty());
+        assert_eq!(data(&decoder.push(b"\ndata: two\r\n\r\n")), vec!["one", "two"]);
+        assert_eq!(data(&decode_bytewise(b"data: a\r\n\r\ndata: b\r\rdata: c\n\n")), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn accepts_data_without_space() {
+        assert_eq!(data(&decode_bytewise(b"data:{\"x\":1}\n\ndata:  two\n\n")), vec!["{\"x\":1}", " two"]);
+    }
+
+    #[test]
+    fn joins_multiline_data_and_keeps_event_name() {
+        let events = decode_bytewise(b"event: content_block_delta\nid: 7\ndata: line1\ndata: line2\n\n");
+        assert_eq!(
+            events,
+            vec![SseEvent { event: Some("content_block_delta".to_string()), data: "line1\nline2".to_string() }]
+        );
+    }
+
+    #[test]
+    fn ignores_comments_and_events_without_data() {
+        let events = decode_bytewise(b": keep-alive\n\nevent: ping\n\nretry: 100\ndata: x\n\n");
+        assert_eq!(events, vec![SseEvent { event: None, data: "x".to_string() }]);
+    }
+
+    #[test]
+    fn keeps_multibyte_characters_split_across_chunks() {
+        let input = "data: {\"text\":\"你好，世界\"}\n\n".as_bytes();
+        assert_eq!(data(&decode_bytewise(input)), vec!["{\"text\":\"你好，世界\"}"]);
+    }
+
+    #[test]
+    fn flushes_last_event_without_trailing_blank_line() {
+        assert_eq!(data(&decode_bytewise(b"data: a\n\ndata: [DONE]")), vec!["a", "[DONE]"]);
+        assert_eq!(data(&decode_bytewise(b"data: a\n")), vec!["a"]);
+    }
+
+    #[test]
+    fn line_decoder_returns_trailing_line_on_finish() {
+        let input = "{\"response\":\"中\"}\n{\"done\":true}".as_bytes();
+        let mut lines = LineDecoder::default();
+        // 在“中”的 UTF-8 字节中间切开
+        assert!(lines.push(&input[..14]).is_empty());
+        assert_eq!(lines.push(&input[14..]), vec!["{\"response\":\"中\"}"]);
+        assert_eq!(lines.finish().as_deref(), Some("{\"done\":true}"));
+        assert_eq!(lines.finish(), None);
+    }
+}
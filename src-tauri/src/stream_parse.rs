@@ -0,0 +1,205 @@
+use serde_json::Value;
+
+/// 从一行（或一个 SSE 事件）流式响应中解析出的片段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Delta {
+    /// 回答正文
+    Content(String),
+    /// 模型的推理/思考过程，不计入最终结果
+    Reasoning(String),
+    /// token 用量，None 表示该事件未提供对应字段
+    Usage {
+        prompt_tokens: Option<i64>,
+        completion_tokens: Option<i64>,
+    },
+    /// 流已结束
+    Done,
+}
+
+/// OpenAI 兼容接口的一个 SSE data 内容。无法解析的行返回空列表
+pub fn parse_openai_line(data: &str) -> Vec<Delta> {
+    let data = data.trim();
+    if data == "[DONE]" {
+        return vec![Delta::Done];
+    }
+    let Ok(parsed) = serde_json::from_str::<Value>(data) else {
+        return Vec::new();
+    };
+    let mut deltas = Vec::new();
+    // Reasoning models stream their thinking as delta.reasoning
+    // (OpenRouter and others) or delta.reasoning_content (DeepSeek, Kimi)
+    let delta = &parsed["choices"][0]["delta"];
+    if let Some(reasoning) = delta["reasoning"].as_str().or(delta["reasoning_content"].as_str()) {
+        if !reasoning.is_empty() {
+            deltas.push(Delta::Reasoning(reasoning.to_string()));
+        }
+    }
+    if let Some(content) = delta["content"].as_str() {
+        deltas.push(Delta::Content(content.to_string()));
+    }
+    // The final chunk carries usage when include_usage is set
+    if parsed["usage"].is_object() {
+        deltas.push(Delta::Usage {
+            prompt_tokens: parsed["usage"]["prompt_tokens"].as_i64(),
+            completion_tokens: parsed["usage"]["completion_tokens"].as_i64(),
+        });
+    }
+    deltas
+}
+
+/// Claude Messages 接口的一个 SSE data 内容，事件类型取自 JSON 中的 type 字段
+pub fn parse_claude_event(data: &str) -> Vec<Delta> {
+    let Ok(parsed) = serde_json::from_str::<Value>(data) else {
+        return Vec::new();
+    };
+    match parsed["type"].as_str().unwrap_or("") {
+        "message_start" => vec![Delta::Usage {
+            prompt_tokens: parsed["message"]["usage"]["input_tokens"].as_i64(),
+            completion_tokens: None,
+        }],
+        "message_delta" => vec![Delta::Usage {
+            prompt_tokens: None,
+            completion_tokens: parsed["usage"]["output_tokens"].as_i64(),
+        }],
+        "content_block_delta" => {
+            let delta = &parsed["delta"];
+            let text = match delta["type"].as_str() {
+                Some("thinking_delta") => delta["thinking"].as_str().map(|t| Delta::Reasoning(t.to_string())),
+                _ => delta["text"].as_str().map(|t| Delta::Content(t.to_string())),
+            };
+            text.into_iter().collect()
+        }
+        "message_stop" => vec![Delta::Done],
+        _ => Vec::new(),
+    }
+}
+
+/// Ollama /api/generate 的一行 NDJSON
+pub fn parse_ollama_line(line: &str) -> Vec<Delta> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Vec::new();
+    }
+    let Ok(parsed) = serde_json::from_str::<Value>(line) else {
+        return Vec::new();
+    };
+    let mut deltas = Vec::new();
+    if let Some(response) = parsed["response"].as_str() {
+        deltas.push(Delta::Content(response.to_string()));
+    }
+    if parsed["done"].as_bool() == Some(true) {
+        deltas.push(Delta::Usage {
+            prompt_tokens: parsed["prompt_eval_count"].as_i64(),
+            completion_tokens: parsed["eval_count"].as_i64(),
+        });
+        deltas.push(Delta::Done);
+    }
+    deltas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sse::{LineDecoder, SseDecoder};
+
+    /// 按 chunk_size 字节切块喂给 SSE 解码器，再逐个事件解析
+    fn run_sse(fixture: &str, chunk_size: usize, parse: fn(&str) -> Vec<Delta>) -> Vec<Delta> {
+        let mut decoder = SseDecoder::default();
+        let mut events: Vec<_> = fixture.as_bytes().chunks(chunk_size).flat_map(|c| decoder.push(c)).collect();
+        events.extend(decoder.finish());
+        events.iter().flat_map(|e| parse(&e.data)).collect()
+    }
+
+    fn text(deltas: &[Delta]) -> String {
+        deltas
+            .iter()
+            .filter_map(|d| match d {
+                Delta::Content(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    const OPENAI_FIXTURE: &str = "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\",\"content\":\"\"}}]}\n\n\
+        data: {\"choices\":[{\"delta\":{\"reasoning_content\":\"想一想\"}}]}\n\n\
+        : keep-alive\n\n\
+        data: {\"choices\":[{\"delta\":{\"content\":\"你好\"}}]}\r\n\r\n\
+        data:{\"choices\":[{\"delta\":{\"content\":\"，世界\"}}]}\n\n\
+        data: {\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":4}}\n\n\
+        data: [DONE]\n\n";
+
+    #[test]
+    fn parses_openai_deltas() {
+        assert_eq!(
+            parse_openai_line("{\"choices\":[{\"delta\":{\"reasoning\":\"r\",\"content\":\"c\"}}]}"),
+            vec![Delta::Reasoning("r".to_string()), Delta::Content("c".to_string())]
+        );
+        assert_eq!(parse_openai_line(" [DONE] "), vec![Delta::Done]);
+        assert!(parse_openai_line("not json").is_empty());
+    }
+
+    #[test]
+    fn openai_fixture_survives_any_chunking() {
+        for chunk_size in [1, 2, 3, 7, 64, OPENAI_FIXTURE.len()] {
+            let deltas = run_sse(OPENAI_FIXTURE, chunk_size, parse_openai_line);
+            assert_eq!(text(&deltas), "你好，世界", "chunk size {}", chunk_size);
+            assert!(deltas.contains(&Delta::Reasoning("想一想".to_string())));
+            assert!(deltas.contains(&Delta::Usage { prompt_tokens: Some(9), completion_tokens: Some(4) }));
+            assert_eq!(deltas.last(), Some(&Delta::Done));
+        }
+    }
+
+    const CLAUDE_FIXTURE: &str = "event: message_start\n\
+        data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":12,\"output_tokens\":1}}}\n\n\
+        event: content_block_delta\n\
+        data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"thinking_delta\",\"thinking\":\"先分析\"}}\n\n\
+        event: content_block_delta\n\
+        data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"signature_delta\",\"signature\":\"abc\"}}\n\n\
+        event: ping\n\
+        data: {\"type\":\"ping\"}\n\n\
+        event: content_block_delta\n\
+        data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello \"}}\n\n\
+        event: content_block_delta\n\
+        data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"text_delta\",\"text\":\"世界\"}}\n\n\
+        event: message_delta\n\
+        data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":5}}\n\n\
+        event: message_stop\n\
+        data: {\"type\":\"message_stop\"}\n\n";
+
+    #[test]
+    fn claude_fixture_survives_any_chunking() {
+        for chunk_size in [1, 5, 16, CLAUDE_FIXTURE.len()] {
+            let deltas = run_sse(CLAUDE_FIXTURE, chunk_size, parse_claude_event);
+            assert_eq!(
+                deltas,
+                vec![
+                    Delta::Usage { prompt_tokens: Some(12), completion_tokens: None },
+                    Delta::Reasoning("先分析".to_string()),
+                    Delta::Content("Hello ".to_string()),
+                    Delta::Content("世界".to_string()),
+                    Delta::Usage { prompt_tokens: None, completion_tokens: Some(5) },
+                    Delta::Done,
+                ],
+                "chunk size {}",
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn ollama_lines_survive_any_chunking() {
+        let fixture = "{\"response\":\"你\",\"done\":false}\n{\"response\":\"好\",\"done\":false}\r\n\n\
+            {\"response\":\"\",\"done\":true,\"prompt_eval_count\":3,\"eval_count\":2}";
+        for chunk_size in [1, 4, fixture.len()] {
+            let mut decoder = LineDecoder::default();
+            let mut lines: Vec<_> = fixture.as_bytes().chunks(chunk_size).flat_map(|c| decoder.push(c)).collect();
+            lines.extend(decoder.finish());
+            let deltas: Vec<_> = lines.iter().flat_map(|l| parse_ollama_line(l)).collect();
+            assert_eq!(text(&deltas), "你好");
+            assert_eq!(
+                &deltas[deltas.len() - 2..],
+                &[Delta::Usage { prompt_tokens: Some(3), completion_tokens: Some(2) }, Delta::Done]
+            );
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use quick_xml::events::Event;
+use quick_xml::{Reader, Writer};
+
+/// 结构化内容（JSON/XML）检测时最多解析的字节数，避免超大剪贴板内容阻塞监听线程
+pub const MAX_STRUCTURED_PARSE_BYTES: usize = 256 * 1024;
+
+/// 完整内容是否为 JSON 对象或数组
+pub fn is_json(text: &str) -> bool {
+    let trimmed = text.trim();
+    if trimmed.len() > MAX_STRUCTURED_PARSE_BYTES
+        || !(trimmed.starts_with('{') || trimmed.starts_with('['))
+    {
+        return false;
+    }
+    serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+}
+
+/// 完整内容是否为格式良好的 XML 文档（单一根元素，标签闭合）
+pub fn is_xml(text: &str) -> bool {
+    let trimmed = text.trim();
+    if trimmed.len() > MAX_STRUCTURED_PARSE_BYTES || !trimmed.starts_with('<') {
+        return false;
+    }
+
+    let mut reader = Reader::from_str(trimmed);
+    let mut depth = 0usize;
+    let mut roots = 0usize;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(_)) => {
+                if depth == 0 {
+                    roots += 1;
+                }
+                depth += 1;
+            }
+            Ok(Event::End(_)) => depth -= 1,
+            Ok(Event::Empty(_)) => {
+                if depth == 0 {
+                    roots += 1;
+                }
+            }
+            Ok(Event::Text(t)) if depth == 0 => {
+                if !t.iter().all(|b| b.is_ascii_whitespace()) {
+                    return false;
+                }
+            }
+            Ok(Event::Eof) => return depth == 0 && roots == 1,
+            Ok(_) => {}
+            Err(_) => return false,
+        }
+    }
+}
+
+pub fn pretty_json(text: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(text.trim()).ok()?;
+    serde_json::to_string_pretty(&value).ok()
+}
+
+pub fn pretty_xml(text: &str) -> Option<String> {
+    let mut reader = Reader::from_str(text.trim());
+    reader.config_mut().trim_text(true);
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    loop {
+        match reader.read_event().ok()? {
+            Event::Eof => break,
+            event => writer.write_event(event).ok()?,
+        }
+    }
+    String::from_utf8(writer.into_inner()).ok()
+}
+
+/// 如果内容是 JSON 或 XML 则重新缩进，否则原样返回
+pub fn pretty_print(text: &str) -> String {
+    if is_json(text) {
+        if let Some(pretty) = pretty_json(text) {
+            return pretty;
+        }
+    }
+    if is_xml(text) {
+        if let Some(pretty) = pretty_xml(text) {
+            return pretty;
+        }
+    }
+    text.to_string()
+}
+
+/// 将 Markdown 转为纯文本，用于粘贴到不支持 Markdown 的应用：
+/// 去掉标题/引用/无序列表标记、分隔线、代码块围栏和行内强调，链接只保留文字
+pub fn strip_markdown(text: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_code_block = false;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            lines.push(line.to_string());
+            continue;
+        }
+        if is_thematic_break(trimmed) {
+            continue;
+        }
+        let indent = &line[..line.len() - trimmed.len()];
+        let body = strip_block_marker(trimmed);
+        lines.push(format!("{}{}", indent, strip_inline(body)));
+    }
+    lines.join("\n")
+}
+
+/// 模板对 AI 最终输出的后处理方式，以字符串形式存入 templates.post_process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostProcess {
+    #[default]
+    None,
+    Trim,
+    JsonPretty,
+    StripMarkdown,
+}
+
+impl PostProcess {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PostProcess::None => "none",
+            PostProcess::Trim => "trim",
+            PostProcess::JsonPretty => "json_pretty",
+            PostProcess::StripMarkdown => "strip_markdown",
+        }
+    }
+
+    /// 解析存储的值，无法识别时不做处理
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "trim" => PostProcess::Trim,
+            "json_pretty" => PostProcess::JsonPretty,
+            "strip_markdown" => PostProcess::StripMarkdown,
+            _ => PostProcess::None,
+        }
+    }
+
+    /// 处理生成结果；内容不符合要求（如不是合法 JSON）时返回错误说明，由调用方决定是否保留原文
+    pub fn apply(self, text: &str) -> Result<String, String> {
+        match self {
+            PostProcess::None => Ok(text.to_string()),
+            PostProcess::Trim => Ok(text.trim().to_string()),
+            PostProcess::JsonPretty => {
+                // 模型常把 JSON 包在 ```json 代码块里
+                pretty_json(strip_code_fence(text)).ok_or_else(|| "output is not valid JSON".to_string())
+            }
+            PostProcess::StripMarkdown => Ok(strip_markdown(text)),
+        }
+    }
+}
+
+/// 去掉包住全文的代码块围栏（含语言标记），没有围栏时原样返回
+fn strip_code_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let Some(body) = rest.strip_suffix("```") else {
+        return trimmed;
+    };
+    // 第一行剩下的是语言标记
+    match body.find('\n') {
+        Some(newline) => &body[newline + 1..],
+        None => trimmed,
+    }
+}
+
+/// 分隔线：三个及以上相同的 - * _（可夹空格）
+fn is_thematic_break(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.len() >= 3
+        && matches!(chars[0], '-' | '*' | '_')
+        && chars.iter().all(|&c| c == chars[0])
+}
+
+/// 去掉行首的标题、引用、无序列表和任务列表标记；有序列表的序号保留
+fn strip_block_marker(line: &str) -> &str {
+    let mut line = line;
+    while let Some(rest) = line.strip_prefix('>') {
+        line = rest.trim_start();
+    }
+
+    let hashes = line.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&hashes) {
+        let rest = &line[hashes..];
+        if rest.is_empty() || rest.starts_with(' ') {
+            return rest.trim().trim_end_matches('#').trim_end();
+        }
+    }
+
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(marker) {
+            let rest = rest.trim_start();
+            for task in ["[ ] ", "[x] ", "[X] "] {
+                if let Some(item) = rest.strip_prefix(task) {
+                    return item;
+                }
+            }
+            return rest;
+        }
+    }
+    line
+}
+
+fn strip_inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if i + 1 < chars.len() && chars[i + 1].is_ascii_punctuation() => {
+                out.push(chars[i + 1]);
+                i += 2;
+            }
+            '`' => {
+                let ticks = chars[i..].iter().take_while(|&&c| c == '`').count();
+                match find_run(&chars, i + ticks, '`', ticks) {
+                    Some(end) => {
+                        let code: String = chars[i + ticks..end].iter().collect();
+                        out.push_str(code.trim());
+                        i = end + ticks;
+                    }
+                    None => {
+                        out.extend(&chars[i..i + ticks]);
+                        i += ticks;
+                    }
+                }
+            }
+            '!' if chars.get(i + 1) == Some(&'[') => match parse_link(&chars, i + 1) {
+                Some((label, end)) => {
+                    out.push_str(&strip_inline(&label));
+                    i = end;
+                }
+                None => {
+                    out.push(c);
+                    i += 1;
+                }
+            },
+            '[' => match parse_link(&chars, i) {
+                Some((label, end)) => {
+                    out.push_str(&strip_inline(&label));
+                    i = end;
+                }
+                None => {
+                    out.push(c);
+                    i += 1;
+                }
+            },
+            '*' | '_' | '~' => {
+                let run = chars[i..].iter().take_while(|&&d| d == c).count();
+                let before = if i == 0 { None } else { Some(chars[i - 1]) };
+                let after = chars.get(i + run).copied();
+                // 强调标记直接丢弃，其余按原样保留
+                if !is_emphasis_delimiter(c, run, before, after) {
+                    out.extend(&chars[i..i + run]);
+                }
+                i += run;
+            }
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// 判断一串 * _ ~ 是否为强调标记：至少一侧紧贴文字；下划线不能在单词中间（如 snake_case）
+fn is_emphasis_delimiter(c: char, run: usize, before: Option<char>, after: Option<char>) -> bool {
+    let touches = |ch: Option<char>| ch.is_some_and(|ch| !ch.is_whitespace());
+    let left_flanking = touches(after);
+    let right_flanking = touches(before);
+    match c {
+        '~' => run == 2 && (left_flanking || right_flanking),
+        '_' => {
+            let word = |ch: Option<char>| ch.is_some_and(|ch| ch.is_alphanumeric());
+            run <= 3 && (left_flanking || right_flanking) && !(word(before) && word(after))
+        }
+        _ => run <= 3 && (left_flanking || right_flanking),
+    }
+}
+
+/// 从 start 起查找恰好 len 个连续 target 的位置
+fn find_run(chars: &[char], start: usize, target: char, len: usize) -> Option<usize> {
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == target {
+            let run = chars[i..].iter().take_while(|&&c| c == target).count();
+            if run == len {
+                return Some(i);
+            }
+            i += run;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// 解析 [文字](地址)，返回文字和链接结束后的位置
+fn parse_link(chars: &[char], open: usize) -> Option<(String, usize)> {
+    let mut depth = 0;
+    let mut close = None;
+    for (j, &c) in chars.iter().enumerate().skip(open) {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(j);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let close = close?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = chars[close + 2..].iter().position(|&c| c == ')')? + close + 2;
+    Some((chars[open + 1..close].iter().collect(), end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_headings() {
+        assert_eq!(strip_markdown("# Title\n## Sub title ##\n#hashtag"), "Title\nSub title\n#hashtag");
+    }
+
+    #[test]
+    fn strips_list_markers() {
+        let md = "- one\n* two\n  + nested\n- [x] done\n1. first\n2. second";
+        assert_eq!(strip_markdown(md), "one\ntwo\n  nested\ndone\n1. first\n2. second");
+    }
+
+    #[test]
+    fn strips_bold_and_italic() {
+        assert_eq!(strip_markdown("**bold** and *italic* and ***both***"), "bold and italic and both");
+        assert_eq!(strip_markdown("__bold__ _it_ ~~gone~~"), "bold it gone");
+    }
+
+    #[test]
+    fn keeps_literal_symbols() {
+        assert_eq!(strip_markdown("2 * 3 = 6"), "2 * 3 = 6");
+        assert_eq!(strip_markdown("use snake_case_name"), "use snake_case_name");
+        assert_eq!(strip_markdown(r"\*not italic\*"), "*not italic*");
+    }
+
+    #[test]
+    fn strips_links_code_and_quotes() {
+        assert_eq!(strip_markdown("see [docs](https://x.y) ![logo](a.png)"), "see docs logo");
+        assert_eq!(strip_markdown("run `cargo test` now"), "run cargo test now");
+        assert_eq!(strip_markdown("> quoted **text**"), "quoted text");
+        assert_eq!(strip_markdown("a\n\n---\nb"), "a\n\nb");
+    }
+
+    #[test]
+    fn keeps_code_block_content() {
+        let md = "```rust\nlet x = a * b; // **not bold**\n```";
+        assert_eq!(strip_markdown(md), "let x = a * b; // **not bold**");
+    }
+
+    #[test]
+    fn post_process_pretty_prints_fenced_json() {
+        let raw = "```json\n{\"a\":[1,2]}\n```";
+        assert_eq!(PostProcess::JsonPretty.apply(raw).unwrap(), "{\n  \"a\": [\n    1,\n    2\n  ]\n}");
+        assert_eq!(PostProcess::JsonPretty.apply(" {\"b\":1} ").unwrap(), "{\n  \"b\": 1\n}");
+        assert!(PostProcess::JsonPretty.apply("Sure! Here is the JSON").is_err());
+    }
+
+    #[test]
+    fn post_process_names_round_trip() {
+        for kind in [PostProcess::None, PostProcess::Trim, PostProcess::JsonPretty, PostProcess::StripMarkdown] {
+            assert_eq!(PostProcess::from_name(kind.as_str()), kind);
+        }
+        assert_eq!(PostProcess::from_name("unknown"), PostProcess::None);
+        assert_eq!(PostProcess::Trim.apply("  hi \n").unwrap(), "hi");
+    }
+}
@@ -1,8 +1,7 @@
 import { useState, useEffect, useCallback, useRef } from "react";
 import { listen } from "@tauri-apps/api/event";
-import { readText } from "@tauri-apps/plugin-clipboard-manager";
 import { getCurrentWindow } from "@tauri-apps/api/window";
-import type { Template, AppView, ClipItem } from "./types";
+import type { Template, AppView, ClipItem, QuickTemplatePayload } from "./types";
 import { ClipList } from "./components/ClipList";
 import { GenerateView } from "./components/GenerateView";
 import { SettingsView } from "./components/SettingsView";
@@ -34,17 +33,10 @@ function App() {
   // Listen for quick-template shortcut events — register once
   useEffect(() => {
     let cancelled = false;
-    const unlisten = listen<string>("quick-template", async (event) => {
+    const unlisten = listen<QuickTemplatePayload>("quick-template", async (event) => {
       if (cancelled) return;
       try {
-        const templateId = event.payload;
-
-        let text = "";
-        try {
-          text = await readText() || "";
-        } catch {
-          // clipboard read may fail
-        }
+        const { template_id: templateId, text } = event.payload;
 
         const tpl = templatesRef.current.find((t) => t.id === templateId) || null;
 
@@ -54,13 +46,28 @@ function App() {
 
         const virtualItem = {
           id: "quick-template",
-          content: text || "",
+          content: text,
           content_hash: "",
           clip_type: "text" as const,
           source_app: null,
           image_path: null,
           is_pinned: false,
           created_at: new Date().toISOString(),
+          image_width: null,
+          image_height: null,
+          image_bytes: null,
+          code_language: null,
+          deleted_at: null,
+          is_favorite: false,
+          phash: null,
+          original_length: null,
+          preview: null,
+          content_length: null,
+          pin_order: null,
+          title: null,
+          expires_at: null,
+          copy_count: 1,
+          age_seconds: 0,
         };
 
         setQuickItems([virtualItem]);
@@ -69,7 +76,7 @@ function App() {
         ai.setError(null);
         setView("generate");
 
-        if (!text?.trim()) {
+        if (!text.trim()) {
           ai.setError("剪贴板为空，请先复制一些文本");
           return;
         }
@@ -79,9 +86,36 @@ function App() {
         console.error("Quick template failed:", e);
       }
     });
+    // 快捷键触发时没有选中任何内容（剪贴板未变化）
+    const unlistenEmpty = listen<string>("quick-template-empty", (event) => {
+      if (cancelled) return;
+      setQuickItems([]);
+      setQuickTemplateId(event.payload);
+      ai.setOutput("");
+      ai.setError("没有选中文本，请先选中要处理的内容再按快捷键");
+      setView("generate");
+    });
     return () => {
       cancelled = true;
       unlisten.then((fn) => fn());
+      unlistenEmpty.then((fn) => fn());
+    };
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, []);
+
+  // pastego://search?q=... 打开窗口后在历史中搜索
+  useEffect(() => {
+    const unlisten = listen<string>("deep-link-search", (event) => {
+      setView("history");
+      clipboard.setSearch(event.payload);
+    });
+    // 清空搜索的快捷键同时回到历史列表，筛选由 useClipboard 重置
+    const unlistenReset = listen("reset-view", () => {
+      setView("history");
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+      unlistenReset.then((fn) => fn());
     };
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, []);
@@ -100,9 +134,7 @@ function App() {
   // 批量删除选中的条目
   const handleDeleteSelected = useCallback(async () => {
     const ids = Array.from(selection.selectedIds);
-    for (const id of ids) {
-      await clipboard.deleteClip(id);
-    }
+    await clipboard.deleteClips(ids);
     selection.removeAll(ids);
   }, [selection, clipboard]);
 
@@ -175,17 +207,38 @@ function App() {
 
       {/* Main content */}
       <div className="main-content">
+        {clipboard.monitorError && (
+          <div className="monitor-warning" title={clipboard.monitorError}>
+            剪贴板暂时无法访问，正在重试…
+          </div>
+        )}
+        {clipboard.monitoringPaused && (
+          <div className="monitor-warning">
+            已暂停监听，复制的内容不会记录
+            <button className="btn-link" onClick={() => clipboard.setPaused(false)}>
+              恢复
+            </button>
+          </div>
+        )}
         {view === "history" && (
           <ClipList
             clips={clipboard.clips}
+            clipDays={clipboard.clipDays}
+            typeCounts={clipboard.typeCounts}
             search={clipboard.search}
             onSearchChange={clipboard.setSearch}
             typeFilter={clipboard.typeFilter}
             onTypeFilterChange={clipboard.setTypeFilter}
+            sort={clipboard.sort}
+            onSortChange={clipboard.setSort}
             isSelected={selection.isSelected}
             onToggleSelect={selection.toggle}
             onDelete={(id: string) => { clipboard.deleteClip(id); selection.remove(id); }}
             onTogglePin={clipboard.togglePin}
+            onFetchTitle={clipboard.fetchUrlTitle}
+            onSetExpiry={clipboard.setClipExpiry}
+            onUpdateContent={clipboard.updateClipContent}
+            onBump={clipboard.bumpClip}
             selectedCount={selection.selectedCount}
             onStartGenerate={handleStartGenerate}
             onSelectAll={() => selection.selectAll(clipboard.clips)}
@@ -201,9 +254,15 @@ function App() {
             templates={ai.templates}
             providers={ai.providers}
             output={ai.output}
+            reasoning={ai.reasoning}
             generating={ai.generating}
+            queued={ai.queued}
+            batchProgress={ai.batchProgress}
             error={ai.error}
+            errorCode={ai.errorCode}
             onGenerate={ai.generate}
+            onGenerateBatch={ai.generateBatch}
+            onPreviewPrompt={ai.previewPrompt}
             onBack={() => { setQuickItems([]); setQuickTemplateId(null); setSnapshotItems([]); setView("history"); }}
             onNavigateSettings={() => setView("settings")}
             initialTemplateId={quickTemplateId}
@@ -218,6 +277,7 @@ function App() {
             onDeleteProvider={ai.deleteProvider}
             onSaveTemplate={ai.saveTemplate}
             onDeleteTemplate={ai.deleteTemplate}
+            onDuplicateTemplate={ai.duplicateTemplate}
             onBack={() => setView("history")}
           />
         )}
@@ -1,7 +1,9 @@
-import { useState, useRef, useEffect, useCallback } from "react";
+import { useState, useRef, useEffect, useCallback, Fragment, type ReactNode } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { writeText } from "@tauri-apps/plugin-clipboard-manager";
-import type { ClipItem, ClipTypeFilter } from "../types";
+import { open } from "@tauri-apps/plugin-shell";
+import type { ClipItem, ClipTypeFilter, ClipDay } from "../types";
+import { getClipContent } from "../hooks/useClipboard";
 
 const TYPE_FILTERS: { key: ClipTypeFilter; label: string; icon: string }[] = [
   { key: "all", label: "全部", icon: "" },
@@ -20,16 +22,58 @@ const SEPARATOR_OPTIONS = [
   { key: "dash", label: "分隔线", value: "\n---\n" },
 ];
 
+// 自动删除时间选项
+const EXPIRY_OPTIONS = [
+  { label: "5分", title: "5 分钟后删除", seconds: 5 * 60 },
+  { label: "1时", title: "1 小时后删除", seconds: 60 * 60 },
+  { label: "1天", title: "1 天后删除", seconds: 24 * 60 * 60 },
+];
+
+/** 完整内容的长度，列表条目只带摘要时使用后端返回的长度 */
+function clipLength(clip: ClipItem): number {
+  return clip.content_length ?? clip.content.length;
+}
+
+/** 按后端返回的区间高亮搜索词；text 是 preview 的前缀，超出部分的区间忽略 */
+function highlightMatches(text: string, ranges: [number, number][] | null | undefined): ReactNode {
+  if (!ranges || ranges.length === 0) return text;
+  const parts: ReactNode[] = [];
+  let last = 0;
+  for (const [start, end] of ranges) {
+    if (start >= text.length) break;
+    const stop = Math.min(end, text.length);
+    parts.push(text.slice(last, start));
+    parts.push(
+      <mark key={start} className="search-match">
+        {text.slice(start, stop)}
+      </mark>
+    );
+    last = stop;
+  }
+  parts.push(text.slice(last));
+  return parts;
+}
+
 interface Props {
   clips: ClipItem[];
+  clipDays: ClipDay[];
+  /** 当前搜索词下各类型的条目数，键为 clip_type（图片包含 GIF） */
+  typeCounts: Record<string, number>;
   search: string;
   onSearchChange: (v: string) => void;
   typeFilter: ClipTypeFilter;
   onTypeFilterChange: (v: ClipTypeFilter) => void;
+  /** 排序方式：null 按时间，"most_used" 按总复制次数，"frecency" 综合次数与最近使用时间，"trending" 按最近的重复复制 */
+  sort: string | null;
+  onSortChange: (sort: string | null) => void;
   isSelected: (id: string) => boolean;
   onToggleSelect: (id: string) => void;
   onDelete: (id: string) => void;
   onTogglePin: (id: string) => void;
+  onFetchTitle: (id: string) => void;
+  onSetExpiry: (id: string, seconds: number | null) => void;
+  onUpdateContent: (id: string, content: string) => Promise<void>;
+  onBump: (id: string) => void;
   selectedCount: number;
   onStartGenerate: () => void;
   onSelectAll: () => void;
@@ -40,14 +84,22 @@ interface Props {
 
 export function ClipList({
   clips,
+  clipDays,
+  typeCounts,
   search,
   onSearchChange,
   typeFilter,
   onTypeFilterChange,
+  sort,
+  onSortChange,
   isSelected,
   onToggleSelect,
   onDelete,
   onTogglePin,
+  onFetchTitle,
+  onSetExpiry,
+  onUpdateContent,
+  onBump,
   selectedCount,
   onStartGenerate,
   onSelectAll,
@@ -61,12 +113,57 @@ export function ClipList({
   const [previewImage, setPreviewImage] = useState<string | null>(null);
   // 长文本展开状态，记录已展开的条目 ID
   const [expandedIds, setExpandedIds] = useState<Set<string>>(new Set());
+  // 展开时按需加载的完整内容
+  const [fullContent, setFullContent] = useState<Record<string, string>>({});
+
+  // 正在编辑内容的条目
+  const [editingId, setEditingId] = useState<string | null>(null);
+  const [editText, setEditText] = useState("");
+  const [editError, setEditError] = useState<string | null>(null);
+
+  const startEdit = useCallback(async (clip: ClipItem) => {
+    try {
+      setEditText(await getClipContent(clip));
+      setEditError(null);
+      setEditingId(clip.id);
+    } catch (e) {
+      console.error("加载完整内容失败:", e);
+    }
+  }, []);
+
+  const saveEdit = useCallback(async (id: string) => {
+    try {
+      await onUpdateContent(id, editText);
+      setFullContent((prev) => ({ ...prev, [id]: editText }));
+      setEditingId(null);
+    } catch (e) {
+      const err = e as { message?: string };
+      setEditError(err.message ?? String(e));
+    }
+  }, [editText, onUpdateContent]);
+
+  // 正在选择自动删除时间的条目
+  const [expiryMenuId, setExpiryMenuId] = useState<string | null>(null);
+  // 有条目设置了到期时间时每秒刷新倒计时
+  const [now, setNow] = useState(Date.now());
+  const hasExpiring = clips.some((c) => c.expires_at);
+  useEffect(() => {
+    if (!hasExpiring) return;
+    const timer = setInterval(() => setNow(Date.now()), 1000);
+    return () => clearInterval(timer);
+  }, [hasExpiring]);
 
   // 文本截断阈值
   const TEXT_TRUNCATE_LIMIT = 200;
 
   // 切换展开/折叠
-  const toggleExpand = useCallback((id: string) => {
+  const toggleExpand = useCallback((clip: ClipItem) => {
+    const id = clip.id;
+    if (!expandedIds.has(id) && fullContent[id] === undefined) {
+      getClipContent(clip)
+        .then((content) => setFullContent((prev) => ({ ...prev, [id]: content })))
+        .catch((e) => console.error("加载完整内容失败:", e));
+    }
     setExpandedIds((prev) => {
       const next = new Set(prev);
       if (next.has(id)) {
@@ -76,7 +173,7 @@ export function ClipList({
       }
       return next;
     });
-  }, []);
+  }, [expandedIds, fullContent]);
   // 合并复制
   const [separator] = useState("newline");
   const [mergeCopied, setMergeCopied] = useState(false);
@@ -86,12 +183,12 @@ export function ClipList({
   const loadImage = async (clip: ClipItem) => {
     if (clip.image_path && !imageCache[clip.id]) {
       try {
-        const base64 = await invoke<string>("read_image_base64", {
+        const dataUrl = await invoke<string>("read_image_base64", {
           path: clip.image_path,
         });
         setImageCache((prev) => ({
           ...prev,
-          [clip.id]: `data:image/png;base64,${base64}`,
+          [clip.id]: dataUrl,
         }));
       } catch (e) {
         console.error("Failed to load image:", e);
@@ -102,15 +199,17 @@ export function ClipList({
   // 加载图片
   useEffect(() => {
     clips.forEach((clip) => {
-      if (clip.clip_type === "image") loadImage(clip);
+      if (clip.image_path) loadImage(clip);
     });
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [clips]);
 
-  // 复制并粘贴到目标应用
-  const handleCopyAndPaste = useCallback(async (clip: ClipItem) => {
+  // 复制并粘贴到目标应用；按住 Option 时粘贴后保持窗口打开，便于连续粘贴
+  const handleCopyAndPaste = useCallback(async (clip: ClipItem, keepOpen = false) => {
     try {
-      await invoke("copy_and_paste", { content: clip.content });
+      await invoke(keepOpen ? "copy_paste_keep_open" : "copy_and_paste", {
+        content: await getClipContent(clip),
+      });
     } catch (e) {
       console.error("复制粘贴失败:", e);
     }
@@ -121,8 +220,8 @@ export function ClipList({
     const items = getSelectedItems(clips);
     if (items.length === 0) return;
     const sep = SEPARATOR_OPTIONS.find((s) => s.key === separator)?.value ?? "\n";
-    const merged = items.map((item) => item.content).join(sep);
     try {
+      const merged = (await Promise.all(items.map(getClipContent))).join(sep);
       await writeText(merged);
       setMergeCopied(true);
       setTimeout(() => setMergeCopied(false), 1500);
@@ -228,8 +327,34 @@ export function ClipList({
           >
             {f.icon && <span className="filter-icon">{f.icon}</span>}
             {f.label}
+            <span className="filter-count">
+              {f.key === "all"
+                ? Object.values(typeCounts).reduce((sum, n) => sum + n, 0)
+                : typeCounts[f.key] ?? 0}
+            </span>
           </button>
         ))}
+        <button
+          className={`filter-chip filter-sort ${sort === "most_used" ? "active" : ""}`}
+          onClick={() => onSortChange(sort === "most_used" ? null : "most_used")}
+          title="按复制次数排序"
+        >
+          常用
+        </button>
+        <button
+          className={`filter-chip filter-sort ${sort === "frecency" ? "active" : ""}`}
+          onClick={() => onSortChange(sort === "frecency" ? null : "frecency")}
+          title="综合复制次数和最近使用时间排序"
+        >
+          综合
+        </button>
+        <button
+          className={`filter-chip filter-sort ${sort === "trending" ? "active" : ""}`}
+          onClick={() => onSortChange(sort === "trending" ? null : "trending")}
+          title="按最近 7 天重复复制的次数排序"
+        >
+          近期
+        </button>
       </div>
 
       {/* 选择操作栏 */}
@@ -309,8 +434,14 @@ export function ClipList({
           </div>
         )}
         {clips.map((clip, index) => (
+          <Fragment key={clip.id}>
+          {sort === null && !clip.is_pinned && daySection(clip) !== daySection(clips[index - 1]) && (
+            <div className="clip-day-header">
+              <span>{DAY_SECTION_LABELS[daySection(clip)!]}</span>
+              <span className="clip-day-count">{daySectionCount(clipDays, daySection(clip)!)}</span>
+            </div>
+          )}
           <div
-            key={clip.id}
             className={`clip-item-wrapper ${isSelected(clip.id) ? "selected" : ""} ${clip.is_pinned ? "pinned" : ""} ${focusIndex === index ? "focused" : ""}`}
           >
             {/* 选中区域 */}
@@ -349,30 +480,59 @@ export function ClipList({
               {/* 主体区域：点击复制+粘贴 */}
               <div
                 className="clip-zone-body"
-                onClick={() => handleCopyAndPaste(clip)}
+                onClick={(e) => editingId !== clip.id && handleCopyAndPaste(clip, e.altKey)}
               >
-                <div className="zone-hover-overlay">
-                  <svg width="28" height="28" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="1.8">
-                    <rect x="8" y="2" width="13" height="13" rx="2" />
-                    <path d="M5 8H4a2 2 0 00-2 2v10a2 2 0 002 2h10a2 2 0 002-2v-1" />
-                  </svg>
-                </div>
+                {editingId !== clip.id && (
+                  <div className="zone-hover-overlay">
+                    <svg width="28" height="28" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="1.8">
+                      <rect x="8" y="2" width="13" height="13" rx="2" />
+                      <path d="M5 8H4a2 2 0 00-2 2v10a2 2 0 002 2h10a2 2 0 002-2v-1" />
+                    </svg>
+                  </div>
+                )}
                 <div className="clip-meta">
                   <span className={`clip-type-badge ${clip.clip_type}`}>
                     {clip.clip_type === "text" && "文本"}
                     {clip.clip_type === "code" && "代码"}
                     {clip.clip_type === "url" && "链接"}
                     {clip.clip_type === "image" && "图片"}
+                    {clip.clip_type === "gif" && "GIF"}
+                    {clip.clip_type === "color" && "颜色"}
+                    {clip.clip_type === "json" && "JSON"}
+                    {clip.clip_type === "xml" && "XML"}
+                    {clip.clip_type === "email" && "邮箱"}
+                    {clip.clip_type === "phone" && "电话"}
                   </span>
+                  {clip.original_length != null && (
+                    <span
+                      className="clip-truncated"
+                      title={`原始大小 ${Math.round(clip.original_length / 1024)} KB`}
+                    >
+                      已截断
+                    </span>
+                  )}
+                  {clip.copy_count > 1 && (
+                    <span className="clip-copy-count" title={`复制过 ${clip.copy_count} 次`}>
+                      ×{clip.copy_count}
+                    </span>
+                  )}
                   {clip.source_app && (
                     <span className="clip-source">{clip.source_app}</span>
                   )}
+                  {clip.expires_at && (
+                    <span
+                      className="clip-expiry"
+                      title={`将于 ${new Date(clip.expires_at).toLocaleString()} 删除`}
+                    >
+                      {formatRemaining(clip.expires_at, now)}
+                    </span>
+                  )}
                   <span className="clip-time">
-                    {formatTime(clip.created_at)}
+                    {formatTime(clip)}
                   </span>
                   {clip.is_pinned && <span className="pin-indicator" />}
                 </div>
-                {clip.clip_type === "image" && imageCache[clip.id] ? (
+                {clip.image_path && imageCache[clip.id] ? (
                   <img
                     src={imageCache[clip.id]}
                     alt="clipboard image"
@@ -382,24 +542,93 @@ export function ClipList({
                       setPreviewImage(imageCache[clip.id]);
                     }}
                   />
+                ) : editingId === clip.id ? (
+                  <div className="clip-edit">
+                    <textarea
+                      className="clip-edit-input"
+                      value={editText}
+                      onChange={(e) => setEditText(e.target.value)}
+                      onKeyDown={(e) => {
+                        // 不触发列表的键盘导航和全局快捷键
+                        e.stopPropagation();
+                        if (e.key === "Escape") setEditingId(null);
+                        if (e.key === "Enter" && e.metaKey) saveEdit(clip.id);
+                      }}
+                      autoFocus
+                    />
+                    {editError && <div className="form-error">{editError}</div>}
+                    <div className="clip-edit-actions">
+                      <button className="btn-cancel" onClick={() => setEditingId(null)}>
+                        取消
+                      </button>
+                      <button
+                        className="btn-save"
+                        onClick={() => saveEdit(clip.id)}
+                        disabled={!editText.trim()}
+                      >
+                        保存
+                      </button>
+                    </div>
+                  </div>
                 ) : (
                   <div className="clip-text-wrapper">
+                    {clip.clip_type === "url" && clip.title && (
+                      <div className="clip-link-title">{clip.title}</div>
+                    )}
                     <div
                       className={`clip-text ${clip.clip_type === "code" ? "code" : ""} ${expandedIds.has(clip.id) ? "expanded" : ""}`}
                     >
-                      {clip.content.length > TEXT_TRUNCATE_LIMIT && !expandedIds.has(clip.id)
-                        ? clip.content.slice(0, TEXT_TRUNCATE_LIMIT) + "..."
-                        : clip.content}
+                      {expandedIds.has(clip.id) && fullContent[clip.id] !== undefined
+                        ? fullContent[clip.id]
+                        : clipLength(clip) > TEXT_TRUNCATE_LIMIT && !expandedIds.has(clip.id)
+                          ? <>
+                              {highlightMatches((clip.preview ?? clip.content).slice(0, TEXT_TRUNCATE_LIMIT), clip.match_ranges)}
+                              ...
+                            </>
+                          : highlightMatches(clip.preview ?? clip.content, clip.match_ranges)}
                     </div>
                   </div>
                 )}
               </div>
-              {/* 操作区域：展开 + 置顶 + 删除 */}
+              {/* 操作区域：展开 + 置顶 + 定时删除 + 删除 */}
               <div className="clip-zone-actions">
-                {clip.clip_type !== "image" && clip.content.length > TEXT_TRUNCATE_LIMIT && (
+                {expiryMenuId === clip.id ? (
+                  <>
+                    {EXPIRY_OPTIONS.map((opt) => (
+                      <button
+                        key={opt.seconds}
+                        className="btn-action btn-expiry-option"
+                        onClick={() => { onSetExpiry(clip.id, opt.seconds); setExpiryMenuId(null); }}
+                        title={opt.title}
+                      >
+                        {opt.label}
+                      </button>
+                    ))}
+                    {clip.expires_at && (
+                      <button
+                        className="btn-action btn-expiry-option"
+                        onClick={() => { onSetExpiry(clip.id, null); setExpiryMenuId(null); }}
+                        title="取消定时删除"
+                      >
+                        不删
+                      </button>
+                    )}
+                    <button
+                      className="btn-action"
+                      onClick={() => setExpiryMenuId(null)}
+                      title="返回"
+                    >
+                      <svg width="12" height="12" viewBox="0 0 8 8" fill="none" stroke="currentColor" strokeWidth="1.2">
+                        <path d="M1 1l6 6M7 1L1 7" strokeLinecap="round" />
+                      </svg>
+                    </button>
+                  </>
+                ) : (
+                <>
+                {!clip.image_path && clipLength(clip) > TEXT_TRUNCATE_LIMIT && (
                   <button
                     className={`btn-action btn-expand ${expandedIds.has(clip.id) ? "expanded" : ""}`}
-                    onClick={() => toggleExpand(clip.id)}
+                    onClick={() => toggleExpand(clip)}
                     title={expandedIds.has(clip.id) ? "收起" : "展开"}
                   >
                     <svg width="14" height="14" viewBox="0 0 14 14" fill="none" stroke="currentColor" strokeWidth="1.8" strokeLinecap="round" strokeLinejoin="round">
@@ -407,6 +636,41 @@ export function ClipList({
                     </svg>
                   </button>
                 )}
+                {(clip.clip_type === "email" || clip.clip_type === "phone") && (
+                  <button
+                    className="btn-action"
+                    onClick={() => {
+                      const target = clip.content.trim();
+                      const href =
+                        clip.clip_type === "email" ? `mailto:${target}` : `tel:${target.replace(/[^\d+]/g, "")}`;
+                      open(href).catch((e) => console.error("打开失败:", e));
+                    }}
+                    title={clip.clip_type === "email" ? "写邮件" : "拨打电话"}
+                  >
+                    {clip.clip_type === "email" ? (
+                      <svg width="14" height="14" viewBox="0 0 14 14" fill="none" stroke="currentColor" strokeWidth="1.2">
+                        <rect x="1.5" y="3" width="11" height="8" rx="1" />
+                        <path d="M1.5 3.5L7 8l5.5-4.5" />
+                      </svg>
+                    ) : (
+                      <svg width="14" height="14" viewBox="0 0 14 14" fill="none" stroke="currentColor" strokeWidth="1.2" strokeLinejoin="round">
+                        <path d="M3 1.5h2l1 3-1.5 1a7 7 0 003.5 3.5l1-1.5 3 1v2a1.5 1.5 0 01-1.5 1.5A10 10 0 011.5 3 1.5 1.5 0 013 1.5z" />
+                      </svg>
+                    )}
+                  </button>
+                )}
+                {clip.clip_type === "url" && !clip.title && (
+                  <button
+                    className="btn-action"
+                    onClick={() => onFetchTitle(clip.id)}
+                    title="获取网页标题"
+                  >
+                    <svg width="14" height="14" viewBox="0 0 14 14" fill="none" stroke="currentColor" strokeWidth="1.2">
+                      <circle cx="7" cy="7" r="5.5" />
+                      <path d="M1.5 7h11M7 1.5c1.6 1.6 1.6 9.4 0 11M7 1.5c-1.6 1.6-1.6 9.4 0 11" />
+                    </svg>
+                  </button>
+                )}
                 <button
                   className="btn-action"
                   onClick={() => onTogglePin(clip.id)}
@@ -423,6 +687,38 @@ export function ClipList({
                     <path d="M5 1L9 1L9 5L12 7L12 8L8 8L8 13L6 13L6 8L2 8L2 7L5 5Z" />
                   </svg>
                 </button>
+                {!clip.is_pinned && (
+                  <button
+                    className="btn-action"
+                    onClick={() => onBump(clip.id)}
+                    title="移到最前"
+                  >
+                    <svg width="14" height="14" viewBox="0 0 14 14" fill="none" stroke="currentColor" strokeWidth="1.2" strokeLinecap="round" strokeLinejoin="round">
+                      <path d="M3 2h8M7 12V5M4 8l3-3 3 3" />
+                    </svg>
+                  </button>
+                )}
+                {!clip.image_path && (
+                  <button
+                    className="btn-action"
+                    onClick={() => startEdit(clip)}
+                    title="编辑"
+                  >
+                    <svg width="14" height="14" viewBox="0 0 14 14" fill="none" stroke="currentColor" strokeWidth="1.2" strokeLinejoin="round">
+                      <path d="M9.5 2.5l2 2L5 11H3V9l6.5-6.5z" />
+                    </svg>
+                  </button>
+                )}
+                <button
+                  className={`btn-action ${clip.expires_at ? "active" : ""}`}
+                  onClick={() => setExpiryMenuId(clip.id)}
+                  title="定时删除"
+                >
+                  <svg width="14" height="14" viewBox="0 0 14 14" fill="none" stroke="currentColor" strokeWidth="1.2" strokeLinecap="round">
+                    <circle cx="7" cy="7" r="5.5" />
+                    <path d="M7 4v3l2 1.5" />
+                  </svg>
+                </button>
                 <button
                   className="btn-action btn-delete"
                   onClick={() => onDelete(clip.id)}
@@ -439,8 +735,11 @@ export function ClipList({
                     <path d="M2 4h10M5 4V2h4v2M3 4v8h8V4" />
                   </svg>
                 </button>
+                </>
+                )}
               </div>
           </div>
+          </Fragment>
         ))}
       </div>
 
@@ -468,10 +767,60 @@ export function ClipList({
   );
 }
 
-function formatTime(iso: string): string {
-  const date = new Date(iso);
+/** 距到期的剩余时间 */
+function formatRemaining(iso: string, now: number): string {
+  const seconds = Math.floor((new Date(iso).getTime() - now) / 1000);
+  if (seconds <= 0) return "即将删除";
+  if (seconds < 3600) {
+    const m = Math.floor(seconds / 60);
+    const s = seconds % 60;
+    return `${m}:${String(s).padStart(2, "0")} 后删除`;
+  }
+  if (seconds < 86400) {
+    return `${Math.floor(seconds / 3600)} 小时 ${Math.floor((seconds % 3600) / 60)} 分后删除`;
+  }
+  return `${Math.floor(seconds / 86400)} 天后删除`;
+}
+
+type DaySection = "today" | "yesterday" | "earlier";
+
+const DAY_SECTION_LABELS: Record<DaySection, string> = {
+  today: "今天",
+  yesterday: "昨天",
+  earlier: "更早",
+};
+
+// 本地时区的 YYYY-MM-DD，与后端 get_clip_days 的分组一致
+function localDay(date: Date): string {
+  const pad = (n: number) => String(n).padStart(2, "0");
+  return `${date.getFullYear()}-${pad(date.getMonth() + 1)}-${pad(date.getDate())}`;
+}
+
+function sectionOfDay(day: string): DaySection {
   const now = new Date();
-  const diff = now.getTime() - date.getTime();
+  if (day === localDay(now)) return "today";
+  const yesterday = new Date(now.getFullYear(), now.getMonth(), now.getDate() - 1);
+  return day === localDay(yesterday) ? "yesterday" : "earlier";
+}
+
+// 条目所属的日期分组，置顶条目不参与分组
+function daySection(clip: ClipItem | undefined): DaySection | null {
+  if (!clip || clip.is_pinned) return null;
+  return sectionOfDay(localDay(new Date(clip.created_at)));
+}
+
+function daySectionCount(days: ClipDay[], section: DaySection): number {
+  return days
+    .filter((d) => sectionOfDay(d.day) === section)
+    .reduce((sum, d) => sum + d.count, 0);
+}
+
+// 优先用后端算好的 age_seconds，旧数据或本地构造的条目再解析 created_at
+function formatTime(clip: ClipItem): string {
+  const date = new Date(clip.created_at);
+  const diff = clip.age_seconds != null
+    ? clip.age_seconds * 1000
+    : Date.now() - date.getTime();
   const mins = Math.floor(diff / 60000);
   if (mins < 1) return "刚刚";
   if (mins < 60) return `${mins}分钟前`;
@@ -1,7 +1,8 @@
 import { useState, useEffect, useRef, useMemo } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { writeText } from "@tauri-apps/plugin-clipboard-manager";
 import Markdown from "react-markdown";
-import type { ClipItem, Template, AiProvider } from "../types";
+import type { ClipItem, Template, AiProvider, InlineTarget } from "../types";
 
 const TEMPLATE_EMOJIS: Record<string, string> = {
   "tpl-translate": "🌐",
@@ -26,14 +27,27 @@ interface Props {
   templates: Template[];
   providers: AiProvider[];
   output: string;
+  /** 模型以独立字段输出的推理过程 */
+  reasoning?: string;
   generating: boolean;
+  queued?: boolean;
+  /** 逐条生成的进度 */
+  batchProgress?: { done: number; total: number } | null;
   error: string | null;
+  errorCode?: string | null;
   onGenerate: (
     items: ClipItem[],
     template: Template | null,
     customPrompt: string,
-    providerId?: string
+    providerId?: string,
+    inlineTarget?: InlineTarget
   ) => void;
+  onGenerateBatch: (items: ClipItem[], template: Template) => void;
+  onPreviewPrompt: (
+    items: ClipItem[],
+    template: Template | null,
+    customPrompt: string
+  ) => Promise<string>;
   onBack: () => void;
   onNavigateSettings: () => void;
   initialTemplateId?: string | null;
@@ -44,9 +58,15 @@ export function GenerateView({
   templates,
   providers,
   output,
+  reasoning = "",
   generating,
+  queued,
+  batchProgress,
   error,
+  errorCode,
   onGenerate,
+  onGenerateBatch,
+  onPreviewPrompt,
   onBack,
   onNavigateSettings,
   initialTemplateId,
@@ -55,32 +75,74 @@ export function GenerateView({
   const [activeTemplateId, setActiveTemplateId] = useState<string | null>(initialTemplateId || null);
   const [customPrompt, setCustomPrompt] = useState("");
   const [copied, setCopied] = useState(false);
+  const [plainCopied, setPlainCopied] = useState(false);
   const [thinkExpanded, setThinkExpanded] = useState(false);
+  const [promptPreview, setPromptPreview] = useState<string | null>(null);
+  // 设置中开启内联生成后，可选择把结果直接输入到之前的应用
+  const [inlineEnabled, setInlineEnabled] = useState(false);
+  const [inlineTarget, setInlineTarget] = useState<InlineTarget | null>(null);
+  // 多个素材时可对每个素材单独运行模板，而不是合并成一次请求
+  const [perItem, setPerItem] = useState(false);
+  const canBatch = selectedItems.length > 1;
   const outputRef = useRef<HTMLDivElement>(null);
   const inputRef = useRef<HTMLInputElement>(null);
 
+  useEffect(() => {
+    invoke<Record<string, string>>("get_settings")
+      .then((s) => setInlineEnabled(s.inline_generation === "true"))
+      .catch((e) => console.error("Failed to load settings:", e));
+  }, []);
+
   useEffect(() => {
     if (outputRef.current) {
       outputRef.current.scrollTop = outputRef.current.scrollHeight;
     }
-  }, [output]);
+  }, [output, reasoning]);
 
-  const defaultProvider = providers.find((p) => p.is_default) || providers[0];
+  // 模板绑定了模型时优先显示绑定的模型
+  const activeTemplate = templates.find((t) => t.id === activeTemplateId);
+  const defaultProvider =
+    (!isCustomMode && providers.find((p) => p.id === activeTemplate?.provider_id)) ||
+    providers.find((p) => p.is_default) ||
+    providers[0];
 
-  const parsed = useMemo(() => parseOutput(output), [output]);
+  // 模型单独输出的推理过程和正文中的 <think> 块一起放进折叠的思考区域
+  const parsed = useMemo(() => {
+    const result = parseOutput(output);
+    const thinking = [reasoning.trim(), result.thinking].filter(Boolean).join("\n\n");
+    return { ...result, thinking };
+  }, [output, reasoning]);
 
   // Click a template card → immediately generate
   const handleTemplateClick = (template: Template) => {
     if (generating) return;
     setIsCustomMode(false);
     setActiveTemplateId(template.id);
-    onGenerate(selectedItems, template, "");
+    if (canBatch && perItem) {
+      onGenerateBatch(selectedItems, template);
+      return;
+    }
+    onGenerate(selectedItems, template, "", undefined, inlineTarget ?? undefined);
   };
 
   // Custom mode: send from input
   const handleCustomSend = () => {
     if (!customPrompt.trim() || generating) return;
-    onGenerate(selectedItems, null, customPrompt);
+    onGenerate(selectedItems, null, customPrompt, undefined, inlineTarget ?? undefined);
+  };
+
+  // 预览将要发送的提示词，模板模式下与点击模板卡片时发送的内容一致
+  const handlePreviewPrompt = async () => {
+    if (promptPreview !== null) {
+      setPromptPreview(null);
+      return;
+    }
+    try {
+      const template = isCustomMode ? null : activeTemplate ?? null;
+      setPromptPreview(await onPreviewPrompt(selectedItems, template, isCustomMode ? customPrompt : ""));
+    } catch (e) {
+      console.error("Failed to preview prompt:", e);
+    }
   };
 
   const handleInputKeyDown = (e: React.KeyboardEvent) => {
@@ -100,6 +162,18 @@ export function GenerateView({
     }
   };
 
+  // 去掉 Markdown 标记后复制，适合粘贴到不支持 Markdown 的应用
+  const handleCopyPlain = async () => {
+    try {
+      const plain = await invoke<string>("strip_markdown", { text: parsed.content || output });
+      await writeText(plain);
+      setPlainCopied(true);
+      setTimeout(() => setPlainCopied(false), 2000);
+    } catch (e) {
+      console.error("Failed to copy:", e);
+    }
+  };
+
   return (
     <div className="generate-view generate-view-chat">
       <div className="generate-header">
@@ -115,8 +189,8 @@ export function GenerateView({
         <div className="materials-chips">
           {selectedItems.map((item) => (
             <span key={item.id} className="material-chip">
-              {item.clip_type === "image" ? "🖼️ 图片" : item.content.slice(0, 30)}
-              {item.content.length > 30 ? "..." : ""}
+              {item.image_path ? "🖼️ 图片" : (item.preview ?? item.content).slice(0, 30)}
+              {(item.content_length ?? item.content.length) > 30 ? "..." : ""}
             </span>
           ))}
         </div>
@@ -166,11 +240,61 @@ export function GenerateView({
       {defaultProvider && (
         <div className="provider-hint">
           将使用 {defaultProvider.name} 生成
+          {(isCustomMode || activeTemplate) && (
+            <button className="btn-link" onClick={handlePreviewPrompt}>
+              {promptPreview !== null ? "收起提示词" : "预览提示词"}
+            </button>
+          )}
+        </div>
+      )}
+      {canBatch && !isCustomMode && (
+        <div className="inline-target">
+          <label className="checkbox-label">
+            <input
+              type="checkbox"
+              checked={perItem}
+              onChange={(e) => setPerItem(e.target.checked)}
+            />
+            <span>逐条生成（每个素材单独运行模板）</span>
+          </label>
         </div>
       )}
+      {inlineEnabled && (
+        <div className="inline-target">
+          <label className="checkbox-label">
+            <input
+              type="checkbox"
+              checked={inlineTarget !== null}
+              onChange={(e) => setInlineTarget(e.target.checked ? "paste" : null)}
+            />
+            <span>直接输入到之前的应用</span>
+          </label>
+          {inlineTarget && (
+            <select
+              value={inlineTarget}
+              onChange={(e) => setInlineTarget(e.target.value as InlineTarget)}
+            >
+              <option value="paste">分批粘贴</option>
+              <option value="type">模拟键盘输入</option>
+            </select>
+          )}
+        </div>
+      )}
+      {promptPreview !== null && (
+        <pre className="prompt-preview">{promptPreview}</pre>
+      )}
 
       {/* Error */}
-      {error && <div className="error-message">{error}</div>}
+      {error && (
+        <div className="error-message">
+          {error}
+          {errorCode === "NoProvider" && (
+            <button className="btn-link" onClick={onNavigateSettings}>
+              前往设置
+            </button>
+          )}
+        </div>
+      )}
 
       {/* Output */}
       {(output || generating) && (
@@ -178,9 +302,14 @@ export function GenerateView({
           <div className="output-header">
             <span>生成结果</span>
             {output && !generating && (
-              <button className="btn-copy" onClick={handleCopy}>
-                {copied ? "已复制 ✓" : "复制结果"}
-              </button>
+              <div className="output-actions">
+                <button className="btn-copy" onClick={handleCopyPlain}>
+                  {plainCopied ? "已复制 ✓" : "复制纯文本"}
+                </button>
+                <button className="btn-copy" onClick={handleCopy}>
+                  {copied ? "已复制 ✓" : "复制结果"}
+                </button>
+              </div>
             )}
           </div>
           <div className="output-content" ref={outputRef}>
@@ -210,7 +339,12 @@ export function GenerateView({
                 <Markdown>{parsed.content}</Markdown>
               </div>
             ) : (
-              generating && !parsed.thinking && "等待响应..."
+              generating && !parsed.thinking && (queued ? "排队中，等待其他请求完成..." : "等待响应...")
+            )}
+            {batchProgress && (
+              <div className="hint">
+                已完成 {batchProgress.done}/{batchProgress.total}
+              </div>
             )}
             {generating && <span className="cursor-blink">▊</span>}
           </div>
@@ -1,5 +1,15 @@
-import { useState, useCallback } from "react";
-import type { AiProvider, Template } from "../types";
+import { useState, useCallback, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { writeText } from "@tauri-apps/plugin-clipboard-manager";
+import type { AiProvider, AppError, PostProcess, ProviderPreset, StorageInfo, Template } from "../types";
+
+/** 字节数格式化为 KB/MB/GB */
+function formatBytes(bytes: number): string {
+  if (bytes < 1024) return `${bytes} B`;
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  if (bytes < 1024 * 1024 * 1024) return `${(bytes / 1024 / 1024).toFixed(1)} MB`;
+  return `${(bytes / 1024 / 1024 / 1024).toFixed(2)} GB`;
+}
 
 /** Convert a KeyboardEvent into a Tauri-compatible shortcut string */
 function keyEventToShortcut(e: React.KeyboardEvent): string | null {
@@ -34,6 +44,9 @@ function keyEventToShortcut(e: React.KeyboardEvent): string | null {
 
 type ProviderKind = AiProvider["kind"];
 
+// 与后端 clear_all_clips 要求的确认口令一致
+const CLEAR_ALL_CONFIRMATION = "DELETE ALL";
+
 interface Props {
   providers: AiProvider[];
   templates: Template[];
@@ -41,55 +54,17 @@ interface Props {
   onDeleteProvider: (id: string) => Promise<void>;
   onSaveTemplate: (template: Template) => Promise<void>;
   onDeleteTemplate: (id: string) => Promise<void>;
+  onDuplicateTemplate: (id: string) => Promise<void>;
   onBack: () => void;
 }
 
-const PROVIDER_PRESETS: Record<
-  string,
-  { label: string; endpoint: string; models: string[]; needsKey: boolean }
-> = {
-  openai: {
-    label: "OpenAI",
-    endpoint: "https://api.openai.com/v1",
-    models: ["gpt-4o", "gpt-4o-mini", "gpt-4-turbo", "gpt-3.5-turbo"],
-    needsKey: true,
-  },
-  claude: {
-    label: "Claude (Anthropic)",
-    endpoint: "https://api.anthropic.com/v1",
-    models: [
-      "claude-sonnet-4-20250514",
-      "claude-haiku-4-20250414",
-      "claude-opus-4-20250514",
-    ],
-    needsKey: true,
-  },
-  kimi: {
-    label: "Kimi (月之暗面)",
-    endpoint: "https://api.moonshot.cn/v1",
-    models: ["moonshot-v1-128k", "moonshot-v1-32k", "moonshot-v1-8k"],
-    needsKey: true,
-  },
-  minimax: {
-    label: "MiniMax (海螺)",
-    endpoint: "https://api.minimax.chat/v1",
-    models: ["MiniMax-Text-01", "abab6.5s-chat", "abab5.5-chat"],
-    needsKey: true,
-  },
-  ollama: {
-    label: "Ollama (本地)",
-    endpoint: "http://localhost:11434",
-    models: ["llama3", "mistral", "codellama", "qwen2"],
-    needsKey: false,
-  },
-};
-
 const KIND_COLORS: Record<string, string> = {
   openai: "#10a37f",
   claude: "#d97706",
   kimi: "#6366f1",
   minimax: "#ec4899",
   ollama: "#8b5cf6",
+  azure: "#0078d4",
 };
 
 export function SettingsView({
@@ -99,11 +74,66 @@ export function SettingsView({
   onDeleteProvider,
   onSaveTemplate,
   onDeleteTemplate,
+  onDuplicateTemplate,
   onBack,
 }: Props) {
   const [editing, setEditing] = useState(false);
   const [editingTemplate, setEditingTemplate] = useState(false);
   const [recordingShortcut, setRecordingShortcut] = useState(false);
+  const [storage, setStorage] = useState<StorageInfo | null>(null);
+  const [logPath, setLogPath] = useState("");
+  const [appSettings, setAppSettings] = useState<Record<string, string>>({});
+  const [clearPinned, setClearPinned] = useState(false);
+  const [clearConfirm, setClearConfirm] = useState("");
+  const [clearResult, setClearResult] = useState<string | null>(null);
+  // 后端提供的常用服务预设，按 kind 索引
+  const [presets, setPresets] = useState<Partial<Record<ProviderKind, ProviderPreset>>>({});
+
+  useEffect(() => {
+    invoke<ProviderPreset[]>("get_provider_presets")
+      .then((items) => setPresets(Object.fromEntries(items.map((p) => [p.kind, p]))))
+      .catch((e) => console.error("Failed to load provider presets:", e));
+    invoke<StorageInfo>("get_storage_info")
+      .then(setStorage)
+      .catch((e) => console.error("Failed to load storage info:", e));
+    invoke<string>("get_log_path").then(setLogPath);
+    invoke<Record<string, string>>("get_settings")
+      .then(setAppSettings)
+      .catch((e) => console.error("Failed to load settings:", e));
+  }, []);
+
+  const updateSetting = async (key: string, value: string) => {
+    try {
+      await invoke("set_setting", { key, value });
+      setAppSettings((prev) => ({ ...prev, [key]: value }));
+    } catch (e) {
+      console.error("Failed to save setting:", e);
+    }
+  };
+  // 更换本地 API 令牌，旧令牌立即失效
+  const handleRegenerateToken = async () => {
+    try {
+      const token = await invoke<string>("regenerate_local_api_token");
+      setAppSettings((prev) => ({ ...prev, local_api_token: token }));
+    } catch (e) {
+      console.error("Failed to regenerate local API token:", e);
+    }
+  };
+  // 永久删除全部历史，需要输入确认口令
+  const handleClearAll = async () => {
+    try {
+      const count = await invoke<number>("clear_all_clips", {
+        includePinned: clearPinned,
+        confirm: clearConfirm,
+      });
+      setClearConfirm("");
+      setClearResult(`已删除 ${count} 条记录`);
+      invoke<StorageInfo>("get_storage_info").then(setStorage);
+    } catch (e) {
+      setClearResult((e as AppError)?.message ?? String(e));
+    }
+  };
+
   const [tplForm, setTplForm] = useState<Template>({
     id: "",
     name: "",
@@ -121,6 +151,17 @@ export function SettingsView({
       setRecordingShortcut(false);
     }
   }, []);
+  // 录制“清空搜索”的全局快捷键，录到后直接保存
+  const [recordingResetShortcut, setRecordingResetShortcut] = useState(false);
+  const handleResetShortcutKeyDown = (e: React.KeyboardEvent) => {
+    e.preventDefault();
+    e.stopPropagation();
+    const shortcut = keyEventToShortcut(e);
+    if (shortcut) {
+      updateSetting("reset_view_shortcut", shortcut);
+      setRecordingResetShortcut(false);
+    }
+  };
   const [form, setForm] = useState<AiProvider>({
     id: "",
     name: "",
@@ -131,13 +172,30 @@ export function SettingsView({
     is_default: false,
   });
 
-  const startAdd = () => {
+  const [ollamaModels, setOllamaModels] = useState<string[]>([]);
+  const [ollamaError, setOllamaError] = useState<string | null>(null);
+
+  // 从 Ollama 服务器读取已安装的模型，作为模型输入框的候选项
+  const fetchOllamaModels = async () => {
+    setOllamaError(null);
+    try {
+      const models = await invoke<string[]>("list_ollama_models", { endpoint: form.endpoint });
+      setOllamaModels(models);
+      if (models.length === 0) setOllamaError("Ollama 中还没有已安装的模型");
+    } catch (e) {
+      setOllamaError((e as AppError)?.message ?? String(e));
+    }
+  };
+
+  // 按预设新建供应商，只需再填写 API Key
+  const startAdd = (kind: ProviderKind = "openai") => {
+    const preset = presets[kind];
     setForm({
       id: `provider-${Date.now()}`,
-      name: "",
-      kind: "openai",
-      endpoint: PROVIDER_PRESETS.openai.endpoint,
-      model: PROVIDER_PRESETS.openai.models[0],
+      name: preset?.label ?? "",
+      kind,
+      endpoint: preset?.endpoint ?? "",
+      model: preset?.models[0] ?? "",
       api_key: "",
       is_default: providers.length === 0,
     });
@@ -150,7 +208,8 @@ export function SettingsView({
   };
 
   const handleKindChange = (kind: ProviderKind) => {
-    const preset = PROVIDER_PRESETS[kind];
+    const preset = presets[kind];
+    if (!preset) return;
     setForm((prev) => ({
       ...prev,
       kind,
@@ -223,7 +282,7 @@ export function SettingsView({
         <div className="section-header">
           <h4>AI 模型</h4>
           {!editing && (
-            <button className="btn-add" onClick={startAdd}>
+            <button className="btn-add" onClick={() => startAdd()}>
               <svg width="14" height="14" viewBox="0 0 14 14" fill="none">
                 <path
                   d="M7 1V13M1 7H13"
@@ -241,7 +300,7 @@ export function SettingsView({
           <div className="provider-form">
             {/* Quick select cards */}
             <div className="kind-cards">
-              {Object.entries(PROVIDER_PRESETS).map(([key, preset]) => (
+              {Object.entries(presets).map(([key, preset]) => (
                 <button
                   key={key}
                   className={`kind-card ${form.kind === key ? "active" : ""}`}
@@ -262,7 +321,7 @@ export function SettingsView({
               <label>名称</label>
               <input
                 type="text"
-                placeholder={`例如: ${PROVIDER_PRESETS[form.kind]?.label}`}
+                placeholder={`例如: ${presets[form.kind]?.label}`}
                 value={form.name}
                 onChange={(e) => setForm({ ...form, name: e.target.value })}
               />
@@ -276,15 +335,40 @@ export function SettingsView({
               />
             </div>
             <div className="form-group">
-              <label>模型</label>
+              <label>{form.kind === "azure" ? "部署名称" : "模型"}</label>
               <input
                 type="text"
-                placeholder={`例如: ${PROVIDER_PRESETS[form.kind]?.models[0] || "model-name"}`}
+                placeholder={`例如: ${presets[form.kind]?.models[0] || "model-name"}`}
                 value={form.model}
                 onChange={(e) => setForm({ ...form, model: e.target.value })}
+                list={form.kind === "ollama" ? "ollama-models" : undefined}
               />
+              {form.kind === "ollama" && (
+                <>
+                  <datalist id="ollama-models">
+                    {ollamaModels.map((m) => (
+                      <option key={m} value={m} />
+                    ))}
+                  </datalist>
+                  <button className="btn-link" onClick={fetchOllamaModels}>
+                    获取已安装的模型
+                  </button>
+                  {ollamaError && <div className="form-error">{ollamaError}</div>}
+                </>
+              )}
             </div>
-            {PROVIDER_PRESETS[form.kind]?.needsKey && (
+            {form.kind === "azure" && (
+              <div className="form-group">
+                <label>API 版本</label>
+                <input
+                  type="text"
+                  placeholder="2024-10-21"
+                  value={form.api_version ?? ""}
+                  onChange={(e) => setForm({ ...form, api_version: e.target.value || null })}
+                />
+              </div>
+            )}
+            {presets[form.kind]?.needs_key && (
               <div className="form-group">
                 <label>API Key</label>
                 <input
@@ -331,7 +415,24 @@ export function SettingsView({
                   </svg>
                 </div>
                 <p>尚未配置 AI 模型</p>
-                <p className="hint">点击上方"添加"按钮开始配置</p>
+                <p className="hint">选择常用服务快速开始，或点击上方"添加"按钮</p>
+                <div className="kind-cards preset-cards">
+                  {Object.values(presets).map((preset) => (
+                    <button
+                      key={preset.kind}
+                      className="kind-card"
+                      onClick={() => startAdd(preset.kind)}
+                      style={
+                        {
+                          "--kind-color": KIND_COLORS[preset.kind],
+                        } as React.CSSProperties
+                      }
+                    >
+                      <span className="kind-dot" />
+                      <span>{preset.label}</span>
+                    </button>
+                  ))}
+                </div>
               </div>
             )}
             {providers.map((p) => (
@@ -348,7 +449,7 @@ export function SettingsView({
                     )}
                   </div>
                   <span className="provider-detail">
-                    {PROVIDER_PRESETS[p.kind]?.label || p.kind} · {p.model}
+                    {presets[p.kind]?.label || p.kind} · {p.model}
                   </span>
                 </div>
                 <div className="provider-actions">
@@ -430,6 +531,32 @@ export function SettingsView({
                 rows={4}
               />
             </div>
+            <div className="form-group">
+              <label>默认模型（可选）</label>
+              <select
+                value={tplForm.provider_id ?? ""}
+                onChange={(e) => setTplForm({ ...tplForm, provider_id: e.target.value || null })}
+              >
+                <option value="">使用默认模型</option>
+                {providers.map((p) => (
+                  <option key={p.id} value={p.id}>
+                    {p.name}
+                  </option>
+                ))}
+              </select>
+            </div>
+            <div className="form-group">
+              <label>输出后处理</label>
+              <select
+                value={tplForm.post_process ?? "none"}
+                onChange={(e) => setTplForm({ ...tplForm, post_process: e.target.value as PostProcess })}
+              >
+                <option value="none">不处理</option>
+                <option value="trim">去除首尾空白</option>
+                <option value="json_pretty">校验并格式化 JSON</option>
+                <option value="strip_markdown">转为纯文本</option>
+              </select>
+            </div>
             <div className="form-group">
               <label>全局快捷键（可选）</label>
               <div className="shortcut-recorder">
@@ -505,6 +632,23 @@ export function SettingsView({
                       <path d="M8.5 2.5l3 3M1 10l7-7 3 3-7 7H1v-3z" />
                     </svg>
                   </button>
+                  <button
+                    className="btn-icon-sm"
+                    onClick={() => onDuplicateTemplate(t.id)}
+                    title="复制"
+                  >
+                    <svg
+                      width="14"
+                      height="14"
+                      viewBox="0 0 14 14"
+                      fill="none"
+                      stroke="currentColor"
+                      strokeWidth="1.3"
+                    >
+                      <rect x="4.5" y="4.5" width="8" height="8" rx="1" />
+                      <path d="M9.5 4.5V2.5a1 1 0 00-1-1h-6a1 1 0 00-1 1v6a1 1 0 001 1h2" />
+                    </svg>
+                  </button>
                   <button
                     className="btn-icon-sm btn-delete"
                     onClick={() => onDeleteTemplate(t.id)}
@@ -528,6 +672,208 @@ export function SettingsView({
         )}
       </div>
 
+      {/* Language of backend-generated text */}
+      <div className="settings-section">
+        <div className="section-header">
+          <h4>语言</h4>
+        </div>
+        <label className="checkbox-label">
+          <span>图片占位文字、托盘菜单和默认模板的语言</span>
+          <select
+            value={appSettings.locale ?? "zh"}
+            onChange={(e) => updateSetting("locale", e.target.value)}
+          >
+            <option value="zh">中文</option>
+            <option value="en">English</option>
+          </select>
+        </label>
+      </div>
+
+      {/* Tray icon click */}
+      <div className="settings-section">
+        <div className="section-header">
+          <h4>托盘</h4>
+        </div>
+        <label className="checkbox-label">
+          <span>左键点击托盘图标</span>
+          <select
+            value={appSettings.tray_click_action ?? "toggle-at-mouse"}
+            onChange={(e) => updateSetting("tray_click_action", e.target.value)}
+          >
+            <option value="toggle-at-mouse">在鼠标旁显示/隐藏窗口</option>
+            <option value="toggle-at-tray">在托盘图标下方显示/隐藏窗口</option>
+            <option value="menu">打开菜单</option>
+          </select>
+        </label>
+      </div>
+
+      {/* Paste behavior */}
+      <div className="settings-section">
+        <div className="section-header">
+          <h4>粘贴</h4>
+        </div>
+        <label className="checkbox-label">
+          <input
+            type="checkbox"
+            checked={appSettings.auto_paste !== "false"}
+            onChange={(e) => updateSetting("auto_paste", e.target.checked ? "true" : "false")}
+          />
+          <span>选择条目后自动粘贴到之前的应用（关闭后只复制到剪贴板）</span>
+        </label>
+        <label className="checkbox-label">
+          <input
+            type="checkbox"
+            checked={appSettings.paste_feedback === "true"}
+            onChange={(e) => updateSetting("paste_feedback", e.target.checked ? "true" : "false")}
+          />
+          <span>粘贴完成后播放提示音</span>
+        </label>
+        <label className="checkbox-label">
+          <input
+            type="checkbox"
+            checked={appSettings.inline_generation === "true"}
+            onChange={(e) => updateSetting("inline_generation", e.target.checked ? "true" : "false")}
+          />
+          <span>允许 AI 生成时直接输入到之前的应用（模拟按键，生成期间请勿切换窗口）</span>
+        </label>
+      </div>
+
+      {/* Local HTTP API for scripts */}
+      <div className="settings-section">
+        <div className="section-header">
+          <h4>本地 API</h4>
+        </div>
+        <label className="checkbox-label">
+          <input
+            type="checkbox"
+            checked={appSettings.enable_local_api === "true"}
+            onChange={(e) => updateSetting("enable_local_api", e.target.checked ? "true" : "false")}
+          />
+          <span>开启本地 HTTP API，供脚本和其他应用读取历史、触发粘贴（仅限本机访问）</span>
+        </label>
+        {appSettings.enable_local_api === "true" && (
+          <>
+            <div className="shortcut-row">
+              <span>端口</span>
+              <input
+                key={appSettings.local_api_port}
+                className="local-api-port"
+                type="number"
+                min={1024}
+                max={65535}
+                defaultValue={appSettings.local_api_port}
+                onBlur={(e) => {
+                  if (e.target.value !== appSettings.local_api_port) {
+                    updateSetting("local_api_port", e.target.value);
+                  }
+                }}
+              />
+            </div>
+            <div className="shortcut-row">
+              <span>访问令牌</span>
+              <span className="storage-path">{appSettings.local_api_token}</span>
+            </div>
+            <div className="clear-history-row">
+              <button
+                className="btn-cancel"
+                onClick={() => writeText(appSettings.local_api_token ?? "")}
+              >
+                复制令牌
+              </button>
+              <button className="btn-cancel" onClick={handleRegenerateToken}>
+                重新生成
+              </button>
+            </div>
+            <span className="hint">
+              请求 http://127.0.0.1:{appSettings.local_api_port}/clips 时需带上请求头 Authorization: Bearer &lt;令牌&gt;
+            </span>
+          </>
+        )}
+      </div>
+
+      {/* Diagnostic log file */}
+      <div className="settings-section">
+        <div className="section-header">
+          <h4>日志</h4>
+        </div>
+        <label className="checkbox-label">
+          <span>写入日志文件</span>
+          <select
+            value={appSettings.log_level ?? "off"}
+            onChange={(e) => updateSetting("log_level", e.target.value)}
+          >
+            <option value="off">关闭</option>
+            <option value="error">仅错误</option>
+            <option value="warn">警告</option>
+            <option value="info">常规</option>
+            <option value="debug">详细</option>
+          </select>
+        </label>
+        {appSettings.log_level && appSettings.log_level !== "off" && (
+          <>
+            <div className="shortcut-row">
+              <span>日志文件</span>
+              <span className="storage-path">{logPath}</span>
+            </div>
+            <div className="clear-history-row">
+              <button className="btn-cancel" onClick={() => writeText(logPath)}>
+                复制路径
+              </button>
+            </div>
+            <span className="hint">反馈问题时可附上日志文件；API Key 不会写入日志</span>
+          </>
+        )}
+      </div>
+
+      {/* Storage info */}
+      {storage && (
+        <div className="settings-section">
+          <div className="section-header">
+            <h4>存储</h4>
+          </div>
+          <div className="shortcuts-list">
+            <div className="shortcut-row" title={storage.db_path}>
+              <span>数据库</span>
+              <span>{formatBytes(storage.db_bytes)}</span>
+            </div>
+            <div className="shortcut-row" title={storage.images_dir}>
+              <span>图片</span>
+              <span>{formatBytes(storage.images_bytes)}</span>
+            </div>
+            <div className="shortcut-row">
+              <span>数据目录</span>
+              <span className="storage-path">{storage.app_data_dir}</span>
+            </div>
+          </div>
+          <div className="clear-history">
+            <label className="checkbox-label">
+              <input
+                type="checkbox"
+                checked={clearPinned}
+                onChange={(e) => setClearPinned(e.target.checked)}
+              />
+              <span>同时删除置顶条目</span>
+            </label>
+            <div className="clear-history-row">
+              <input
+                value={clearConfirm}
+                onChange={(e) => setClearConfirm(e.target.value)}
+                placeholder={`输入 ${CLEAR_ALL_CONFIRMATION} 确认`}
+              />
+              <button
+                className="btn-danger"
+                disabled={clearConfirm !== CLEAR_ALL_CONFIRMATION}
+                onClick={handleClearAll}
+              >
+                清空全部历史
+              </button>
+            </div>
+            <span className="hint">永久删除所有记录（含回收站）和图片，无法恢复</span>
+            {clearResult && <span className="hint">{clearResult}</span>}
+          </div>
+        </div>
+      )}
+
       {/* Keyboard shortcuts info */}
       <div className="settings-section">
         <div className="section-header">
@@ -538,6 +884,31 @@ export function SettingsView({
             <span>快速打开/隐藏历史列表</span>
             <kbd>Cmd + Shift + V</kbd>
           </div>
+          <div className="shortcut-row">
+            <span>清空搜索并回到默认列表</span>
+            <div className="shortcut-recorder">
+              <div
+                className={`shortcut-recorder-box ${recordingResetShortcut ? "recording" : ""}`}
+                tabIndex={0}
+                onKeyDown={handleResetShortcutKeyDown}
+                onFocus={() => setRecordingResetShortcut(true)}
+                onBlur={() => setRecordingResetShortcut(false)}
+              >
+                {recordingResetShortcut
+                  ? "请按下快捷键组合..."
+                  : appSettings.reset_view_shortcut || "点击此处录制快捷键"}
+              </div>
+              {appSettings.reset_view_shortcut && (
+                <button
+                  className="shortcut-clear-btn"
+                  onClick={() => updateSetting("reset_view_shortcut", "")}
+                  title="清除快捷键"
+                >
+                  ✕
+                </button>
+              )}
+            </div>
+          </div>
           {templates.filter(t => t.shortcut).map(t => (
             <div className="shortcut-row" key={t.id}>
               <span>{t.name}</span>
@@ -1,14 +1,62 @@
 import { useState, useCallback, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { listen } from "@tauri-apps/api/event";
-import type { Template, AiProvider, StreamChunk, ClipItem } from "../types";
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
+import type {
+  Template,
+  AiProvider,
+  StreamChunk,
+  ClipItem,
+  AppError,
+  AiQueuedEvent,
+  InlineTarget,
+  BatchItemResult,
+  BatchProgress,
+} from "../types";
+import { getClipContent } from "./useClipboard";
+
+/** 将 invoke 抛出的错误统一为 AppError */
+function toAppError(e: unknown): AppError {
+  if (e && typeof e === "object" && "code" in e && "message" in e) {
+    return e as AppError;
+  }
+  return { code: "Other", message: String(e) };
+}
+
+/** 按素材顺序拼接逐条生成的结果，尚未完成的条目不显示 */
+function formatBatchOutput(results: (BatchItemResult | undefined)[]): string {
+  return results
+    .map((r, i) => r && `【素材 ${i + 1}】\n\n${r.content ?? `生成失败：${r.error}`}`)
+    .filter(Boolean)
+    .join("\n\n---\n\n");
+}
+
+/** 由后端渲染最终提示词，生成和预览共用，保证预览的就是实际发送的内容 */
+async function renderPrompt(
+  selectedItems: ClipItem[],
+  template: Template | null,
+  customPrompt: string
+): Promise<string> {
+  const contents = await Promise.all(selectedItems.map(getClipContent));
+  return invoke<string>("preview_prompt", {
+    templateId: template?.id ?? null,
+    materials: selectedItems.map((item, i) => ({ clip_type: item.clip_type, content: contents[i] })),
+    customPrompt,
+  });
+}
 
 export function useAI() {
   const [templates, setTemplates] = useState<Template[]>([]);
   const [providers, setProviders] = useState<AiProvider[]>([]);
   const [output, setOutput] = useState("");
+  // 模型单独输出的推理过程，与正文分开显示
+  const [reasoning, setReasoning] = useState("");
   const [generating, setGenerating] = useState(false);
+  // 请求在排队等待供应商的并发名额
+  const [queued, setQueued] = useState(false);
   const [error, setError] = useState<string | null>(null);
+  const [errorCode, setErrorCode] = useState<AppError["code"] | null>(null);
+  // 逐条生成的进度，普通生成时为 null
+  const [batchProgress, setBatchProgress] = useState<{ done: number; total: number } | null>(null);
   const unlistenRef = useRef<(() => void) | null>(null);
 
   const fetchTemplates = useCallback(async () => {
@@ -34,33 +82,49 @@ export function useAI() {
       selectedItems: ClipItem[],
       template: Template | null,
       customPrompt: string,
-      providerId?: string
+      providerId?: string,
+      inlineTarget?: InlineTarget
     ) => {
       setGenerating(true);
       setOutput("");
+      setReasoning("");
       setError(null);
+      setErrorCode(null);
 
       // Assemble prompt
-      const materials = selectedItems
-        .map((item, i) => `【素材 ${i + 1}】(${item.clip_type})\n${item.content}`)
-        .join("\n\n---\n\n");
-
       let prompt: string;
-      if (template) {
-        prompt = template.prompt.replace("{{materials}}", materials);
-        if (customPrompt.trim()) {
-          prompt += `\n\n额外要求：${customPrompt}`;
-        }
-      } else {
-        prompt = customPrompt
-          ? `${customPrompt}\n\n以下是参考素材：\n\n${materials}`
-          : materials;
+      try {
+        prompt = await renderPrompt(selectedItems, template, customPrompt);
+      } catch (e) {
+        const err = toAppError(e);
+        setError(err.message);
+        setErrorCode(err.code);
+        setGenerating(false);
+        return;
       }
+      // 图片素材以视觉输入的形式一并发送
+      const imagePaths = selectedItems
+        .map((item) => item.image_path)
+        .filter((path): path is string => !!path);
 
-      // Listen for stream events
-      const unlisten = await listen<StreamChunk>("ai-stream", (event) => {
+      // Listen for stream events addressed to this window and request;
+      // a seq gap means an event was dropped or reordered
+      const requestId = crypto.randomUUID();
+      let nextSeq = 0;
+      let gap = false;
+      const webview = getCurrentWebviewWindow();
+      const unlistenQueued = await webview.listen<AiQueuedEvent>("ai-queued", (event) => {
+        if (event.payload.request_id === requestId) setQueued(true);
+      });
+      const unlisten = await webview.listen<StreamChunk>("ai-stream", (event) => {
+        if (event.payload.request_id !== requestId) return;
+        setQueued(false);
+        if (event.payload.seq !== nextSeq) gap = true;
+        nextSeq = event.payload.seq + 1;
         if (event.payload.done) {
           setGenerating(false);
+        } else if (event.payload.kind === "reasoning") {
+          setReasoning((prev) => prev + event.payload.content);
         } else {
           setOutput((prev) => prev + event.payload.content);
         }
@@ -68,21 +132,76 @@ export function useAI() {
       unlistenRef.current = unlisten;
 
       try {
-        await invoke("ai_generate", {
-          providerId: providerId || null,
-          prompt,
-        });
+        // 内联生成时输出直接送入之前的应用（不支持图片素材），窗口由后端隐藏
+        const full = inlineTarget
+          ? await invoke<string>("ai_generate_inline", {
+              providerId: providerId || null,
+              templateId: template?.id ?? null,
+              target: inlineTarget,
+              requestId,
+              prompt,
+            })
+          : await invoke<string>("ai_generate", {
+              providerId: providerId || null,
+              templateId: template?.id ?? null,
+              requestId,
+              imagePaths,
+              prompt,
+            });
+        // 流式事件不完整，或模板对输出做了后处理时，以最终结果为准
+        const postProcessed = !inlineTarget && (template?.post_process ?? "none") !== "none";
+        if (gap || postProcessed) setOutput(full);
       } catch (e) {
-        setError(String(e));
+        const err = toAppError(e);
+        setError(err.message);
+        setErrorCode(err.code);
         setGenerating(false);
       } finally {
         unlisten();
+        unlistenQueued();
         unlistenRef.current = null;
+        setQueued(false);
       }
     },
     []
   );
 
+  // 对每个素材单独运行模板，结果按素材顺序分段显示
+  const generateBatch = useCallback(async (selectedItems: ClipItem[], template: Template) => {
+    setGenerating(true);
+    setOutput("");
+    setReasoning("");
+    setError(null);
+    setErrorCode(null);
+    setBatchProgress({ done: 0, total: selectedItems.length });
+
+    const requestId = crypto.randomUUID();
+    const results: (BatchItemResult | undefined)[] = new Array(selectedItems.length);
+    const unlisten = await getCurrentWebviewWindow().listen<BatchProgress>("ai-batch-progress", (event) => {
+      if (event.payload.request_id !== requestId) return;
+      results[event.payload.index] = event.payload.result;
+      setBatchProgress({ done: event.payload.done, total: event.payload.total });
+      setOutput(formatBatchOutput(results));
+    });
+
+    try {
+      const all = await invoke<BatchItemResult[]>("ai_generate_batch", {
+        templateId: template.id,
+        clipIds: selectedItems.map((item) => item.id),
+        requestId,
+      });
+      setOutput(formatBatchOutput(all));
+    } catch (e) {
+      const err = toAppError(e);
+      setError(err.message);
+      setErrorCode(err.code);
+    } finally {
+      unlisten();
+      setGenerating(false);
+      setBatchProgress(null);
+    }
+  }, []);
+
   const saveProvider = useCallback(
     async (provider: AiProvider) => {
       await invoke("save_provider", { provider });
@@ -115,19 +234,34 @@ export function useAI() {
     [fetchTemplates]
   );
 
+  const duplicateTemplate = useCallback(
+    async (id: string) => {
+      await invoke<Template>("duplicate_template", { id });
+      await fetchTemplates();
+    },
+    [fetchTemplates]
+  );
+
   return {
     templates,
     providers,
     output,
+    reasoning,
     generating,
+    queued,
     error,
+    errorCode,
+    batchProgress,
     fetchTemplates,
     fetchProviders,
     generate,
+    generateBatch,
+    previewPrompt: renderPrompt,
     saveProvider,
     deleteProvider,
     saveTemplate,
     deleteTemplate,
+    duplicateTemplate,
     setOutput,
     setError,
   };
@@ -1,49 +1,143 @@
 import { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import type { ClipItem, ClipTypeFilter } from "../types";
+import type { ClipItem, ClipChanged, ClipTypeFilter, CleanupDone, ClipDay } from "../types";
+
+/** 获取条目的完整内容：列表中的条目只带摘要，需要按需加载 */
+export async function getClipContent(item: ClipItem): Promise<string> {
+  if (item.preview === null) return item.content;
+  return invoke<string>("get_clip_content", { id: item.id });
+}
 
 export function useClipboard() {
   const [clips, setClips] = useState<ClipItem[]>([]);
+  // 按本地日期的条目数，用于列表的日期分组
+  const [clipDays, setClipDays] = useState<ClipDay[]>([]);
+  // 当前搜索词下各类型的条目数，用于筛选标签上的数字
+  const [typeCounts, setTypeCounts] = useState<Record<string, number>>({});
   const [search, setSearch] = useState("");
   const [typeFilter, setTypeFilter] = useState<ClipTypeFilter>("all");
+  // 排序方式，null 为按时间
+  const [sort, setSort] = useState<string | null>(null);
   const [loading, setLoading] = useState(false);
+  // 剪贴板监听异常时的错误信息，恢复后清空
+  const [monitorError, setMonitorError] = useState<string | null>(null);
+  // 用户从托盘暂停了监听
+  const [monitoringPaused, setMonitoringPaused] = useState(false);
 
   const fetchClips = useCallback(async () => {
     setLoading(true);
     try {
-      const items = await invoke<ClipItem[]>("get_clips", {
+      const filter = {
         search: search || null,
         clipType: typeFilter === "all" ? null : typeFilter,
-        limit: 200,
-        offset: 0,
-      });
+      };
+      // “近期常用”按最近 7 天的重复复制次数排序，由单独的命令返回
+      const list =
+        sort === "trending"
+          ? invoke<ClipItem[]>("get_trending_clips", { ...filter, limit: 200 })
+          : invoke<ClipItem[]>("get_clips", { ...filter, sort, limit: 200, offset: 0 });
+      const [items, days, counts] = await Promise.all([
+        list,
+        invoke<ClipDay[]>("get_clip_days", filter),
+        invoke<Record<string, number>>("get_type_counts", { search: filter.search }),
+      ]);
       setClips(items);
+      setClipDays(days);
+      setTypeCounts(counts);
     } catch (e) {
       console.error("Failed to fetch clips:", e);
     } finally {
       setLoading(false);
     }
-  }, [search, typeFilter]);
+  }, [search, typeFilter, sort]);
 
   useEffect(() => {
     fetchClips();
   }, [fetchClips]);
 
   useEffect(() => {
-    const unlisten = listen<ClipItem>("clipboard-changed", () => {
+    const unlisten = listen<ClipChanged>("clipboard-changed", () => {
+      fetchClips();
+    });
+    // 到期条目被后台删除
+    const unlistenExpired = listen<number>("clips-expired", () => {
+      fetchClips();
+    });
+    const unlistenUpdated = listen<string>("clip-updated", () => {
+      fetchClips();
+    });
+    const unlistenBumped = listen<string>("clipboard-bumped", () => {
+      fetchClips();
+    });
+    // 设置中清空了全部历史
+    const unlistenCleared = listen<number>("clipboard-cleared", () => {
+      fetchClips();
+    });
+    // 从导出文件导入了条目
+    const unlistenImported = listen<number>("clips-imported", () => {
       fetchClips();
     });
+    const unlistenCleanup = listen<CleanupDone>("cleanup-done", (event) => {
+      if (event.payload.old > 0 || event.payload.over_limit > 0) fetchClips();
+    });
     return () => {
       unlisten.then((fn) => fn());
+      unlistenExpired.then((fn) => fn());
+      unlistenUpdated.then((fn) => fn());
+      unlistenBumped.then((fn) => fn());
+      unlistenCleared.then((fn) => fn());
+      unlistenImported.then((fn) => fn());
+      unlistenCleanup.then((fn) => fn());
     };
   }, [fetchClips]);
 
+  useEffect(() => {
+    const unlistenError = listen<string>("monitor-error", (event) => {
+      setMonitorError(event.payload);
+    });
+    const unlistenRecovered = listen("monitor-recovered", () => {
+      setMonitorError(null);
+    });
+    invoke<boolean>("is_monitoring_paused").then(setMonitoringPaused);
+    const unlistenPaused = listen<boolean>("monitoring-paused", (event) => {
+      setMonitoringPaused(event.payload);
+    });
+    return () => {
+      unlistenError.then((fn) => fn());
+      unlistenRecovered.then((fn) => fn());
+      unlistenPaused.then((fn) => fn());
+    };
+  }, []);
+
+  // 全局快捷键清空了搜索和筛选，事件带回默认列表的第一页
+  useEffect(() => {
+    const unlisten = listen<ClipItem[]>("reset-view", (event) => {
+      setSearch("");
+      setTypeFilter("all");
+      setSort(null);
+      setClips(event.payload);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  const setPaused = async (paused: boolean) => {
+    await invoke("set_monitoring_paused", { paused });
+  };
+
   const deleteClip = async (id: string) => {
     await invoke("delete_clip", { id });
     setClips((prev) => prev.filter((c) => c.id !== id));
   };
 
+  const deleteClips = async (ids: string[]) => {
+    await invoke<number>("delete_clips", { ids });
+    const removed = new Set(ids);
+    setClips((prev) => prev.filter((c) => !removed.has(c.id)));
+  };
+
   const togglePin = async (id: string) => {
     const pinned = await invoke<boolean>("toggle_pin", { id });
     setClips((prev) =>
@@ -52,15 +146,63 @@ export function useClipboard() {
     fetchClips();
   };
 
+  // 抓取链接的网页标题
+  const fetchUrlTitle = async (id: string) => {
+    try {
+      const title = await invoke<string | null>("fetch_url_metadata", { id });
+      setClips((prev) => prev.map((c) => (c.id === id ? { ...c, title } : c)));
+    } catch (e) {
+      console.error("Failed to fetch page title:", e);
+    }
+  };
+
+  // 修改条目内容，后端会重新识别类型并按去重设置合并重复条目
+  const updateClipContent = async (id: string, content: string) => {
+    // 列表由 clip-updated 事件刷新
+    await invoke("update_clip_content", { id, newContent: content });
+  };
+
+  // 把条目移到最前，不经过系统剪贴板；列表由 clipboard-bumped 事件刷新
+  const bumpClip = async (id: string) => {
+    await invoke("bump_clip", { id });
+  };
+
+  // 设置条目在 seconds 秒后自动删除，传 null 取消
+  const setClipExpiry = async (id: string, seconds: number | null) => {
+    const expiresAt = await invoke<string | null>("set_clip_expiry", { id, seconds });
+    setClips((prev) =>
+      prev.map((c) => (c.id === id ? { ...c, expires_at: expiresAt } : c))
+    );
+  };
+
+  // 按给定顺序排列置顶条目
+  const reorderPins = async (orderedIds: string[]) => {
+    await invoke<number>("reorder_pins", { orderedIds });
+    fetchClips();
+  };
+
   return {
     clips,
+    clipDays,
+    typeCounts,
     search,
     setSearch,
     typeFilter,
     setTypeFilter,
+    sort,
+    setSort,
     loading,
+    monitorError,
+    monitoringPaused,
+    setPaused,
     deleteClip,
+    deleteClips,
     togglePin,
+    fetchUrlTitle,
+    setClipExpiry,
+    updateClipContent,
+    bumpClip,
+    reorderPins,
     refresh: fetchClips,
   };
 }
@@ -2,11 +2,49 @@ export interface ClipItem {
   id: string;
   content: string;
   content_hash: string;
-  clip_type: "text" | "code" | "url" | "image";
+  clip_type: "text" | "code" | "url" | "image" | "gif" | "color" | "json" | "xml" | "email" | "phone";
   source_app: string | null;
   image_path: string | null;
   is_pinned: boolean;
   created_at: string;
+  image_width: number | null;
+  image_height: number | null;
+  image_bytes: number | null;
+  code_language: string | null;
+  deleted_at: string | null;
+  is_favorite: boolean;
+  phash: number | null;
+  original_length: number | null;
+  /** 列表接口只返回摘要，完整内容需通过 get_clip_content 获取 */
+  preview: string | null;
+  content_length: number | null;
+  pin_order: number | null;
+  /** 链接条目的网页标题，未抓取时为 null */
+  title: string | null;
+  /** 到期时间，到期后自动删除（置顶也会删除） */
+  expires_at: string | null;
+  /** 复制次数，重复复制合并时递增 */
+  copy_count: number;
+  age_seconds: number | null;
+  /** 搜索词在 preview 中的位置，[起, 止) 按 UTF-16 计，可直接用于 slice；没有搜索时为 null */
+  match_ranges?: [number, number][] | null;
+}
+
+/** 后台定期清理完成时的删除数量 */
+export interface CleanupDone {
+  old: number;
+  over_limit: number;
+  /** 按保留天数删除的条目按类型计数 */
+  by_type: Record<string, number>;
+}
+
+/** 数据存储位置和占用空间（字节） */
+export interface StorageInfo {
+  app_data_dir: string;
+  db_path: string;
+  images_dir: string;
+  db_bytes: number;
+  images_bytes: number;
 }
 
 export interface Template {
@@ -15,23 +53,139 @@ export interface Template {
   prompt: string;
   category: string;
   shortcut: string | null;
+  provider_id?: string | null;
+  /** 对 AI 最终输出的后处理，缺省为 none */
+  post_process?: PostProcess;
+}
+
+export type PostProcess = "none" | "trim" | "json_pretty" | "strip_markdown";
+
+export interface TemplateCategory {
+  name: string;
+  count: number;
+  position: number | null;
 }
 
 export interface AiProvider {
   id: string;
   name: string;
-  kind: "openai" | "claude" | "ollama" | "kimi" | "minimax";
+  kind: "openai" | "claude" | "ollama" | "kimi" | "minimax" | "azure";
   endpoint: string;
   model: string;
   api_key: string;
   is_default: boolean;
+  connect_timeout_secs?: number | null;
+  read_timeout_secs?: number | null;
+  proxy_url?: string | null;
+  extra_headers?: Record<string, string> | null;
+  path_template?: string | null;
+  max_concurrency?: number | null;
+  /** Azure OpenAI 的 api-version，未设置时使用后端默认版本 */
+  api_version?: string | null;
+}
+
+export interface LabelCount {
+  label: string;
+  count: number;
+}
+
+export interface ClipStats {
+  total: number;
+  by_type: LabelCount[];
+  per_day: LabelCount[];
+  top_sources: LabelCount[];
+  image_bytes: number;
+}
+
+export interface UsageStat {
+  day: string;
+  provider_id: string | null;
+  provider_name: string | null;
+  generations: number;
+  prompt_tokens: number;
+  completion_tokens: number;
 }
 
 export interface StreamChunk {
   content: string;
+  /** reasoning 为模型的推理过程，不计入最终结果 */
+  kind: "content" | "reasoning";
   done: boolean;
+  seq: number;
+  request_id: string | null;
 }
 
+/** clipboard-changed 事件负载，完整内容需通过 get_clips / get_clip_content 获取 */
+export interface ClipChanged {
+  id: string;
+  clip_type: string;
+  source_app: string | null;
+  created_at: string;
+}
+
+/** quick-template 事件负载：模板 id 和快捷键触发时复制到的文本 */
+export interface QuickTemplatePayload {
+  template_id: string;
+  text: string;
+}
+
+/** ai-queued 事件负载：请求在等待供应商的并发名额 */
+export interface AiQueuedEvent {
+  request_id: string | null;
+  provider_id: string;
+}
+
+/** 逐条生成中单个条目的结果，失败时 content 为 null */
+export interface BatchItemResult {
+  clip_id: string;
+  content: string | null;
+  error: string | null;
+}
+
+/** ai-batch-progress 事件负载：每完成一个条目发送一次 */
+export interface BatchProgress {
+  request_id: string | null;
+  index: number;
+  done: number;
+  total: number;
+  result: BatchItemResult;
+}
+
+/** 常用服务的供应商预设，第一个模型为默认 */
+export interface ProviderPreset {
+  kind: AiProvider["kind"];
+  label: string;
+  endpoint: string;
+  models: string[];
+  needs_key: boolean;
+}
+
+/** 某一天（本地时区，YYYY-MM-DD）的条目数 */
+export interface ClipDay {
+  day: string;
+  count: number;
+}
+
+/** 内联生成的输出方式：paste 分批粘贴，type 模拟按键输入 */
+export type InlineTarget = "paste" | "type";
+
 export type ClipTypeFilter = "all" | "text" | "code" | "url" | "image";
 
 export type AppView = "history" | "generate" | "settings";
+
+/** 后端命令返回的错误，code 用于区分错误类别 */
+export interface AppError {
+  code:
+    | "NoProvider"
+    | "NotFound"
+    | "InvalidInput"
+    | "DbLocked"
+    | "Database"
+    | "Timeout"
+    | "Network"
+    | "Api"
+    | "InvalidResponse"
+    | "Io"
+    | "Other";
+  message: string;
+}